@@ -1,41 +1,75 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coins, to_binary, Addr, BankMsg, Binary, BlockInfo, CosmosMsg, Decimal, Delegation, Deps,
-    DepsMut, Empty, Env, GovMsg, MessageInfo, Order, Response, StdError, StdResult, Timestamp,
-    VoteOption, WasmMsg,
+    coins, from_json, to_json_binary, Addr, BankMsg, Binary, BlockInfo, CosmosMsg, Decimal,
+    Delegation, Deps, DepsMut, Empty, Env, GovMsg, MessageInfo, Order, Response, StdError,
+    StdResult, Storage, Timestamp, Uint128, VoteOption, WasmMsg,
 };
 use cw2::set_contract_version;
+use cw20::Cw20ReceiveMsg;
 use cw3::{
     Ballot, Proposal, ProposalListResponse, ProposalResponse, Status, Vote, VoteInfo,
     VoteListResponse, Votes,
 };
+use cw_storage_plus::Bound;
 use cw_utils::{Threshold, ThresholdError};
 
-use crate::data_structure::EmptyStruct;
+use crate::data_structure::{
+    raw_amounts, validate_deposit, validate_reward_split, validate_vesting_amounts,
+    validate_vesting_schedule, validate_weighted_recipients, Clawback, DelegationCap, EmptyStruct,
+    LinearVestingState, MilestoneState, PendingWithdrawal, RewardPolicy, ScheduleAmendment,
+    SchedulePoint, ScheduleUnit, Tranche, VestingScheduleSpec, WeightedRecipient, WithdrawalKind,
+    WithdrawalRecord,
+};
 use crate::error::ContractError;
 use crate::msg::{
-    AdminListResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, OpListResponse, QueryMsg,
-    SeiQueryWrapper, ShowConfigResponse, ShowInfoResponse, ShowTotalVestedResponse,
+    AdminListResponse, AllowedValidatorListResponse, ExecuteMsg, InstantiateMsg, MigrateMsg,
+    MilestoneEntry, MilestonesResponse, NextUnlockResponse, OpListResponse, PendingWithdrawalEntry,
+    PendingWithdrawalsResponse, PortfolioDelegation, PortfolioResponse, PrincipalLedgerResponse,
+    QueryMsg, ReceiveMsg, ReconcileResponse, Redelegation, RewardRecipientEntry,
+    RewardRecipientsResponse, SeiQueryWrapper, ShowConfigResponse, ShowExpandedScheduleResponse,
+    ShowInfoResponse, ShowTotalVestedResponse, TrancheListResponse, TrancheReconciliation,
+    VestingEvent, VestingScheduleResponse, WithdrawalEntry, WithdrawalHistoryResponse,
 };
 use crate::permission::{authorize_admin, authorize_op, authorize_self_call};
+use crate::schedule::{expand_schedule_spec, timestamp_from_rfc3339};
 use crate::staking::{
     delegate, get_all_delegated_validators, get_delegation_rewards, get_unbonding_balance,
-    redelegate, undelegate, withdraw_delegation_rewards,
+    get_unbonding_entries, redelegate, undelegate, withdraw_rewards,
 };
 use crate::state::{
-    get_number_of_admins, next_proposal_id, ADMINS, ADMIN_VOTING_THRESHOLD, BALLOTS, DENOM,
-    MAX_VOTING_PERIOD, OPS, PROPOSALS, STAKING_REWARD_ADDRESS, TOTAL_AMOUNT,
-    UNLOCK_DISTRIBUTION_ADDRESS, VESTING_AMOUNTS, VESTING_TIMESTAMPS, WITHDRAWN_LOCKED,
+    get_number_of_admins, get_tranche_ids, load_schedule, next_proposal_id,
+    queue_pending_withdrawal, record_delegation, record_redelegation, record_unbonding,
+    record_undelegation, record_withdrawal, save_schedule, sum_over_native_tranches,
+    total_delegated_principal, total_pending_withdrawals, total_unbonding_principal,
+    validation_policy, ADMINS, ADMIN_VOTING_THRESHOLD, ALLOWED_VALIDATORS, BALLOTS,
+    COMPOUNDED_REWARDS, CW20_TOKEN, DEFAULT_TRANCHE_ID, DELEGATED_PRINCIPAL, DELEGATION_CAP, DENOM,
+    LEGACY_TOTAL_AMOUNT, LEGACY_TRANCHE_VESTING_AMOUNTS, LEGACY_TRANCHE_VESTING_TIMESTAMPS,
+    LEGACY_UNLOCK_DISTRIBUTION_ADDRESS, LEGACY_VESTING_AMOUNTS, LEGACY_VESTING_TIMESTAMPS,
+    LEGACY_WITHDRAWN_LOCKED, LEGACY_WITHDRAWN_UNLOCKED, LINEAR_VESTING, MAX_VOTING_PERIOD,
+    MILESTONES, OPS, PENDING_WITHDRAWALS, PERMISSIONLESS_RELEASE, PROPOSALS, REWARD_POLICY,
+    SCHEDULE_CURSOR, SCHEDULE_UNIT, STAKING_REWARD_ADDRESS, STAKING_REWARD_SPLIT, TOTAL_AMOUNT,
+    UNBONDING_PERIOD, UNBONDING_PRINCIPAL, UNDELEGATE_FOR_WITHDRAWALS, UNLOCK_DISTRIBUTION_ADDRESS,
+    UNLOCK_DISTRIBUTION_SPLIT, VALIDATION_POLICY, VESTING_SCHEDULE, WITHDRAWALS,
+    WITHDRAWALS_BY_KIND, WITHDRAWN_LOCKED, WITHDRAWN_REWARDS_BY_RECIPIENT,
     WITHDRAWN_STAKING_REWARDS, WITHDRAWN_UNLOCKED,
 };
-use crate::vesting::{collect_vested, distribute_vested, total_vested_amount};
+use crate::vesting::{
+    amend_schedule, clawback_after, clawback_amount, clawback_lapsed_milestones, collect_vested,
+    distribute_vested, merge_schedule, next_unlock, remaining_amount, remove_milestones,
+    schedule_now, schedule_unit, split_by_weight, total_vested_amount, tranche_denom, transfer_msg,
+    unlocked_payouts,
+};
 use semver::Version;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:sei-gringotts";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// pagination of list queries
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
 pub fn validate_migration(
     deps: Deps<SeiQueryWrapper>,
     contract_name: &str,
@@ -75,6 +109,10 @@ pub fn migrate(
         return migrate_109_handler(deps, env);
     }
 
+    if CONTRACT_VERSION == "0.2.0" {
+        return migrate_200_handler(deps, env);
+    }
+
     Ok(Response::default())
 }
 
@@ -123,7 +161,7 @@ fn migrate_105_handler(
             Timestamp::from_nanos(1815609600000000000),
             Timestamp::from_nanos(1818288000000000000),
         ];
-        VESTING_TIMESTAMPS.save(deps.storage, &timestamps)?;
+        LEGACY_VESTING_TIMESTAMPS.save(deps.storage, &timestamps)?;
         return Ok(Response::default());
     }
 
@@ -193,8 +231,8 @@ fn migrate_105_handler(
             Timestamp::from_nanos(1881446400000000000),
         ];
         let amounts: Vec<u128> = vec![20000000000000; 60];
-        VESTING_TIMESTAMPS.save(deps.storage, &timestamps)?;
-        VESTING_AMOUNTS.save(deps.storage, &amounts)?;
+        LEGACY_VESTING_TIMESTAMPS.save(deps.storage, &timestamps)?;
+        LEGACY_VESTING_AMOUNTS.save(deps.storage, &amounts)?;
         return Ok(Response::default());
     }
 
@@ -208,6 +246,76 @@ fn migrate_109_handler(
     Ok(Response::new())
 }
 
+fn migrate_200_handler(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+) -> Result<Response, ContractError> {
+    // move the single-tranche state into the default tranche
+    if let Some(vesting_timestamps) = LEGACY_VESTING_TIMESTAMPS.may_load(deps.storage)? {
+        let vesting_amounts = LEGACY_VESTING_AMOUNTS.load(deps.storage)?;
+        let total_amount = LEGACY_TOTAL_AMOUNT.load(deps.storage)?;
+        let unlock_distribution_address = LEGACY_UNLOCK_DISTRIBUTION_ADDRESS.load(deps.storage)?;
+        let withdrawn_unlocked = LEGACY_WITHDRAWN_UNLOCKED.load(deps.storage)?;
+        let withdrawn_locked = LEGACY_WITHDRAWN_LOCKED.load(deps.storage)?;
+        save_schedule(
+            deps.storage,
+            DEFAULT_TRANCHE_ID,
+            &vesting_timestamps,
+            &vesting_amounts,
+        )?;
+        TOTAL_AMOUNT.save(deps.storage, DEFAULT_TRANCHE_ID, &total_amount)?;
+        UNLOCK_DISTRIBUTION_ADDRESS.save(
+            deps.storage,
+            DEFAULT_TRANCHE_ID,
+            &unlock_distribution_address,
+        )?;
+        WITHDRAWN_UNLOCKED.save(deps.storage, DEFAULT_TRANCHE_ID, &withdrawn_unlocked)?;
+        WITHDRAWN_LOCKED.save(deps.storage, DEFAULT_TRANCHE_ID, &withdrawn_locked)?;
+        LEGACY_VESTING_TIMESTAMPS.remove(deps.storage);
+        LEGACY_VESTING_AMOUNTS.remove(deps.storage);
+        LEGACY_TOTAL_AMOUNT.remove(deps.storage);
+        LEGACY_UNLOCK_DISTRIBUTION_ADDRESS.remove(deps.storage);
+        LEGACY_WITHDRAWN_UNLOCKED.remove(deps.storage);
+        LEGACY_WITHDRAWN_LOCKED.remove(deps.storage);
+    }
+    // move per-tranche schedule vectors into indexed schedule storage
+    let tranche_ids = LEGACY_TRANCHE_VESTING_TIMESTAMPS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+    for tranche_id in tranche_ids {
+        let vesting_timestamps =
+            LEGACY_TRANCHE_VESTING_TIMESTAMPS.load(deps.storage, &tranche_id)?;
+        let vesting_amounts = LEGACY_TRANCHE_VESTING_AMOUNTS.load(deps.storage, &tranche_id)?;
+        save_schedule(
+            deps.storage,
+            &tranche_id,
+            &vesting_timestamps,
+            &vesting_amounts,
+        )?;
+        LEGACY_TRANCHE_VESTING_TIMESTAMPS.remove(deps.storage, &tranche_id);
+        LEGACY_TRANCHE_VESTING_AMOUNTS.remove(deps.storage, &tranche_id);
+    }
+    // seed the principal ledger: stake and unbonding balances predating it are all principal
+    if DELEGATED_PRINCIPAL.is_empty(deps.storage) && UNBONDING_PRINCIPAL.is_empty(deps.storage) {
+        let delegations = delegated_amounts(deps.as_ref(), &env)?;
+        let unbonding_entries = get_unbonding_entries(deps.as_ref(), env.clone())?;
+        for (validator, amount) in delegations {
+            record_delegation(deps.storage, &validator, amount)?;
+        }
+        for entry in unbonding_entries {
+            let completion_time =
+                timestamp_from_rfc3339(&entry.completion_time).ok_or_else(|| {
+                    StdError::generic_err(format!(
+                        "invalid unbonding completion time {}",
+                        entry.completion_time
+                    ))
+                })?;
+            record_unbonding(deps.storage, entry.balance.u128(), completion_time)?;
+        }
+    }
+    Ok(Response::new())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut<SeiQueryWrapper>,
@@ -226,7 +334,14 @@ pub fn instantiate(
             ThresholdError::InvalidThreshold {},
         ));
     }
-    msg.tranche.validate(env, info.funds)?;
+    let tranche = msg.tranche.with_expanded_schedule()?;
+    if tranche.cw20_token.is_some() {
+        return Err(ContractError::InvalidTranche(
+            "the instantiation tranche must vest a native denom".to_string(),
+        ));
+    }
+    let validation_policy = msg.validation_policy.unwrap_or_default();
+    tranche.validate(env, info.funds, &validation_policy)?;
 
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     for admin in msg.admins.iter() {
@@ -235,19 +350,9 @@ pub fn instantiate(
     for op in msg.ops.iter() {
         OPS.save(deps.storage, op, &EmptyStruct {})?;
     }
-    DENOM.save(deps.storage, &msg.tranche.denom)?;
-    VESTING_TIMESTAMPS.save(deps.storage, &msg.tranche.vesting_timestamps)?;
-    VESTING_AMOUNTS.save(deps.storage, &msg.tranche.vesting_amounts)?;
-    let total: u128 = msg.tranche.vesting_amounts.iter().sum();
-    TOTAL_AMOUNT.save(deps.storage, &total)?;
-    UNLOCK_DISTRIBUTION_ADDRESS.save(
-        deps.storage,
-        &msg.tranche.unlocked_token_distribution_address,
-    )?;
-    STAKING_REWARD_ADDRESS.save(
-        deps.storage,
-        &msg.tranche.staking_reward_distribution_address,
-    )?;
+    DENOM.save(deps.storage, &tranche.denom)?;
+    STAKING_REWARD_ADDRESS.save(deps.storage, &tranche.staking_reward_distribution_address)?;
+    save_tranche(deps.storage, DEFAULT_TRANCHE_ID, tranche)?;
     MAX_VOTING_PERIOD.save(deps.storage, &msg.max_voting_period)?;
     ADMIN_VOTING_THRESHOLD.save(
         deps.storage,
@@ -256,11 +361,82 @@ pub fn instantiate(
         },
     )?;
     WITHDRAWN_STAKING_REWARDS.save(deps.storage, &0)?;
-    WITHDRAWN_UNLOCKED.save(deps.storage, &0)?;
-    WITHDRAWN_LOCKED.save(deps.storage, &0)?;
+    VALIDATION_POLICY.save(deps.storage, &validation_policy)?;
+    if let Some(unbonding_period) = msg.unbonding_period {
+        UNBONDING_PERIOD.save(deps.storage, &unbonding_period)?;
+    }
     Ok(Response::default())
 }
 
+fn assert_tranche_exists(storage: &dyn Storage, tranche_id: &str) -> Result<(), ContractError> {
+    if !TOTAL_AMOUNT.has(storage, tranche_id) {
+        return Err(ContractError::TrancheNotFound(tranche_id.to_string()));
+    }
+    Ok(())
+}
+
+// stores the vesting state of a tranche that has already been validated
+fn save_tranche(
+    storage: &mut dyn Storage,
+    tranche_id: &str,
+    tranche: Tranche,
+) -> Result<(), ContractError> {
+    // the schedule is stored in the tranche's unit
+    let schedule_unit = tranche.schedule_unit();
+    if schedule_unit != ScheduleUnit::Time {
+        SCHEDULE_UNIT.save(storage, tranche_id, &schedule_unit)?;
+    }
+    save_schedule(
+        storage,
+        tranche_id,
+        &tranche.vesting_timestamps,
+        &raw_amounts(&tranche.vesting_amounts),
+    )?;
+    let mut total = Uint128::zero();
+    for amount in tranche.vesting_amounts.iter() {
+        total = total.checked_add(*amount).map_err(StdError::from)?;
+    }
+    for milestone in tranche.milestones() {
+        MILESTONES.save(
+            storage,
+            (tranche_id, &milestone.milestone_id),
+            &MilestoneState {
+                amount: milestone.amount,
+                deadline: milestone
+                    .deadline
+                    .map(|deadline| schedule_unit.point(deadline)),
+                reached: false,
+            },
+        )?;
+        total = total
+            .checked_add(milestone.amount)
+            .map_err(StdError::from)?;
+    }
+    TOTAL_AMOUNT.save(storage, tranche_id, &total.u128())?;
+    if let Some(linear_vesting) = tranche.linear_vesting {
+        LINEAR_VESTING.save(
+            storage,
+            tranche_id,
+            &LinearVestingState {
+                cliff: linear_vesting.cliff.map(|cliff| schedule_unit.point(cliff)),
+                segment_start: schedule_unit.point(linear_vesting.start),
+                segment_amount: tranche.vesting_amounts[0],
+            },
+        )?;
+    }
+    if let Some(cw20_token) = tranche.cw20_token {
+        CW20_TOKEN.save(storage, tranche_id, &cw20_token)?;
+    }
+    UNLOCK_DISTRIBUTION_ADDRESS.save(
+        storage,
+        tranche_id,
+        &tranche.unlocked_token_distribution_address,
+    )?;
+    WITHDRAWN_UNLOCKED.save(storage, tranche_id, &0)?;
+    WITHDRAWN_LOCKED.save(storage, tranche_id, &0)?;
+    Ok(())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut<SeiQueryWrapper>,
@@ -270,21 +446,54 @@ pub fn execute(
 ) -> Result<Response<Empty>, ContractError> {
     match msg {
         ExecuteMsg::Delegate { validator, amount } => {
-            execute_delegate(deps.as_ref(), info, validator, amount)
+            execute_delegate(deps, env, info, validator, amount.u128())
         }
         ExecuteMsg::Redelegate {
             src_validator,
             dst_validator,
             amount,
-        } => execute_redelegate(deps.as_ref(), info, src_validator, dst_validator, amount),
+        } => execute_redelegate(deps, env, info, src_validator, dst_validator, amount.u128()),
         ExecuteMsg::Undelegate { validator, amount } => {
-            execute_undelegate(deps.as_ref(), info, validator, amount)
+            execute_undelegate(deps, env, info, validator, amount.u128())
+        }
+        ExecuteMsg::DelegateMany { delegations } => {
+            execute_delegate_many(deps, env, info, delegations)
+        }
+        ExecuteMsg::UndelegateMany { undelegations } => {
+            execute_undelegate_many(deps, env, info, undelegations)
+        }
+        ExecuteMsg::RedelegateMany { redelegations } => {
+            execute_redelegate_many(deps, env, info, redelegations)
         }
         ExecuteMsg::InitiateWithdrawUnlocked { amount } => {
-            execute_initiate_withdraw_unlocked(deps, env, info, amount)
+            execute_initiate_withdraw_unlocked(deps, env, info, DEFAULT_TRANCHE_ID, amount.u128())
+        }
+        ExecuteMsg::InitiateWithdrawTrancheUnlocked { tranche_id, amount } => {
+            execute_initiate_withdraw_unlocked(deps, env, info, &tranche_id, amount.u128())
         }
+        ExecuteMsg::AddTranche {
+            tranche_id,
+            tranche,
+        } => execute_add_tranche(deps, env, info, tranche_id, tranche),
+        ExecuteMsg::Receive(wrapper) => execute_receive(deps, env, info, wrapper),
+        ExecuteMsg::TopUp {
+            tranche_id,
+            vesting_timestamps,
+            vesting_amounts,
+        } => execute_top_up(
+            deps,
+            env,
+            info,
+            tranche_id.unwrap_or_else(|| DEFAULT_TRANCHE_ID.to_string()),
+            vesting_timestamps,
+            vesting_amounts,
+        ),
         ExecuteMsg::UpdateOp { op, remove } => execute_update_op(deps, info, op, remove),
         ExecuteMsg::InitiateWithdrawReward {} => execute_initiate_withdraw_reward(deps, env, info),
+        ExecuteMsg::ReleaseVested {} => execute_release_vested(deps, env, info),
+        ExecuteMsg::ClaimPendingWithdrawals {} => {
+            execute_claim_pending_withdrawals(deps, env, info)
+        }
         ExecuteMsg::ProposeUpdateAdmin { admin, remove } => {
             execute_propose_update_admin(deps, env, info, admin, remove)
         }
@@ -296,6 +505,26 @@ pub fn execute(
             info,
             unlocked_distribution_address,
         ),
+        ExecuteMsg::ProposeUpdateTrancheUnlockedDistributionAddress {
+            tranche_id,
+            unlocked_distribution_address,
+        } => execute_propose_update_tranche_unlocked_distribution_address(
+            deps,
+            env,
+            info,
+            tranche_id,
+            unlocked_distribution_address,
+        ),
+        ExecuteMsg::ProposeUpdateUnlockedDistributionSplit {
+            tranche_id,
+            recipients,
+        } => execute_propose_update_unlocked_distribution_split(
+            deps,
+            env,
+            info,
+            tranche_id.unwrap_or_else(|| DEFAULT_TRANCHE_ID.to_string()),
+            recipients,
+        ),
         ExecuteMsg::ProposeUpdateStakingRewardDistributionAddress {
             staking_reward_distribution_address,
         } => execute_propose_update_staking_reward_distribution_address(
@@ -307,6 +536,66 @@ pub fn execute(
         ExecuteMsg::ProposeEmergencyWithdraw { dst } => {
             execute_propose_emergency_withdraw(deps, env, info, dst)
         }
+        ExecuteMsg::ProposeClawback {
+            tranche_id,
+            dst,
+            clawback,
+        } => execute_propose_clawback(
+            deps,
+            env,
+            info,
+            tranche_id.unwrap_or_else(|| DEFAULT_TRANCHE_ID.to_string()),
+            dst,
+            clawback,
+        ),
+        ExecuteMsg::ProposeAmendSchedule {
+            tranche_id,
+            amendment,
+        } => execute_propose_amend_schedule(
+            deps,
+            env,
+            info,
+            tranche_id.unwrap_or_else(|| DEFAULT_TRANCHE_ID.to_string()),
+            amendment,
+        ),
+        ExecuteMsg::ProposeUpdatePermissionlessRelease { enabled } => {
+            execute_propose_update_permissionless_release(deps, env, info, enabled)
+        }
+        ExecuteMsg::ProposeUpdateUndelegateForWithdrawals { enabled } => {
+            execute_propose_update_undelegate_for_withdrawals(deps, env, info, enabled)
+        }
+        ExecuteMsg::ProposeUpdateAllowedValidator { validator, remove } => {
+            execute_propose_update_allowed_validator(deps, env, info, validator, remove)
+        }
+        ExecuteMsg::ProposeUpdateDelegationCap { cap } => {
+            execute_propose_update_delegation_cap(deps, env, info, cap)
+        }
+        ExecuteMsg::ProposeUpdateRewardPolicy { policy } => {
+            execute_propose_update_reward_policy(deps, env, info, policy)
+        }
+        ExecuteMsg::ProposeUpdateStakingRewardSplit { recipients } => {
+            execute_propose_update_staking_reward_split(deps, env, info, recipients)
+        }
+        ExecuteMsg::ProposeMilestoneReached {
+            tranche_id,
+            milestone_id,
+        } => execute_propose_milestone_reached(
+            deps,
+            env,
+            info,
+            tranche_id.unwrap_or_else(|| DEFAULT_TRANCHE_ID.to_string()),
+            milestone_id,
+        ),
+        ExecuteMsg::ProposeCorrectTotalAmount {
+            tranche_id,
+            explanation,
+        } => execute_propose_correct_total_amount(
+            deps,
+            env,
+            info,
+            tranche_id.unwrap_or_else(|| DEFAULT_TRANCHE_ID.to_string()),
+            explanation,
+        ),
         ExecuteMsg::ProposeGovVote {
             gov_proposal_id,
             gov_vote,
@@ -324,8 +613,29 @@ pub fn execute(
             deps,
             env,
             info,
+            DEFAULT_TRANCHE_ID,
+            unlocked_distribution_address,
+        ),
+        ExecuteMsg::InternalUpdateTrancheUnlockedDistributionAddress {
+            tranche_id,
+            unlocked_distribution_address,
+        } => execute_internal_update_unlocked_distribution_address(
+            deps,
+            env,
+            info,
+            &tranche_id,
             unlocked_distribution_address,
         ),
+        ExecuteMsg::InternalUpdateUnlockedDistributionSplit {
+            tranche_id,
+            recipients,
+        } => execute_internal_update_unlocked_distribution_split(
+            deps,
+            env,
+            info,
+            &tranche_id,
+            recipients,
+        ),
         ExecuteMsg::InternalUpdateStakingRewardDistributionAddress {
             staking_reward_distribution_address,
         } => execute_internal_update_staking_reward_distribution_address(
@@ -334,187 +644,922 @@ pub fn execute(
             info,
             staking_reward_distribution_address,
         ),
-        ExecuteMsg::InternalWithdrawLocked { dst } => {
-            execute_internal_withdraw_locked(deps, env, info, dst)
+        ExecuteMsg::InternalUpdatePermissionlessRelease { enabled } => {
+            execute_internal_update_permissionless_release(deps, env, info, enabled)
+        }
+        ExecuteMsg::InternalUpdateUndelegateForWithdrawals { enabled } => {
+            execute_internal_update_undelegate_for_withdrawals(deps, env, info, enabled)
+        }
+        ExecuteMsg::InternalUpdateAllowedValidator { validator, remove } => {
+            execute_internal_update_allowed_validator(deps, env, info, validator, remove)
+        }
+        ExecuteMsg::InternalUpdateDelegationCap { cap } => {
+            execute_internal_update_delegation_cap(deps, env, info, cap)
+        }
+        ExecuteMsg::InternalUpdateRewardPolicy { policy } => {
+            execute_internal_update_reward_policy(deps, env, info, policy)
+        }
+        ExecuteMsg::InternalUpdateStakingRewardSplit { recipients } => {
+            execute_internal_update_staking_reward_split(deps, env, info, recipients)
+        }
+        ExecuteMsg::InternalWithdrawLocked { dst, proposer } => {
+            execute_internal_withdraw_locked(deps, env, info, dst, proposer)
+        }
+        ExecuteMsg::InternalClawback {
+            tranche_id,
+            dst,
+            clawback,
+            proposer,
+        } => execute_internal_clawback(deps, env, info, tranche_id, dst, clawback, proposer),
+        ExecuteMsg::InternalAmendSchedule {
+            tranche_id,
+            amendment,
+            proposer,
+        } => execute_internal_amend_schedule(deps, env, info, tranche_id, amendment, proposer),
+        ExecuteMsg::InternalMilestoneReached {
+            tranche_id,
+            milestone_id,
+        } => execute_internal_milestone_reached(deps, env, info, tranche_id, milestone_id),
+        ExecuteMsg::InternalCorrectTotalAmount {
+            tranche_id,
+            total_amount,
+        } => {
+            execute_internal_correct_total_amount(deps, env, info, tranche_id, total_amount.u128())
         }
     }
 }
 
 fn execute_delegate(
-    deps: Deps<SeiQueryWrapper>,
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
     info: MessageInfo,
     validator: String,
     amount: u128,
 ) -> Result<Response<Empty>, ContractError> {
     authorize_op(deps.storage, info.sender)?;
+    assert_validator_allowed(deps.storage, &validator)?;
+    assert_within_delegation_cap(deps.as_ref(), &env, std::iter::once((&validator, amount)))?;
+    assert_within_liquid_principal(deps.storage, &env, amount)?;
     let denom = DENOM.load(deps.storage)?;
+    record_delegation(deps.storage, &validator, amount)?;
     let mut response = Response::new();
     response = delegate(response, validator, amount, denom);
     Ok(response)
 }
 
 fn execute_redelegate(
-    deps: Deps<SeiQueryWrapper>,
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
     info: MessageInfo,
     src_validator: String,
     dst_validator: String,
     amount: u128,
 ) -> Result<Response<Empty>, ContractError> {
     authorize_op(deps.storage, info.sender)?;
+    assert_validator_allowed(deps.storage, &dst_validator)?;
+    assert_within_delegation_cap(
+        deps.as_ref(),
+        &env,
+        std::iter::once((&dst_validator, amount)),
+    )?;
+    // redelegations move stake, not liquid funds. Only principal held by the source validator is
+    // booked to the destination.
+    assert_delegated(
+        deps.as_ref(),
+        &env,
+        std::iter::once((&src_validator, amount)),
+    )?;
     let denom = DENOM.load(deps.storage)?;
+    record_redelegation(deps.storage, &src_validator, &dst_validator, amount)?;
     let mut response = Response::new();
     response = redelegate(response, src_validator, dst_validator, amount, denom);
     Ok(response)
 }
 
 fn execute_undelegate(
-    deps: Deps<SeiQueryWrapper>,
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
     info: MessageInfo,
     validator: String,
     amount: u128,
 ) -> Result<Response<Empty>, ContractError> {
     authorize_op(deps.storage, info.sender)?;
+    assert_delegated(deps.as_ref(), &env, std::iter::once((&validator, amount)))?;
     let denom = DENOM.load(deps.storage)?;
+    record_undelegation(deps.storage, &validator, amount, env.block.time)?;
     let mut response = Response::new();
     response = undelegate(response, validator, amount, denom);
     Ok(response)
 }
 
-fn execute_initiate_withdraw_unlocked(
+fn execute_delegate_many(
     deps: DepsMut<SeiQueryWrapper>,
     env: Env,
     info: MessageInfo,
-    amount: u128,
+    delegations: Vec<(String, Uint128)>,
 ) -> Result<Response<Empty>, ContractError> {
     authorize_op(deps.storage, info.sender)?;
-    let vested_amount = collect_vested(deps.storage, env.block.time, amount)?;
-    WITHDRAWN_UNLOCKED.update(deps.storage, |old| -> Result<u128, StdError> {
-        Ok(old + vested_amount)
-    })?;
-    distribute_vested(deps.storage, vested_amount, Response::new())
+    let total = validate_batch_amounts(delegations.iter().map(|(_, amount)| *amount))?;
+    for (validator, _) in delegations.iter() {
+        assert_validator_allowed(deps.storage, validator)?;
+    }
+    assert_within_delegation_cap(
+        deps.as_ref(),
+        &env,
+        delegations
+            .iter()
+            .map(|(validator, amount)| (validator, amount.u128())),
+    )?;
+    assert_within_liquid_principal(deps.storage, &env, total)?;
+    let denom = DENOM.load(deps.storage)?;
+    let mut response = Response::new()
+        .add_attribute("action", "delegate_many")
+        .add_attribute("total", total.to_string());
+    for (validator, amount) in delegations {
+        record_delegation(deps.storage, &validator, amount.u128())?;
+        response = delegate(response, validator.clone(), amount.u128(), denom.clone())
+            .add_attribute("validator", validator)
+            .add_attribute("amount", amount);
+    }
+    Ok(response)
 }
 
-fn execute_initiate_withdraw_reward(
+fn execute_undelegate_many(
     deps: DepsMut<SeiQueryWrapper>,
     env: Env,
     info: MessageInfo,
+    undelegations: Vec<(String, Uint128)>,
 ) -> Result<Response<Empty>, ContractError> {
     authorize_op(deps.storage, info.sender)?;
-    let mut response = Response::new();
-    let mut total = calculate_withdrawn_rewards(deps.as_ref(), env.clone())?;
-    if total > 0 {
-        response = response.add_message(BankMsg::Send {
-            to_address: STAKING_REWARD_ADDRESS.load(deps.storage)?.to_string(),
-            amount: coins(total, DENOM.load(deps.storage)?),
-        });
+    let total = validate_batch_amounts(undelegations.iter().map(|(_, amount)| *amount))?;
+    assert_delegated(
+        deps.as_ref(),
+        &env,
+        undelegations
+            .iter()
+            .map(|(validator, amount)| (validator, amount.u128())),
+    )?;
+    let denom = DENOM.load(deps.storage)?;
+    let mut response = Response::new()
+        .add_attribute("action", "undelegate_many")
+        .add_attribute("total", total.to_string());
+    for (validator, amount) in undelegations {
+        record_undelegation(deps.storage, &validator, amount.u128(), env.block.time)?;
+        response = undelegate(response, validator.clone(), amount.u128(), denom.clone())
+            .add_attribute("validator", validator)
+            .add_attribute("amount", amount);
     }
-    for validator in get_all_delegated_validators(deps.as_ref(), env.clone())? {
-        let withdrawable_amount =
-            get_delegation_rewards(deps.as_ref(), env.clone(), validator.clone())?;
-        response =
-            withdraw_delegation_rewards(deps.as_ref(), response, validator, withdrawable_amount)?;
-        total += withdrawable_amount;
-    }
-    WITHDRAWN_STAKING_REWARDS.update(deps.storage, |old| -> Result<u128, StdError> {
-        Ok(old + total)
-    })?;
     Ok(response)
 }
 
-// rewards may be automatically withdrawn to contract's bank balance during redelegation/undelegation/delegating
-// more to the same validator. The amount of such withdrawn rewards, assuming no external deposit to the contract
-// is present, can be calculated as: bank balance - (total - withdrawn principal - staked - unbonding).
-// Note that because CW currently doesn't support querying unbonding amount, we will ignore unbonding amount in the
-// calculationg for now. This would make under-withdraw possible but still impossible to over-withdraw (which is bad).
-// To avoid under-withdraw, the operator can wait till there is no unbonding amount for the contract when executing
-// rewards withdrawal.
-fn calculate_withdrawn_rewards(
-    deps: Deps<SeiQueryWrapper>,
+fn execute_redelegate_many(
+    deps: DepsMut<SeiQueryWrapper>,
     env: Env,
-) -> Result<u128, ContractError> {
-    let bank_balance = deps
+    info: MessageInfo,
+    redelegations: Vec<Redelegation>,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_op(deps.storage, info.sender)?;
+    let total = validate_batch_amounts(redelegations.iter().map(|r| r.amount))?;
+    for redelegation in redelegations.iter() {
+        assert_validator_allowed(deps.storage, &redelegation.dst_validator)?;
+    }
+    assert_within_delegation_cap(
+        deps.as_ref(),
+        &env,
+        redelegations
+            .iter()
+            .map(|r| (&r.dst_validator, r.amount.u128())),
+    )?;
+    // see `execute_redelegate`
+    assert_delegated(
+        deps.as_ref(),
+        &env,
+        redelegations
+            .iter()
+            .map(|r| (&r.src_validator, r.amount.u128())),
+    )?;
+    let denom = DENOM.load(deps.storage)?;
+    let mut response = Response::new()
+        .add_attribute("action", "redelegate_many")
+        .add_attribute("total", total.to_string());
+    for redelegation in redelegations {
+        record_redelegation(
+            deps.storage,
+            &redelegation.src_validator,
+            &redelegation.dst_validator,
+            redelegation.amount.u128(),
+        )?;
+        response = redelegate(
+            response,
+            redelegation.src_validator.clone(),
+            redelegation.dst_validator.clone(),
+            redelegation.amount.u128(),
+            denom.clone(),
+        )
+        .add_attribute("src_validator", redelegation.src_validator)
+        .add_attribute("dst_validator", redelegation.dst_validator)
+        .add_attribute("amount", redelegation.amount);
+    }
+    Ok(response)
+}
+
+// delegations are booked as principal, so they cannot use withdrawn staking rewards
+fn assert_within_liquid_principal(
+    storage: &dyn Storage,
+    env: &Env,
+    amount: u128,
+) -> Result<(), ContractError> {
+    let liquid = liquid_principal(storage, env)?;
+    if amount > liquid {
+        return Err(ContractError::InvalidDelegation(format!(
+            "{} exceeds the liquid principal of {}",
+            amount, liquid
+        )));
+    }
+    Ok(())
+}
+
+fn validator_allowed(storage: &dyn Storage, validator: &str) -> bool {
+    ALLOWED_VALIDATORS.is_empty(storage) || ALLOWED_VALIDATORS.has(storage, validator)
+}
+
+fn assert_validator_allowed(storage: &dyn Storage, validator: &str) -> Result<(), ContractError> {
+    if validator_allowed(storage, validator) {
+        return Ok(());
+    }
+    Err(ContractError::ValidatorNotAllowed(validator.to_string()))
+}
+
+// checks that the amounts added to each validator, summed per validator, keep its delegation within
+// the delegation cap
+fn assert_within_delegation_cap<'a>(
+    deps: Deps<SeiQueryWrapper>,
+    env: &Env,
+    amounts: impl Iterator<Item = (&'a String, u128)>,
+) -> Result<(), ContractError> {
+    let cap = match DELEGATION_CAP.may_load(deps.storage)? {
+        Some(cap) => cap,
+        None => return Ok(()),
+    };
+    let limit = cap.limit(sum_over_native_tranches(deps.storage, &TOTAL_AMOUNT)?);
+    for (validator, amount) in sum_by_validator(amounts) {
+        let current = current_delegation(deps, env, validator)?;
+        if current + amount > limit {
+            return Err(ContractError::DelegationCapExceeded {
+                validator: validator.to_string(),
+                delegated: current + amount,
+                cap: limit,
+            });
+        }
+    }
+    Ok(())
+}
+
+// trims each amount to what may still be delegated to its validator under the delegation cap. Used
+// for compounding, which should not fail the reward withdrawal over a full validator.
+fn fit_within_delegation_cap(
+    deps: Deps<SeiQueryWrapper>,
+    env: &Env,
+    amounts: Vec<(String, u128)>,
+) -> StdResult<Vec<(String, u128)>> {
+    let cap = match DELEGATION_CAP.may_load(deps.storage)? {
+        Some(cap) => cap,
+        None => return Ok(amounts),
+    };
+    let limit = cap.limit(sum_over_native_tranches(deps.storage, &TOTAL_AMOUNT)?);
+    let mut fitted = vec![];
+    for (validator, amount) in amounts {
+        let room = limit.saturating_sub(current_delegation(deps, env, &validator)?);
+        let amount = std::cmp::min(amount, room);
+        if amount > 0 {
+            fitted.push((validator, amount));
+        }
+    }
+    Ok(fitted)
+}
+
+fn current_delegation(deps: Deps<SeiQueryWrapper>, env: &Env, validator: &str) -> StdResult<u128> {
+    let denom = DENOM.load(deps.storage)?;
+    Ok(deps
         .querier
-        .query_balance(env.contract.address.clone(), DENOM.load(deps.storage)?)?
-        .amount
-        .u128();
-    let total_locked: u128 = TOTAL_AMOUNT.load(deps.storage)?;
-    let withdrawn_principal =
-        WITHDRAWN_LOCKED.load(deps.storage)? + WITHDRAWN_UNLOCKED.load(deps.storage)?;
-    let staked: u128 = deps
+        .query_delegation(env.contract.address.clone(), validator)?
+        .filter(|delegation| delegation.amount.denom == denom)
+        .map(|delegation| delegation.amount.amount.u128())
+        .unwrap_or_default())
+}
+
+fn validate_batch_amounts(amounts: impl Iterator<Item = Uint128>) -> Result<u128, ContractError> {
+    let mut total = 0u128;
+    let mut count = 0;
+    for amount in amounts {
+        if amount.is_zero() {
+            return Err(ContractError::InvalidDelegation(
+                "amounts must be positive".to_string(),
+            ));
+        }
+        total = total
+            .checked_add(amount.u128())
+            .ok_or_else(|| ContractError::InvalidDelegation("amounts overflow".to_string()))?;
+        count += 1;
+    }
+    if count == 0 {
+        return Err(ContractError::InvalidDelegation(
+            "batch cannot be empty".to_string(),
+        ));
+    }
+    Ok(total)
+}
+
+// the contract's delegations of `DENOM`, by validator
+fn delegated_amounts(deps: Deps<SeiQueryWrapper>, env: &Env) -> StdResult<Vec<(String, u128)>> {
+    let denom = DENOM.load(deps.storage)?;
+    Ok(deps
         .querier
         .query_all_delegations(env.contract.address.clone())?
-        .iter()
-        .map(|del: &Delegation| -> u128 {
-            if del.amount.clone().denom != DENOM.load(deps.storage).unwrap() {
-                return 0;
-            }
-            del.amount.amount.u128()
-        })
-        .sum();
-    let unbonding: u128 = get_unbonding_balance(deps, env.clone())?;
-    let mut principal_in_bank: u128 = 0;
-    if withdrawn_principal + staked + unbonding < total_locked {
-        principal_in_bank = total_locked - withdrawn_principal - staked - unbonding;
+        .into_iter()
+        .filter(|delegation| delegation.amount.denom == denom)
+        .map(|delegation| (delegation.validator, delegation.amount.amount.u128()))
+        .collect())
+}
+
+fn sum_by_validator<'a>(
+    amounts: impl Iterator<Item = (&'a String, u128)>,
+) -> Vec<(&'a String, u128)> {
+    let mut sums: Vec<(&String, u128)> = vec![];
+    for (validator, amount) in amounts {
+        match sums.iter_mut().find(|(v, _)| *v == validator) {
+            Some((_, total)) => *total = total.saturating_add(amount),
+            None => sums.push((validator, amount)),
+        }
     }
-    if principal_in_bank < bank_balance {
-        return Ok(bank_balance - principal_in_bank);
+    sums
+}
+
+// checks that the amounts moved out of each validator, summed per validator, are delegated to it
+fn assert_delegated<'a>(
+    deps: Deps<SeiQueryWrapper>,
+    env: &Env,
+    amounts: impl Iterator<Item = (&'a String, u128)>,
+) -> Result<(), ContractError> {
+    let delegations = delegated_amounts(deps, env)?;
+    for (validator, amount) in sum_by_validator(amounts) {
+        let delegated = delegations
+            .iter()
+            .find(|(v, _)| v == validator)
+            .map(|(_, delegated)| *delegated)
+            .unwrap_or_default();
+        if amount > delegated {
+            return Err(ContractError::InvalidDelegation(format!(
+                "{} exceeds the {} delegated to {}",
+                amount, delegated, validator
+            )));
+        }
     }
-    Ok(0)
+    Ok(())
 }
 
-fn execute_update_op(
+fn execute_initiate_withdraw_unlocked(
     deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
     info: MessageInfo,
-    op: Addr,
-    remove: bool,
+    tranche_id: &str,
+    amount: u128,
 ) -> Result<Response<Empty>, ContractError> {
-    authorize_admin(deps.storage, info.sender)?;
-    if remove {
-        OPS.remove(deps.storage, &op);
-    } else {
-        OPS.save(deps.storage, &op, &EmptyStruct {})?;
+    authorize_op(deps.storage, info.sender.clone())?;
+    assert_tranche_exists(deps.storage, tranche_id)?;
+    let mut shortfall = 0u128;
+    if !CW20_TOKEN.has(deps.storage, tranche_id) {
+        let liquid = liquid_balance(deps.as_ref(), &env)?;
+        if amount > liquid {
+            shortfall = amount - liquid;
+            if !UNDELEGATE_FOR_WITHDRAWALS
+                .may_load(deps.storage)?
+                .unwrap_or_default()
+            {
+                return Err(ContractError::InsufficientLiquidBalance { shortfall });
+            }
+        }
     }
-    Ok(Response::new())
+    let now = schedule_now(deps.storage, tranche_id, &env)?;
+    let vested_amount = collect_vested(deps.storage, tranche_id, now, amount)?;
+    WITHDRAWN_UNLOCKED.update(deps.storage, tranche_id, |old| -> Result<u128, StdError> {
+        Ok(old.unwrap_or_default() + vested_amount)
+    })?;
+    if shortfall > 0 {
+        return queue_unlocked_withdrawal(deps, env, tranche_id, vested_amount, shortfall);
+    }
+    record_unlocked_payouts(deps.storage, &env, &info.sender, tranche_id, vested_amount)?;
+    distribute_vested(deps.storage, tranche_id, vested_amount, Response::new())
 }
 
-fn execute_propose_update_admin(
+// the contract's balance of `DENOM` that is not reserved for pending withdrawals
+fn liquid_balance(deps: Deps<SeiQueryWrapper>, env: &Env) -> StdResult<u128> {
+    let balance = deps
+        .querier
+        .query_balance(env.contract.address.clone(), DENOM.load(deps.storage)?)?
+        .amount
+        .u128();
+    Ok(balance.saturating_sub(total_pending_withdrawals(deps.storage)?))
+}
+
+// undelegates `shortfall` and queues the payout of `amount` until `ClaimPendingWithdrawals` finds
+// the unbonded tokens in the contract's balance
+fn queue_unlocked_withdrawal(
     deps: DepsMut<SeiQueryWrapper>,
     env: Env,
-    info: MessageInfo,
-    admin: Addr,
-    remove: bool,
+    tranche_id: &str,
+    amount: u128,
+    shortfall: u128,
 ) -> Result<Response<Empty>, ContractError> {
-    let msg = ExecuteMsg::InternalUpdateAdmin {
-        admin: admin.clone(),
-        remove: remove,
-    };
-    let title: String;
-    if remove {
-        title = format!("remove {}", admin.to_string());
-    } else {
-        title = format!("add {}", admin.to_string());
+    let denom = DENOM.load(deps.storage)?;
+    let mut delegations = delegated_amounts(deps.as_ref(), &env)?;
+    if delegations.iter().map(|(_, amount)| amount).sum::<u128>() < shortfall {
+        return Err(ContractError::InsufficientLiquidBalance { shortfall });
     }
-    execute_propose(
-        deps,
-        env.clone(),
-        info.clone(),
-        title.clone(),
-        vec![CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: env.contract.address.to_string(),
-            msg: to_binary(&msg)?,
-            funds: vec![],
-        })],
-    )
+    // undelegate from the largest delegations first to keep the number of messages low
+    delegations.sort_by_key(|(_, delegated)| std::cmp::Reverse(*delegated));
+    let mut response = Response::new()
+        .add_attribute("action", "queue_withdrawal")
+        .add_attribute("tranche_id", tranche_id)
+        .add_attribute("amount", amount.to_string());
+    let mut remaining = shortfall;
+    for (validator, delegated) in delegations {
+        if remaining == 0 {
+            break;
+        }
+        let to_undelegate = std::cmp::min(delegated, remaining);
+        record_undelegation(deps.storage, &validator, to_undelegate, env.block.time)?;
+        response = undelegate(response, validator, to_undelegate, denom.clone());
+        remaining -= to_undelegate;
+    }
+    for (recipient, share) in unlocked_payouts(deps.storage, tranche_id, amount)? {
+        queue_pending_withdrawal(
+            deps.storage,
+            &PendingWithdrawal {
+                tranche_id: tranche_id.to_string(),
+                recipient,
+                amount: Uint128::new(share),
+                time: env.block.time,
+            },
+        )?;
+    }
+    Ok(response)
 }
 
-fn execute_propose_update_unlocked_distribution_address(
+fn execute_claim_pending_withdrawals(
     deps: DepsMut<SeiQueryWrapper>,
     env: Env,
     info: MessageInfo,
-    unlocked_distribution_address: Addr,
 ) -> Result<Response<Empty>, ContractError> {
-    let msg = ExecuteMsg::InternalUpdateUnlockedDistributionAddress {
-        unlocked_distribution_address: unlocked_distribution_address.clone(),
+    authorize_op(deps.storage, info.sender.clone())?;
+    let denom = DENOM.load(deps.storage)?;
+    let mut balance = deps
+        .querier
+        .query_balance(env.contract.address.clone(), denom.clone())?
+        .amount
+        .u128();
+    let pending = PENDING_WITHDRAWALS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut response = Response::new()
+        .add_attribute("action", "claim_pending_withdrawals")
+        .add_attribute("sender", info.sender.clone());
+    for (id, withdrawal) in pending {
+        let amount = withdrawal.amount.u128();
+        if amount > balance {
+            break;
+        }
+        balance -= amount;
+        PENDING_WITHDRAWALS.remove(deps.storage, id);
+        record_payout(
+            deps.storage,
+            &env,
+            &info.sender,
+            WithdrawalKind::Unlocked,
+            Some(&withdrawal.tranche_id),
+            &withdrawal.recipient,
+            amount,
+        )?;
+        response = response
+            .add_message(BankMsg::Send {
+                to_address: withdrawal.recipient.to_string(),
+                amount: coins(amount, denom.clone()),
+            })
+            .add_attribute("pending_withdrawal_id", id.to_string());
+    }
+    Ok(response)
+}
+
+fn execute_release_vested(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response<Empty>, ContractError> {
+    if !PERMISSIONLESS_RELEASE
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+    {
+        return Err(ContractError::PermissionlessReleaseDisabled {});
+    }
+    let mut response = Response::new()
+        .add_attribute("action", "release_vested")
+        .add_attribute("sender", info.sender.clone());
+    let mut vested = vec![];
+    let mut native_amount = 0u128;
+    for tranche_id in get_tranche_ids(deps.storage)? {
+        let now = schedule_now(deps.storage, &tranche_id, &env)?;
+        let vested_amount = total_vested_amount(deps.storage, &tranche_id, now)?;
+        if vested_amount == 0 {
+            continue;
+        }
+        if !CW20_TOKEN.has(deps.storage, &tranche_id) {
+            native_amount += vested_amount;
+        }
+        vested.push((tranche_id, now, vested_amount));
+    }
+    let liquid = liquid_balance(deps.as_ref(), &env)?;
+    if native_amount > liquid {
+        return Err(ContractError::InsufficientLiquidBalance {
+            shortfall: native_amount - liquid,
+        });
+    }
+    for (tranche_id, now, vested_amount) in vested {
+        collect_vested(deps.storage, &tranche_id, now, vested_amount)?;
+        WITHDRAWN_UNLOCKED.update(deps.storage, &tranche_id, |old| -> Result<u128, StdError> {
+            Ok(old.unwrap_or_default() + vested_amount)
+        })?;
+        record_unlocked_payouts(deps.storage, &env, &info.sender, &tranche_id, vested_amount)?;
+        response = distribute_vested(deps.storage, &tranche_id, vested_amount, response)?
+            .add_attribute("tranche_id", tranche_id)
+            .add_attribute("amount", vested_amount.to_string());
+    }
+    Ok(response)
+}
+
+fn execute_add_tranche(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: String,
+    tranche: Tranche,
+) -> Result<Response<Empty>, ContractError> {
+    if tranche.cw20_token.is_some() {
+        return Err(ContractError::InvalidTranche(
+            "CW20 tranches must be funded through the CW20 receive hook".to_string(),
+        ));
+    }
+    if tranche.denom != DENOM.load(deps.storage)? {
+        return Err(ContractError::InvalidTranche(
+            "tranche denom must match the contract denom".to_string(),
+        ));
+    }
+    add_tranche(deps, env, info, tranche_id, tranche)
+}
+
+// `info.funds` holds the deposit, which for CW20 tranches is the amount received through the hook
+// denominated in the token's contract address
+fn add_tranche(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: String,
+    tranche: Tranche,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_admin(deps.storage, info.sender.clone())?;
+    if tranche_id.is_empty() {
+        return Err(ContractError::InvalidTranche(
+            "tranche ID cannot be empty".to_string(),
+        ));
+    }
+    if TOTAL_AMOUNT.has(deps.storage, &tranche_id) {
+        return Err(ContractError::InvalidTranche(format!(
+            "tranche {} already exists",
+            tranche_id
+        )));
+    }
+    // staking rewards are pooled across tranches, so they must share the same reward destination
+    if tranche.staking_reward_distribution_address != STAKING_REWARD_ADDRESS.load(deps.storage)? {
+        return Err(ContractError::InvalidTranche(
+            "tranche staking reward distribution address must match the contract's".to_string(),
+        ));
+    }
+    let tranche = tranche.with_expanded_schedule()?;
+    tranche.validate(env, info.funds, &validation_policy(deps.storage)?)?;
+    save_tranche(deps.storage, &tranche_id, tranche)?;
+    Ok(Response::new()
+        .add_attribute("action", "add_tranche")
+        .add_attribute("sender", info.sender)
+        .add_attribute("tranche_id", tranche_id))
+}
+
+fn execute_top_up(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: String,
+    vesting_timestamps: Vec<Timestamp>,
+    vesting_amounts: Vec<Uint128>,
+) -> Result<Response<Empty>, ContractError> {
+    if CW20_TOKEN.has(deps.storage, &tranche_id) {
+        return Err(ContractError::InvalidTranche(
+            "CW20 tranches must be funded through the CW20 receive hook".to_string(),
+        ));
+    }
+    let denom = DENOM.load(deps.storage)?;
+    top_up(
+        deps,
+        env,
+        info,
+        tranche_id,
+        vesting_timestamps,
+        vesting_amounts,
+        denom,
+    )
+}
+
+fn top_up(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: String,
+    vesting_timestamps: Vec<Timestamp>,
+    vesting_amounts: Vec<Uint128>,
+    denom: String,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_admin(deps.storage, info.sender.clone())?;
+    assert_tranche_exists(deps.storage, &tranche_id)?;
+    let amount = validate_vesting_amounts(&vesting_timestamps, &vesting_amounts)?;
+    validate_deposit(&info.funds, &denom, amount)?;
+    let unit = schedule_unit(deps.storage, &tranche_id)?;
+    let policy = validation_policy(deps.storage)?;
+    validate_vesting_schedule(&vesting_timestamps, &unit, env.clone(), &policy)?;
+    merge_schedule(
+        deps.storage,
+        &tranche_id,
+        unit.now(&env),
+        &vesting_timestamps,
+        &raw_amounts(&vesting_amounts),
+    )?;
+    TOTAL_AMOUNT.update(deps.storage, &tranche_id, |old| -> Result<u128, StdError> {
+        Ok(old.unwrap_or_default() + amount)
+    })?;
+    Ok(Response::new()
+        .add_attribute("action", "top_up")
+        .add_attribute("sender", info.sender)
+        .add_attribute("tranche_id", tranche_id)
+        .add_attribute("amount", amount.to_string()))
+}
+
+// CW20 tokens can't be attached to a message, so CW20 tranches are funded by sending the tokens to
+// this contract with a `ReceiveMsg` on top
+fn execute_receive(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response<Empty>, ContractError> {
+    let token = info.sender;
+    let info = MessageInfo {
+        sender: deps.api.addr_validate(&wrapper.sender)?,
+        funds: coins(wrapper.amount.u128(), token.to_string()),
+    };
+    match from_json(&wrapper.msg)? {
+        ReceiveMsg::AddTranche {
+            tranche_id,
+            tranche,
+        } => {
+            if tranche.cw20_token.as_ref() != Some(&token) || tranche.denom != token.as_str() {
+                return Err(ContractError::InvalidTranche(
+                    "tranche must vest the received CW20 token".to_string(),
+                ));
+            }
+            add_tranche(deps, env, info, tranche_id, *tranche)
+        }
+        ReceiveMsg::TopUp {
+            tranche_id,
+            vesting_timestamps,
+            vesting_amounts,
+        } => {
+            if CW20_TOKEN.may_load(deps.storage, &tranche_id)? != Some(token.clone()) {
+                return Err(ContractError::InvalidTranche(
+                    "tranche must vest the received CW20 token".to_string(),
+                ));
+            }
+            top_up(
+                deps,
+                env,
+                info,
+                tranche_id,
+                vesting_timestamps,
+                vesting_amounts,
+                token.to_string(),
+            )
+        }
+    }
+}
+
+// withdraws all rewards into the contract's balance, delegates the reward policy's share of them
+// and pays out the rest
+fn execute_initiate_withdraw_reward(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_op(deps.storage, info.sender.clone())?;
+    let policy = REWARD_POLICY.may_load(deps.storage)?.unwrap_or_default();
+    let mut response = Response::new().add_attribute("action", "withdraw_reward");
+    let mut total = calculate_withdrawn_rewards(deps.as_ref(), env.clone())?;
+    for validator in get_all_delegated_validators(deps.as_ref(), env.clone())? {
+        total += get_delegation_rewards(deps.as_ref(), env.clone(), validator.clone())?;
+        response = withdraw_rewards(response, validator);
+    }
+    let to_compound = Uint128::new(total)
+        .multiply_ratio(policy.compound_percentage(), 100u128)
+        .u128();
+    let denom = DENOM.load(deps.storage)?;
+    let mut compounded = 0u128;
+    if to_compound > 0 {
+        let delegations = match policy.validator() {
+            Some(validator) if validator_allowed(deps.storage, validator) => {
+                vec![(validator.clone(), to_compound)]
+            }
+            // the validator was taken off the allowlist after the policy was set. Its share is
+            // paid out instead of holding up the rewards.
+            Some(_) => vec![],
+            None => split_by_delegation(deps.as_ref(), &env, to_compound)?,
+        };
+        for (validator, amount) in fit_within_delegation_cap(deps.as_ref(), &env, delegations)? {
+            compounded += amount;
+            response = delegate(response, validator, amount, denom.clone());
+        }
+        let previously_compounded = COMPOUNDED_REWARDS
+            .may_load(deps.storage)?
+            .unwrap_or_default();
+        COMPOUNDED_REWARDS.save(deps.storage, &(previously_compounded + compounded))?;
+    }
+    let sent = total - compounded;
+    for (recipient, share) in reward_payouts(deps.storage, sent)? {
+        response = response.add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins(share, denom.clone()),
+        });
+        record_payout(
+            deps.storage,
+            &env,
+            &info.sender,
+            WithdrawalKind::Reward,
+            None,
+            &recipient,
+            share,
+        )?;
+        WITHDRAWN_REWARDS_BY_RECIPIENT.update(
+            deps.storage,
+            &recipient,
+            |old| -> StdResult<u128> { Ok(old.unwrap_or_default() + share) },
+        )?;
+    }
+    WITHDRAWN_STAKING_REWARDS.update(deps.storage, |old| -> StdResult<u128> { Ok(old + sent) })?;
+    Ok(response
+        .add_attribute("compounded", compounded.to_string())
+        .add_attribute("sent", sent.to_string()))
+}
+
+// who receives how much when `amount` staking rewards are paid out. Recipients whose share rounds
+// down to zero are left out.
+fn reward_payouts(storage: &dyn Storage, amount: u128) -> StdResult<Vec<(Addr, u128)>> {
+    if amount == 0 {
+        return Ok(vec![]);
+    }
+    match STAKING_REWARD_SPLIT.may_load(storage)? {
+        Some(recipients) => Ok(split_by_weight(amount, &recipients)
+            .into_iter()
+            .filter(|(_, share)| *share > 0)
+            .collect()),
+        None => Ok(vec![(STAKING_REWARD_ADDRESS.load(storage)?, amount)]),
+    }
+}
+
+// splits `amount` in proportion to the existing delegations. The rounding remainder goes to the
+// largest delegation.
+fn split_by_delegation(
+    deps: Deps<SeiQueryWrapper>,
+    env: &Env,
+    amount: u128,
+) -> Result<Vec<(String, u128)>, ContractError> {
+    let mut delegations = delegated_amounts(deps, env)?;
+    let total_delegated: u128 = delegations.iter().map(|(_, delegated)| delegated).sum();
+    if total_delegated == 0 {
+        return Err(ContractError::InvalidDelegation(
+            "no delegations to compound rewards into".to_string(),
+        ));
+    }
+    delegations.sort_by_key(|(_, delegated)| std::cmp::Reverse(*delegated));
+    let mut shares: Vec<(String, u128)> = delegations
+        .into_iter()
+        .map(|(validator, delegated)| {
+            let share = Uint128::new(amount).multiply_ratio(delegated, total_delegated);
+            (validator, share.u128())
+        })
+        .collect();
+    let distributed: u128 = shares.iter().map(|(_, share)| share).sum();
+    shares[0].1 += amount - distributed;
+    Ok(shares.into_iter().filter(|(_, share)| *share > 0).collect())
+}
+
+// rewards may be automatically withdrawn to contract's bank balance during redelegation/undelegation/delegating
+// more to the same validator. The amount of such withdrawn rewards, assuming no external deposit to the contract
+// is present, is the part of the bank balance that is not liquid principal.
+fn calculate_withdrawn_rewards(deps: Deps<SeiQueryWrapper>, env: Env) -> StdResult<u128> {
+    let bank_balance = deps
+        .querier
+        .query_balance(env.contract.address.clone(), DENOM.load(deps.storage)?)?
+        .amount
+        .u128();
+    Ok(bank_balance.saturating_sub(liquid_principal(deps.storage, &env)?))
+}
+
+// the principal the contract should hold in its balance according to the principal ledger, i.e.
+// what has been neither withdrawn, delegated nor is unbonding
+fn liquid_principal(storage: &dyn Storage, env: &Env) -> StdResult<u128> {
+    let total_locked: u128 = sum_over_native_tranches(storage, &TOTAL_AMOUNT)?;
+    // pending withdrawals are counted as withdrawn but are still held by the contract
+    let withdrawn_principal = sum_over_native_tranches(storage, &WITHDRAWN_LOCKED)?
+        + sum_over_native_tranches(storage, &WITHDRAWN_UNLOCKED)?
+        - total_pending_withdrawals(storage)?;
+    Ok(total_locked.saturating_sub(
+        withdrawn_principal
+            + total_delegated_principal(storage)?
+            + total_unbonding_principal(storage, env.block.time)?,
+    ))
+}
+
+fn staked_balance(deps: Deps<SeiQueryWrapper>, env: &Env) -> StdResult<u128> {
+    Ok(deps
+        .querier
+        .query_all_delegations(env.contract.address.clone())?
+        .iter()
+        .map(|del: &Delegation| -> u128 {
+            if del.amount.clone().denom != DENOM.load(deps.storage).unwrap() {
+                return 0;
+            }
+            del.amount.amount.u128()
+        })
+        .sum())
+}
+
+fn execute_update_op(
+    deps: DepsMut<SeiQueryWrapper>,
+    info: MessageInfo,
+    op: Addr,
+    remove: bool,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_admin(deps.storage, info.sender)?;
+    if remove {
+        OPS.remove(deps.storage, &op);
+    } else {
+        OPS.save(deps.storage, &op, &EmptyStruct {})?;
+    }
+    Ok(Response::new())
+}
+
+fn execute_propose_update_admin(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    admin: Addr,
+    remove: bool,
+) -> Result<Response<Empty>, ContractError> {
+    let msg = ExecuteMsg::InternalUpdateAdmin {
+        admin: admin.clone(),
+        remove: remove,
+    };
+    let title: String;
+    if remove {
+        title = format!("remove {}", admin.to_string());
+    } else {
+        title = format!("add {}", admin.to_string());
+    }
+    execute_propose(
+        deps,
+        env.clone(),
+        info.clone(),
+        title.clone(),
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        })],
+    )
+}
+
+fn execute_propose_update_unlocked_distribution_address(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    unlocked_distribution_address: Addr,
+) -> Result<Response<Empty>, ContractError> {
+    let msg = ExecuteMsg::InternalUpdateUnlockedDistributionAddress {
+        unlocked_distribution_address: unlocked_distribution_address.clone(),
     };
     let title = format!(
         "updating unlocked distribution address {}",
@@ -527,92 +1572,371 @@ fn execute_propose_update_unlocked_distribution_address(
         title.clone(),
         vec![CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: env.contract.address.to_string(),
-            msg: to_binary(&msg)?,
+            msg: to_json_binary(&msg)?,
             funds: vec![],
         })],
     )
 }
 
-fn execute_propose_update_staking_reward_distribution_address(
+fn execute_propose_update_tranche_unlocked_distribution_address(
     deps: DepsMut<SeiQueryWrapper>,
     env: Env,
     info: MessageInfo,
-    staking_reward_distribution_address: Addr,
+    tranche_id: String,
+    unlocked_distribution_address: Addr,
 ) -> Result<Response<Empty>, ContractError> {
-    let msg = ExecuteMsg::InternalUpdateStakingRewardDistributionAddress {
-        staking_reward_distribution_address: staking_reward_distribution_address.clone(),
+    assert_tranche_exists(deps.storage, &tranche_id)?;
+    let title = format!(
+        "updating unlocked distribution address of tranche {} {}",
+        tranche_id, unlocked_distribution_address
+    );
+    let msg = ExecuteMsg::InternalUpdateTrancheUnlockedDistributionAddress {
+        tranche_id,
+        unlocked_distribution_address,
     };
+    execute_propose(
+        deps,
+        env.clone(),
+        info,
+        title,
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        })],
+    )
+}
+
+fn execute_propose_update_unlocked_distribution_split(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: String,
+    recipients: Vec<WeightedRecipient>,
+) -> Result<Response<Empty>, ContractError> {
+    assert_tranche_exists(deps.storage, &tranche_id)?;
+    validate_weighted_recipients(&recipients)?;
     let title = format!(
-        "updating staking reward distribution address {}",
-        staking_reward_distribution_address.to_string()
+        "updating unlocked distribution split for tranche {} to {:?}",
+        tranche_id, recipients
     );
+    let msg = ExecuteMsg::InternalUpdateUnlockedDistributionSplit {
+        tranche_id,
+        recipients,
+    };
     execute_propose(
         deps,
         env.clone(),
-        info.clone(),
-        title.clone(),
+        info,
+        title,
         vec![CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: env.contract.address.to_string(),
-            msg: to_binary(&msg)?,
+            msg: to_json_binary(&msg)?,
             funds: vec![],
         })],
     )
 }
 
-fn execute_propose_emergency_withdraw(
+fn execute_propose_update_staking_reward_split(
     deps: DepsMut<SeiQueryWrapper>,
     env: Env,
     info: MessageInfo,
-    dst: Addr,
+    recipients: Vec<WeightedRecipient>,
 ) -> Result<Response<Empty>, ContractError> {
-    let title = format!("emergecy withdraw to {}", dst.to_string());
-    let msg = ExecuteMsg::InternalWithdrawLocked { dst };
+    validate_reward_split(&recipients)?;
+    let title = format!("updating staking reward split to {:?}", recipients);
+    let msg = ExecuteMsg::InternalUpdateStakingRewardSplit { recipients };
     execute_propose(
         deps,
         env.clone(),
-        info.clone(),
-        title.clone(),
+        info,
+        title,
         vec![CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: env.contract.address.to_string(),
-            msg: to_binary(&msg)?,
+            msg: to_json_binary(&msg)?,
             funds: vec![],
         })],
     )
 }
 
-fn execute_propose_gov_vote(
+fn execute_propose_update_staking_reward_distribution_address(
     deps: DepsMut<SeiQueryWrapper>,
     env: Env,
     info: MessageInfo,
-    gov_proposal_id: u64,
-    gov_vote: VoteOption,
+    staking_reward_distribution_address: Addr,
 ) -> Result<Response<Empty>, ContractError> {
-    let title = format!("voting {:?} for {}", gov_vote, gov_proposal_id);
-    let msg = GovMsg::Vote {
-        proposal_id: gov_proposal_id,
-        vote: gov_vote,
+    let msg = ExecuteMsg::InternalUpdateStakingRewardDistributionAddress {
+        staking_reward_distribution_address: staking_reward_distribution_address.clone(),
     };
+    let title = format!(
+        "updating staking reward distribution address {}",
+        staking_reward_distribution_address.to_string()
+    );
     execute_propose(
         deps,
         env.clone(),
         info.clone(),
         title.clone(),
-        vec![CosmosMsg::Gov(msg)],
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        })],
     )
 }
 
-fn execute_propose(
+fn execute_propose_emergency_withdraw(
     deps: DepsMut<SeiQueryWrapper>,
     env: Env,
     info: MessageInfo,
-    title: String,
-    msgs: Vec<CosmosMsg>,
+    dst: Addr,
 ) -> Result<Response<Empty>, ContractError> {
-    authorize_admin(deps.storage, info.sender.clone())?;
-
-    let expires = MAX_VOTING_PERIOD.load(deps.storage)?.after(&env.block);
-    let mut prop = Proposal {
-        title: title,
+    let title = format!("emergecy withdraw to {}", dst.to_string());
+    let msg = ExecuteMsg::InternalWithdrawLocked {
+        dst,
+        proposer: Some(info.sender.clone()),
+    };
+    execute_propose(
+        deps,
+        env.clone(),
+        info.clone(),
+        title.clone(),
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        })],
+    )
+}
+
+fn execute_propose_clawback(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: String,
+    dst: Addr,
+    clawback: Clawback,
+) -> Result<Response<Empty>, ContractError> {
+    assert_tranche_exists(deps.storage, &tranche_id)?;
+    if let Clawback::After {
+        cutoff: Some(cutoff),
+    } = clawback
+    {
+        if cutoff < schedule_now(deps.storage, &tranche_id, &env)? {
+            return Err(ContractError::InvalidClawback(
+                "cutoff cannot be in the past".to_string(),
+            ));
+        }
+    }
+    let title = format!(
+        "clawback {:?} from tranche {} to {}",
+        clawback, tranche_id, dst
+    );
+    let msg = ExecuteMsg::InternalClawback {
+        tranche_id,
+        dst,
+        clawback,
+        proposer: info.sender.clone(),
+    };
+    execute_propose(
+        deps,
+        env.clone(),
+        info,
+        title,
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        })],
+    )
+}
+
+fn execute_propose_amend_schedule(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: String,
+    amendment: ScheduleAmendment,
+) -> Result<Response<Empty>, ContractError> {
+    assert_tranche_exists(deps.storage, &tranche_id)?;
+    amendment.validate(
+        env.clone(),
+        &schedule_unit(deps.storage, &tranche_id)?,
+        &validation_policy(deps.storage)?,
+    )?;
+    assert_native_addition(deps.storage, &tranche_id, &amendment)?;
+    let title = format!(
+        "amend schedule of tranche {} after {}",
+        tranche_id, amendment.cutoff
+    );
+    let msg = ExecuteMsg::InternalAmendSchedule {
+        tranche_id,
+        amendment,
+        proposer: info.sender.clone(),
+    };
+    execute_propose(
+        deps,
+        env.clone(),
+        info,
+        title,
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        })],
+    )
+}
+
+fn execute_propose_update_permissionless_release(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response<Empty>, ContractError> {
+    let msg = ExecuteMsg::InternalUpdatePermissionlessRelease { enabled };
+    let title = format!("updating permissionless release to {}", enabled);
+    execute_propose(
+        deps,
+        env.clone(),
+        info,
+        title,
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        })],
+    )
+}
+
+fn execute_propose_update_undelegate_for_withdrawals(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response<Empty>, ContractError> {
+    let msg = ExecuteMsg::InternalUpdateUndelegateForWithdrawals { enabled };
+    let title = format!("updating undelegation for withdrawals to {}", enabled);
+    execute_propose(
+        deps,
+        env.clone(),
+        info,
+        title,
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        })],
+    )
+}
+
+fn execute_propose_update_allowed_validator(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    validator: String,
+    remove: bool,
+) -> Result<Response<Empty>, ContractError> {
+    let title = if remove {
+        format!("remove validator {} from the allowlist", validator)
+    } else {
+        format!("add validator {} to the allowlist", validator)
+    };
+    let msg = ExecuteMsg::InternalUpdateAllowedValidator { validator, remove };
+    execute_propose(
+        deps,
+        env.clone(),
+        info,
+        title,
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        })],
+    )
+}
+
+fn execute_propose_update_delegation_cap(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    cap: Option<DelegationCap>,
+) -> Result<Response<Empty>, ContractError> {
+    let title = match &cap {
+        Some(cap) => {
+            cap.validate()?;
+            format!("updating delegation cap to {:?}", cap)
+        }
+        None => "removing the delegation cap".to_string(),
+    };
+    let msg = ExecuteMsg::InternalUpdateDelegationCap { cap };
+    execute_propose(
+        deps,
+        env.clone(),
+        info,
+        title,
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        })],
+    )
+}
+
+fn execute_propose_update_reward_policy(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    policy: RewardPolicy,
+) -> Result<Response<Empty>, ContractError> {
+    policy.validate()?;
+    let title = format!("updating reward policy to {:?}", policy);
+    let msg = ExecuteMsg::InternalUpdateRewardPolicy { policy };
+    execute_propose(
+        deps,
+        env.clone(),
+        info,
+        title,
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        })],
+    )
+}
+
+fn execute_propose_gov_vote(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    gov_proposal_id: u64,
+    gov_vote: VoteOption,
+) -> Result<Response<Empty>, ContractError> {
+    let title = format!("voting {:?} for {}", gov_vote, gov_proposal_id);
+    let msg = GovMsg::Vote {
+        proposal_id: gov_proposal_id,
+        vote: gov_vote,
+    };
+    execute_propose(
+        deps,
+        env.clone(),
+        info.clone(),
+        title.clone(),
+        vec![CosmosMsg::Gov(msg)],
+    )
+}
+
+fn execute_propose(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    title: String,
+    msgs: Vec<CosmosMsg>,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_admin(deps.storage, info.sender.clone())?;
+
+    let expires = MAX_VOTING_PERIOD.load(deps.storage)?.after(&env.block);
+    let mut prop = Proposal {
+        title: title,
         description: "".to_string(),
         start_height: env.block.height,
         expires,
@@ -726,10 +2050,27 @@ fn execute_internal_update_unlocked_distribution_address(
     deps: DepsMut<SeiQueryWrapper>,
     env: Env,
     info: MessageInfo,
+    tranche_id: &str,
     unlocked_distribution_address: Addr,
 ) -> Result<Response<Empty>, ContractError> {
     authorize_self_call(env, info)?;
-    UNLOCK_DISTRIBUTION_ADDRESS.save(deps.storage, &unlocked_distribution_address)?;
+    assert_tranche_exists(deps.storage, tranche_id)?;
+    UNLOCK_DISTRIBUTION_ADDRESS.save(deps.storage, tranche_id, &unlocked_distribution_address)?;
+    UNLOCK_DISTRIBUTION_SPLIT.remove(deps.storage, tranche_id);
+    Ok(Response::new())
+}
+
+fn execute_internal_update_unlocked_distribution_split(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: &str,
+    recipients: Vec<WeightedRecipient>,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_self_call(env, info)?;
+    assert_tranche_exists(deps.storage, tranche_id)?;
+    validate_weighted_recipients(&recipients)?;
+    UNLOCK_DISTRIBUTION_SPLIT.save(deps.storage, tranche_id, &recipients)?;
     Ok(Response::new())
 }
 
@@ -741,85 +2082,566 @@ fn execute_internal_update_staking_reward_distribution_address(
 ) -> Result<Response<Empty>, ContractError> {
     authorize_self_call(env, info)?;
     STAKING_REWARD_ADDRESS.save(deps.storage, &staking_reward_distribution_address)?;
+    STAKING_REWARD_SPLIT.remove(deps.storage);
     Ok(Response::new())
 }
 
-fn execute_internal_withdraw_locked(
+fn execute_internal_update_staking_reward_split(
     deps: DepsMut<SeiQueryWrapper>,
     env: Env,
     info: MessageInfo,
-    dst: Addr,
+    recipients: Vec<WeightedRecipient>,
 ) -> Result<Response<Empty>, ContractError> {
     authorize_self_call(env, info)?;
-    let amount = VESTING_AMOUNTS.load(deps.storage)?.iter().sum();
-    VESTING_AMOUNTS.save(deps.storage, &vec![])?;
-    VESTING_TIMESTAMPS.save(deps.storage, &vec![])?;
-    WITHDRAWN_LOCKED.update(deps.storage, |old| -> Result<u128, StdError> {
-        Ok(old + amount)
-    })?;
-    Ok(Response::new().add_message(BankMsg::Send {
-        to_address: dst.to_string(),
-        amount: coins(amount, DENOM.load(deps.storage)?),
-    }))
+    validate_reward_split(&recipients)?;
+    STAKING_REWARD_SPLIT.save(deps.storage, &recipients)?;
+    Ok(Response::new())
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps<SeiQueryWrapper>, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::ListProposals {} => to_binary(&query_proposals(deps, env)?),
-        QueryMsg::ListVotes { proposal_id } => to_binary(&query_votes(deps, proposal_id)?),
-        QueryMsg::ListAdmins {} => to_binary(&query_admins(deps)?),
-        QueryMsg::ListOps {} => to_binary(&query_ops(deps)?),
-        QueryMsg::Info {} => to_binary(&query_info(deps)?),
-        QueryMsg::Config {} => to_binary(&query_config(deps)?),
-        QueryMsg::TotalVested {} => to_binary(&query_total_vested(deps, env)?),
-    }
+fn execute_internal_update_permissionless_release(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_self_call(env, info)?;
+    PERMISSIONLESS_RELEASE.save(deps.storage, &enabled)?;
+    Ok(Response::new())
 }
 
-fn query_proposals(deps: Deps<SeiQueryWrapper>, env: Env) -> StdResult<ProposalListResponse> {
-    let proposals: Vec<ProposalResponse> = PROPOSALS
-        .range(deps.storage, None, None, Order::Descending)
-        .map(|p| map_proposal(&env.block, p))
-        .collect::<StdResult<_>>()?;
-    Ok(ProposalListResponse { proposals })
+fn execute_internal_update_undelegate_for_withdrawals(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_self_call(env, info)?;
+    UNDELEGATE_FOR_WITHDRAWALS.save(deps.storage, &enabled)?;
+    Ok(Response::new())
 }
 
-fn map_proposal(
-    block: &BlockInfo,
-    item: StdResult<(u64, Proposal)>,
-) -> StdResult<ProposalResponse> {
-    item.map(|(id, prop)| {
-        let status = prop.current_status(block);
-        let threshold = prop.threshold.to_response(prop.total_weight);
-        ProposalResponse {
-            id,
-            title: prop.title,
-            description: prop.description,
-            msgs: prop.msgs,
-            status,
-            deposit: prop.deposit,
-            proposer: prop.proposer,
-            expires: prop.expires,
-            threshold,
+fn execute_internal_update_delegation_cap(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    cap: Option<DelegationCap>,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_self_call(env, info)?;
+    match cap {
+        Some(cap) => {
+            cap.validate()?;
+            DELEGATION_CAP.save(deps.storage, &cap)?;
         }
-    })
+        None => DELEGATION_CAP.remove(deps.storage),
+    }
+    Ok(Response::new())
 }
 
-fn query_votes(deps: Deps<SeiQueryWrapper>, proposal_id: u64) -> StdResult<VoteListResponse> {
-    let votes = BALLOTS
-        .prefix(proposal_id)
-        .range(deps.storage, None, None, Order::Ascending)
-        .map(|item| {
-            item.map(|(addr, ballot)| VoteInfo {
-                proposal_id,
-                voter: addr.into(),
-                vote: ballot.vote,
-                weight: ballot.weight,
-            })
-        })
-        .collect::<StdResult<_>>()?;
+fn execute_internal_update_reward_policy(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    policy: RewardPolicy,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_self_call(env, info)?;
+    policy.validate()?;
+    REWARD_POLICY.save(deps.storage, &policy)?;
+    Ok(Response::new())
+}
 
-    Ok(VoteListResponse { votes })
+fn execute_internal_update_allowed_validator(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    validator: String,
+    remove: bool,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_self_call(env, info)?;
+    if remove {
+        ALLOWED_VALIDATORS.remove(deps.storage, &validator);
+    } else {
+        ALLOWED_VALIDATORS.save(deps.storage, &validator, &EmptyStruct {})?;
+    }
+    Ok(Response::new())
+}
+
+fn execute_internal_withdraw_locked(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    dst: Addr,
+    proposer: Option<Addr>,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_self_call(env.clone(), info)?;
+    let sender = proposer.unwrap_or_else(|| env.contract.address.clone());
+    let mut response = Response::new();
+    let mut amount = 0u128;
+    for tranche_id in get_tranche_ids(deps.storage)? {
+        let tranche_amount: u128 = load_schedule(deps.storage, &tranche_id)?
+            .1
+            .iter()
+            .sum::<u128>()
+            + remove_milestones(deps.storage, &tranche_id)?;
+        save_schedule(deps.storage, &tranche_id, &[], &[])?;
+        LINEAR_VESTING.remove(deps.storage, &tranche_id);
+        WITHDRAWN_LOCKED.update(deps.storage, &tranche_id, |old| -> Result<u128, StdError> {
+            Ok(old.unwrap_or_default() + tranche_amount)
+        })?;
+        if !CW20_TOKEN.has(deps.storage, &tranche_id) {
+            amount += tranche_amount;
+        } else if tranche_amount > 0 {
+            record_payout(
+                deps.storage,
+                &env,
+                &sender,
+                WithdrawalKind::Locked,
+                Some(&tranche_id),
+                &dst,
+                tranche_amount,
+            )?;
+            response = response.add_message(transfer_msg(
+                deps.storage,
+                &tranche_id,
+                &dst,
+                tranche_amount,
+            )?);
+        }
+    }
+    if amount > 0 {
+        record_payout(
+            deps.storage,
+            &env,
+            &sender,
+            WithdrawalKind::Locked,
+            None,
+            &dst,
+            amount,
+        )?;
+        response = response.add_message(BankMsg::Send {
+            to_address: dst.to_string(),
+            amount: coins(amount, DENOM.load(deps.storage)?),
+        });
+    }
+    Ok(response)
+}
+
+fn execute_internal_clawback(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: String,
+    dst: Addr,
+    clawback: Clawback,
+    proposer: Addr,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_self_call(env.clone(), info)?;
+    assert_tranche_exists(deps.storage, &tranche_id)?;
+    let now = schedule_now(deps.storage, &tranche_id, &env)?;
+    let amount = match clawback {
+        Clawback::After { cutoff } => {
+            clawback_after(deps.storage, &tranche_id, now, cutoff.unwrap_or(now))?
+        }
+        Clawback::Amount { amount } => {
+            clawback_amount(deps.storage, &tranche_id, now, amount.u128())?
+        }
+        Clawback::LapsedMilestones {} => {
+            clawback_lapsed_milestones(deps.storage, &tranche_id, now)?
+        }
+    };
+    WITHDRAWN_LOCKED.update(deps.storage, &tranche_id, |old| -> Result<u128, StdError> {
+        Ok(old.unwrap_or_default() + amount)
+    })?;
+    let mut response = Response::new()
+        .add_attribute("action", "clawback")
+        .add_attribute("tranche_id", tranche_id.clone())
+        .add_attribute("amount", amount.to_string());
+    if amount > 0 {
+        record_payout(
+            deps.storage,
+            &env,
+            &proposer,
+            WithdrawalKind::Locked,
+            Some(&tranche_id),
+            &dst,
+            amount,
+        )?;
+        response = response.add_message(transfer_msg(deps.storage, &tranche_id, &dst, amount)?);
+    }
+    Ok(response)
+}
+
+fn execute_internal_amend_schedule(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: String,
+    amendment: ScheduleAmendment,
+    proposer: Addr,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_self_call(env.clone(), info)?;
+    assert_tranche_exists(deps.storage, &tranche_id)?;
+    let unit = schedule_unit(deps.storage, &tranche_id)?;
+    let policy = validation_policy(deps.storage)?;
+    amendment.validate(env.clone(), &unit, &policy)?;
+    assert_native_addition(deps.storage, &tranche_id, &amendment)?;
+    let added_amount = amendment.added_amount.u128();
+    let clawed_back_amount = amendment.clawed_back_amount.u128();
+    if added_amount > 0 && added_amount > calculate_withdrawn_rewards(deps.as_ref(), env.clone())? {
+        return Err(ContractError::InvalidAmendment(
+            "insufficient unallocated funds to add to the schedule".to_string(),
+        ));
+    }
+    let replaced = amend_schedule(deps.storage, &tranche_id, unit.now(&env), &amendment)?;
+    TOTAL_AMOUNT.update(deps.storage, &tranche_id, |old| -> Result<u128, StdError> {
+        Ok(old.unwrap_or_default() + added_amount)
+    })?;
+    WITHDRAWN_LOCKED.update(deps.storage, &tranche_id, |old| -> Result<u128, StdError> {
+        Ok(old.unwrap_or_default() + clawed_back_amount)
+    })?;
+    let mut response = Response::new()
+        .add_attribute("action", "amend_schedule")
+        .add_attribute("tranche_id", tranche_id.clone())
+        .add_attribute("replaced_amount", replaced.to_string())
+        .add_attribute("added_amount", amendment.added_amount.to_string())
+        .add_attribute(
+            "clawed_back_amount",
+            amendment.clawed_back_amount.to_string(),
+        );
+    if let Some(dst) = amendment.clawback_dst {
+        record_payout(
+            deps.storage,
+            &env,
+            &proposer,
+            WithdrawalKind::Locked,
+            Some(&tranche_id),
+            &dst,
+            clawed_back_amount,
+        )?;
+        response = response.add_message(transfer_msg(
+            deps.storage,
+            &tranche_id,
+            &dst,
+            clawed_back_amount,
+        )?);
+    }
+    Ok(response)
+}
+
+// amendments add from the contract's unallocated native balance. CW20 tranches are topped up through
+// the receive hook instead.
+fn assert_native_addition(
+    storage: &dyn Storage,
+    tranche_id: &str,
+    amendment: &ScheduleAmendment,
+) -> Result<(), ContractError> {
+    if !amendment.added_amount.is_zero() && CW20_TOKEN.has(storage, tranche_id) {
+        return Err(ContractError::InvalidAmendment(
+            "CW20 tranches must be topped up through the CW20 receive hook".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn execute_propose_milestone_reached(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: String,
+    milestone_id: String,
+) -> Result<Response<Empty>, ContractError> {
+    assert_tranche_exists(deps.storage, &tranche_id)?;
+    let now = schedule_now(deps.storage, &tranche_id, &env)?;
+    load_pending_milestone(deps.storage, &tranche_id, &milestone_id, now)?;
+    let title = format!(
+        "milestone {} of tranche {} reached",
+        milestone_id, tranche_id
+    );
+    let msg = ExecuteMsg::InternalMilestoneReached {
+        tranche_id,
+        milestone_id,
+    };
+    execute_propose(
+        deps,
+        env.clone(),
+        info,
+        title,
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        })],
+    )
+}
+
+fn execute_internal_milestone_reached(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: String,
+    milestone_id: String,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_self_call(env.clone(), info)?;
+    assert_tranche_exists(deps.storage, &tranche_id)?;
+    let now = schedule_now(deps.storage, &tranche_id, &env)?;
+    let mut milestone = load_pending_milestone(deps.storage, &tranche_id, &milestone_id, now)?;
+    milestone.reached = true;
+    MILESTONES.save(deps.storage, (&tranche_id, &milestone_id), &milestone)?;
+    Ok(Response::new()
+        .add_attribute("action", "milestone_reached")
+        .add_attribute("tranche_id", tranche_id)
+        .add_attribute("milestone_id", milestone_id)
+        .add_attribute("amount", milestone.amount.to_string()))
+}
+
+fn execute_propose_correct_total_amount(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: String,
+    explanation: String,
+) -> Result<Response<Empty>, ContractError> {
+    assert_tranche_exists(deps.storage, &tranche_id)?;
+    if explanation.is_empty() {
+        return Err(ContractError::InvalidCorrection(
+            "explanation cannot be empty".to_string(),
+        ));
+    }
+    let reconciliation = reconcile_tranche(deps.storage, &tranche_id)?;
+    if reconciliation.accounted_amount == reconciliation.total_amount {
+        return Err(ContractError::InvalidCorrection(format!(
+            "tranche {} has no accounting discrepancy",
+            tranche_id
+        )));
+    }
+    let title = format!(
+        "correct total amount of tranche {} from {} to {}: {}",
+        tranche_id, reconciliation.total_amount, reconciliation.accounted_amount, explanation
+    );
+    let msg = ExecuteMsg::InternalCorrectTotalAmount {
+        tranche_id,
+        total_amount: reconciliation.accounted_amount,
+    };
+    execute_propose(
+        deps,
+        env.clone(),
+        info,
+        title,
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        })],
+    )
+}
+
+fn execute_internal_correct_total_amount(
+    deps: DepsMut<SeiQueryWrapper>,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: String,
+    total_amount: u128,
+) -> Result<Response<Empty>, ContractError> {
+    authorize_self_call(env, info)?;
+    assert_tranche_exists(deps.storage, &tranche_id)?;
+    // the proposal was voted on for a specific amount
+    if reconcile_tranche(deps.storage, &tranche_id)?.accounted_amount != Uint128::new(total_amount)
+    {
+        return Err(ContractError::InvalidCorrection(format!(
+            "accounting of tranche {} changed since the proposal",
+            tranche_id
+        )));
+    }
+    let previous = TOTAL_AMOUNT.load(deps.storage, &tranche_id)?;
+    TOTAL_AMOUNT.save(deps.storage, &tranche_id, &total_amount)?;
+    Ok(Response::new()
+        .add_attribute("action", "correct_total_amount")
+        .add_attribute("tranche_id", tranche_id)
+        .add_attribute("previous_total_amount", previous.to_string())
+        .add_attribute("total_amount", total_amount.to_string()))
+}
+
+// loads a milestone that can still be reached
+fn load_pending_milestone(
+    storage: &dyn Storage,
+    tranche_id: &str,
+    milestone_id: &str,
+    now: Timestamp,
+) -> Result<MilestoneState, ContractError> {
+    let milestone = MILESTONES
+        .may_load(storage, (tranche_id, milestone_id))?
+        .ok_or_else(|| {
+            ContractError::InvalidMilestone(format!("milestone {} not found", milestone_id))
+        })?;
+    if milestone.reached {
+        return Err(ContractError::InvalidMilestone(format!(
+            "milestone {} has already been reached",
+            milestone_id
+        )));
+    }
+    if milestone.is_lapsed(now) {
+        return Err(ContractError::InvalidMilestone(format!(
+            "milestone {} has lapsed",
+            milestone_id
+        )));
+    }
+    Ok(milestone)
+}
+
+// appends a payout to the withdrawal ledger. `tranche_id` is `None` for payouts in the native denom
+// that are pooled across tranches.
+fn record_payout(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    kind: WithdrawalKind,
+    tranche_id: Option<&str>,
+    recipient: &Addr,
+    amount: u128,
+) -> StdResult<()> {
+    let denom = match tranche_id {
+        Some(tranche_id) => tranche_denom(storage, tranche_id)?,
+        None => DENOM.load(storage)?,
+    };
+    record_withdrawal(
+        storage,
+        &WithdrawalRecord {
+            height: env.block.height,
+            time: env.block.time,
+            kind,
+            tranche_id: tranche_id.map(|id| id.to_string()),
+            denom,
+            amount: Uint128::new(amount),
+            recipient: recipient.clone(),
+            sender: sender.clone(),
+        },
+    )?;
+    Ok(())
+}
+
+fn record_unlocked_payouts(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    tranche_id: &str,
+    amount: u128,
+) -> StdResult<()> {
+    for (recipient, share) in unlocked_payouts(storage, tranche_id, amount)? {
+        record_payout(
+            storage,
+            env,
+            sender,
+            WithdrawalKind::Unlocked,
+            Some(tranche_id),
+            &recipient,
+            share,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps<SeiQueryWrapper>, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::ListProposals {} => to_json_binary(&query_proposals(deps, env)?),
+        QueryMsg::ListVotes { proposal_id } => to_json_binary(&query_votes(deps, proposal_id)?),
+        QueryMsg::ListAdmins {} => to_json_binary(&query_admins(deps)?),
+        QueryMsg::ListOps {} => to_json_binary(&query_ops(deps)?),
+        QueryMsg::ListAllowedValidators {} => to_json_binary(&query_allowed_validators(deps)?),
+        QueryMsg::RewardRecipients {} => to_json_binary(&query_reward_recipients(deps)?),
+        QueryMsg::Info {} => to_json_binary(&query_info(deps, DEFAULT_TRANCHE_ID)?),
+        QueryMsg::TrancheInfo { tranche_id } => to_json_binary(&query_info(deps, &tranche_id)?),
+        QueryMsg::ListTranches {} => to_json_binary(&query_tranches(deps)?),
+        QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
+        QueryMsg::TotalVested {} => {
+            to_json_binary(&query_total_vested(deps, env, DEFAULT_TRANCHE_ID)?)
+        }
+        QueryMsg::TrancheTotalVested { tranche_id } => {
+            to_json_binary(&query_total_vested(deps, env, &tranche_id)?)
+        }
+        QueryMsg::ExpandScheduleSpec { spec } => to_json_binary(&query_expand_schedule_spec(spec)?),
+        QueryMsg::VestingSchedule {
+            tranche_id,
+            start_after,
+            limit,
+        } => to_json_binary(&query_vesting_schedule(
+            deps,
+            &tranche_id.unwrap_or_else(|| DEFAULT_TRANCHE_ID.to_string()),
+            start_after,
+            limit,
+        )?),
+        QueryMsg::NextUnlock { tranche_id } => to_json_binary(&query_next_unlock(
+            deps,
+            env,
+            &tranche_id.unwrap_or_else(|| DEFAULT_TRANCHE_ID.to_string()),
+        )?),
+        QueryMsg::WithdrawalHistory {
+            start_after,
+            limit,
+            kind,
+        } => to_json_binary(&query_withdrawal_history(deps, start_after, limit, kind)?),
+        QueryMsg::VestedAt { tranche_id, at } => to_json_binary(&query_vested_at(
+            deps,
+            &tranche_id.unwrap_or_else(|| DEFAULT_TRANCHE_ID.to_string()),
+            at,
+        )?),
+        QueryMsg::PendingWithdrawals { start_after, limit } => {
+            to_json_binary(&query_pending_withdrawals(deps, start_after, limit)?)
+        }
+        QueryMsg::Milestones { tranche_id } => to_json_binary(&query_milestones(
+            deps,
+            env,
+            &tranche_id.unwrap_or_else(|| DEFAULT_TRANCHE_ID.to_string()),
+        )?),
+        QueryMsg::Reconcile {} => to_json_binary(&query_reconcile(deps, env)?),
+        QueryMsg::PrincipalLedger {} => to_json_binary(&query_principal_ledger(deps, env)?),
+        QueryMsg::Portfolio {} => to_json_binary(&query_portfolio(deps, env)?),
+    }
+}
+
+fn query_proposals(deps: Deps<SeiQueryWrapper>, env: Env) -> StdResult<ProposalListResponse> {
+    let proposals: Vec<ProposalResponse> = PROPOSALS
+        .range(deps.storage, None, None, Order::Descending)
+        .map(|p| map_proposal(&env.block, p))
+        .collect::<StdResult<_>>()?;
+    Ok(ProposalListResponse { proposals })
+}
+
+fn map_proposal(
+    block: &BlockInfo,
+    item: StdResult<(u64, Proposal)>,
+) -> StdResult<ProposalResponse> {
+    item.map(|(id, prop)| {
+        let status = prop.current_status(block);
+        let threshold = prop.threshold.to_response(prop.total_weight);
+        ProposalResponse {
+            id,
+            title: prop.title,
+            description: prop.description,
+            msgs: prop.msgs,
+            status,
+            deposit: prop.deposit,
+            proposer: prop.proposer,
+            expires: prop.expires,
+            threshold,
+        }
+    })
+}
+
+fn query_votes(deps: Deps<SeiQueryWrapper>, proposal_id: u64) -> StdResult<VoteListResponse> {
+    let votes = BALLOTS
+        .prefix(proposal_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|(addr, ballot)| VoteInfo {
+                proposal_id,
+                voter: addr.into(),
+                vote: ballot.vote,
+                weight: ballot.weight,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(VoteListResponse { votes })
 }
 
 fn query_admins(deps: Deps<SeiQueryWrapper>) -> StdResult<AdminListResponse> {
@@ -838,915 +2660,3631 @@ fn query_ops(deps: Deps<SeiQueryWrapper>) -> StdResult<OpListResponse> {
     Ok(OpListResponse { ops })
 }
 
-fn query_info(deps: Deps<SeiQueryWrapper>) -> StdResult<ShowInfoResponse> {
-    Ok(ShowInfoResponse {
-        denom: DENOM.load(deps.storage)?,
-        vesting_timestamps: VESTING_TIMESTAMPS.load(deps.storage)?,
-        vesting_amounts: VESTING_AMOUNTS.load(deps.storage)?,
-        unlock_distribution_address: UNLOCK_DISTRIBUTION_ADDRESS.load(deps.storage)?,
-        staking_reward_address: STAKING_REWARD_ADDRESS.load(deps.storage)?,
-        withdrawn_staking_rewards: WITHDRAWN_STAKING_REWARDS.load(deps.storage)?,
-        withdrawn_unlocked: WITHDRAWN_UNLOCKED.load(deps.storage)?,
-        withdrawn_locked: WITHDRAWN_LOCKED.load(deps.storage)?,
-    })
-}
+fn query_reward_recipients(deps: Deps<SeiQueryWrapper>) -> StdResult<RewardRecipientsResponse> {
+    let recipients = WITHDRAWN_REWARDS_BY_RECIPIENT
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|(address, withdrawn)| RewardRecipientEntry {
+                address,
+                withdrawn: withdrawn.into(),
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(RewardRecipientsResponse { recipients })
+}
+
+fn query_allowed_validators(
+    deps: Deps<SeiQueryWrapper>,
+) -> StdResult<AllowedValidatorListResponse> {
+    let validators = ALLOWED_VALIDATORS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    Ok(AllowedValidatorListResponse { validators })
+}
+
+fn query_info(deps: Deps<SeiQueryWrapper>, tranche_id: &str) -> StdResult<ShowInfoResponse> {
+    if !TOTAL_AMOUNT.has(deps.storage, tranche_id) {
+        return Err(StdError::not_found(format!("tranche {}", tranche_id)));
+    }
+    let (vesting_timestamps, vesting_amounts) = load_schedule(deps.storage, tranche_id)?;
+    Ok(ShowInfoResponse {
+        denom: tranche_denom(deps.storage, tranche_id)?,
+        cw20_token: CW20_TOKEN.may_load(deps.storage, tranche_id)?,
+        schedule_unit: schedule_unit(deps.storage, tranche_id)?,
+        vesting_timestamps,
+        vesting_amounts: vesting_amounts.into_iter().map(Uint128::new).collect(),
+        linear_vesting: LINEAR_VESTING.may_load(deps.storage, tranche_id)?,
+        unlock_distribution_address: UNLOCK_DISTRIBUTION_ADDRESS.load(deps.storage, tranche_id)?,
+        unlock_distribution_split: UNLOCK_DISTRIBUTION_SPLIT.may_load(deps.storage, tranche_id)?,
+        staking_reward_address: STAKING_REWARD_ADDRESS.load(deps.storage)?,
+        staking_reward_split: STAKING_REWARD_SPLIT.may_load(deps.storage)?,
+        withdrawn_staking_rewards: WITHDRAWN_STAKING_REWARDS.load(deps.storage)?.into(),
+        compounded_staking_rewards: COMPOUNDED_REWARDS
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+            .into(),
+        withdrawn_unlocked: WITHDRAWN_UNLOCKED.load(deps.storage, tranche_id)?.into(),
+        withdrawn_locked: WITHDRAWN_LOCKED.load(deps.storage, tranche_id)?.into(),
+    })
+}
+
+fn query_tranches(deps: Deps<SeiQueryWrapper>) -> StdResult<TrancheListResponse> {
+    Ok(TrancheListResponse {
+        tranche_ids: get_tranche_ids(deps.storage)?,
+    })
+}
+
+fn query_config(deps: Deps<SeiQueryWrapper>) -> StdResult<ShowConfigResponse> {
+    Ok(ShowConfigResponse {
+        max_voting_period: MAX_VOTING_PERIOD.load(deps.storage)?,
+        admin_voting_threshold: ADMIN_VOTING_THRESHOLD.load(deps.storage)?,
+        permissionless_release: PERMISSIONLESS_RELEASE
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        undelegate_for_withdrawals: UNDELEGATE_FOR_WITHDRAWALS
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        validation_policy: validation_policy(deps.storage)?,
+        delegation_cap: DELEGATION_CAP.may_load(deps.storage)?,
+        reward_policy: REWARD_POLICY.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
+
+fn query_total_vested(
+    deps: Deps<SeiQueryWrapper>,
+    env: Env,
+    tranche_id: &str,
+) -> StdResult<ShowTotalVestedResponse> {
+    let unit = schedule_unit(deps.storage, tranche_id)?;
+    query_vested_at(deps, tranche_id, unit.point(unit.now(&env)))
+}
+
+fn query_vested_at(
+    deps: Deps<SeiQueryWrapper>,
+    tranche_id: &str,
+    at: SchedulePoint,
+) -> StdResult<ShowTotalVestedResponse> {
+    if !TOTAL_AMOUNT.has(deps.storage, tranche_id) {
+        return Err(StdError::not_found(format!("tranche {}", tranche_id)));
+    }
+    if at.unit() != schedule_unit(deps.storage, tranche_id)? {
+        return Err(StdError::generic_err(format!(
+            "tranche {} is not scheduled in {:?}",
+            tranche_id,
+            at.unit()
+        )));
+    }
+    let vested_amount = total_vested_amount(deps.storage, tranche_id, at.position())?;
+    Ok(ShowTotalVestedResponse {
+        vested_amount: vested_amount.into(),
+        cw20_token: CW20_TOKEN.may_load(deps.storage, tranche_id)?,
+    })
+}
+
+fn query_vesting_schedule(
+    deps: Deps<SeiQueryWrapper>,
+    tranche_id: &str,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<VestingScheduleResponse> {
+    if !TOTAL_AMOUNT.has(deps.storage, tranche_id) {
+        return Err(StdError::not_found(format!("tranche {}", tranche_id)));
+    }
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let cursor = SCHEDULE_CURSOR
+        .may_load(deps.storage, tranche_id)?
+        .unwrap_or_default();
+    // cumulative amounts are stored relative to the last `save_schedule`, so rebase them onto the
+    // remaining schedule
+    let base = match VESTING_SCHEDULE.may_load(deps.storage, (tranche_id, cursor.first))? {
+        Some(first) => first.cumulative - first.amount + cursor.withdrawn_from_first,
+        None => 0,
+    };
+    let min = match start_after {
+        Some(start_after) => Bound::exclusive(start_after),
+        None => Bound::inclusive(cursor.first),
+    };
+    let events = VESTING_SCHEDULE
+        .prefix(tranche_id)
+        .range(deps.storage, Some(min), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (index, entry) = item?;
+            let mut amount = entry.amount;
+            if index == cursor.first {
+                amount -= cursor.withdrawn_from_first;
+            }
+            Ok(VestingEvent {
+                index,
+                timestamp: entry.point.position(),
+                amount: amount.into(),
+                cumulative_amount: (entry.cumulative - base).into(),
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(VestingScheduleResponse {
+        schedule_unit: schedule_unit(deps.storage, tranche_id)?,
+        events,
+    })
+}
+
+fn query_next_unlock(
+    deps: Deps<SeiQueryWrapper>,
+    env: Env,
+    tranche_id: &str,
+) -> StdResult<NextUnlockResponse> {
+    if !TOTAL_AMOUNT.has(deps.storage, tranche_id) {
+        return Err(StdError::not_found(format!("tranche {}", tranche_id)));
+    }
+    let unit = schedule_unit(deps.storage, tranche_id)?;
+    let (timestamp, amount) = match next_unlock(deps.storage, tranche_id, unit.now(&env))? {
+        Some((timestamp, amount)) => (Some(timestamp), amount),
+        None => (None, 0),
+    };
+    Ok(NextUnlockResponse {
+        schedule_unit: unit,
+        timestamp,
+        amount: amount.into(),
+    })
+}
+
+fn query_pending_withdrawals(
+    deps: Deps<SeiQueryWrapper>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PendingWithdrawalsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let withdrawals = PENDING_WITHDRAWALS
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(id, withdrawal)| PendingWithdrawalEntry { id, withdrawal }))
+        .collect::<StdResult<_>>()?;
+    Ok(PendingWithdrawalsResponse { withdrawals })
+}
+
+fn reconcile_tranche(storage: &dyn Storage, tranche_id: &str) -> StdResult<TrancheReconciliation> {
+    let remaining_amount = remaining_amount(storage, tranche_id)?;
+    let withdrawn_unlocked = WITHDRAWN_UNLOCKED.load(storage, tranche_id)?;
+    let withdrawn_locked = WITHDRAWN_LOCKED.load(storage, tranche_id)?;
+    Ok(TrancheReconciliation {
+        tranche_id: tranche_id.to_string(),
+        cw20_token: CW20_TOKEN.may_load(storage, tranche_id)?,
+        total_amount: TOTAL_AMOUNT.load(storage, tranche_id)?.into(),
+        remaining_amount: remaining_amount.into(),
+        withdrawn_unlocked: withdrawn_unlocked.into(),
+        withdrawn_locked: withdrawn_locked.into(),
+        accounted_amount: (remaining_amount + withdrawn_unlocked + withdrawn_locked).into(),
+    })
+}
+
+// CW20 tranches are reported, but their holdings are not checked
+fn query_reconcile(deps: Deps<SeiQueryWrapper>, env: Env) -> StdResult<ReconcileResponse> {
+    let mut tranches = vec![];
+    let mut expected_holdings = total_pending_withdrawals(deps.storage)?;
+    for tranche_id in get_tranche_ids(deps.storage)? {
+        let reconciliation = reconcile_tranche(deps.storage, &tranche_id)?;
+        if reconciliation.cw20_token.is_none() {
+            expected_holdings += reconciliation.remaining_amount.u128();
+        }
+        tranches.push(reconciliation);
+    }
+    let bank_balance = deps
+        .querier
+        .query_balance(env.contract.address.clone(), DENOM.load(deps.storage)?)?
+        .amount
+        .u128();
+    let staked = staked_balance(deps, &env)?;
+    let unbonding = match get_unbonding_balance(deps, env.clone()) {
+        Ok(unbonding) => unbonding,
+        Err(_) => total_unbonding_principal(deps.storage, env.block.time)?,
+    };
+    let holdings = bank_balance + staked + unbonding;
+    Ok(ReconcileResponse {
+        tranches,
+        expected_holdings: expected_holdings.into(),
+        bank_balance: bank_balance.into(),
+        staked: staked.into(),
+        unbonding: unbonding.into(),
+        surplus: holdings.saturating_sub(expected_holdings).into(),
+        shortfall: expected_holdings.saturating_sub(holdings).into(),
+    })
+}
+
+fn query_principal_ledger(
+    deps: Deps<SeiQueryWrapper>,
+    env: Env,
+) -> StdResult<PrincipalLedgerResponse> {
+    let delegations: Vec<(String, Uint128)> = DELEGATED_PRINCIPAL
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(validator, amount)| (validator, amount.into())))
+        .collect::<StdResult<_>>()?;
+    let unbonding = UNBONDING_PRINCIPAL
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| !matches!(item, Ok((_, entry)) if entry.completion_time <= env.block.time))
+        .map(|item| item.map(|(_, entry)| entry))
+        .collect::<StdResult<_>>()?;
+    Ok(PrincipalLedgerResponse {
+        delegations,
+        unbonding,
+        delegated: total_delegated_principal(deps.storage)?.into(),
+        unbonding_total: total_unbonding_principal(deps.storage, env.block.time)?.into(),
+        liquid: liquid_principal(deps.storage, &env)?.into(),
+        reported_unbonding: get_unbonding_balance(deps, env).ok().map(Uint128::new),
+    })
+}
+
+fn query_portfolio(deps: Deps<SeiQueryWrapper>, env: Env) -> StdResult<PortfolioResponse> {
+    let mut delegations = vec![];
+    for (validator, amount) in delegated_amounts(deps, &env)? {
+        let pending_rewards = get_delegation_rewards(deps, env.clone(), validator.clone())?;
+        delegations.push(PortfolioDelegation {
+            validator,
+            amount: amount.into(),
+            pending_rewards: pending_rewards.into(),
+        });
+    }
+    let delegated_principal = total_delegated_principal(deps.storage)?;
+    let unbonding_principal = total_unbonding_principal(deps.storage, env.block.time)?;
+    let liquid_principal = liquid_principal(deps.storage, &env)?;
+    let principal = delegated_principal + unbonding_principal + liquid_principal;
+    let staked_principal_share = if principal == 0 {
+        Decimal::zero()
+    } else {
+        Decimal::from_ratio(delegated_principal, principal)
+    };
+    Ok(PortfolioResponse {
+        total_delegated: delegations.iter().map(|d| d.amount).sum(),
+        total_pending_rewards: delegations.iter().map(|d| d.pending_rewards).sum(),
+        delegations,
+        unbonding: get_unbonding_entries(deps, env.clone()).ok(),
+        liquid_principal: liquid_principal.into(),
+        liquid_rewards: calculate_withdrawn_rewards(deps, env)?.into(),
+        staked_principal_share,
+    })
+}
+
+fn query_milestones(
+    deps: Deps<SeiQueryWrapper>,
+    env: Env,
+    tranche_id: &str,
+) -> StdResult<MilestonesResponse> {
+    if !TOTAL_AMOUNT.has(deps.storage, tranche_id) {
+        return Err(StdError::not_found(format!("tranche {}", tranche_id)));
+    }
+    let now = schedule_now(deps.storage, tranche_id, &env)?;
+    let milestones = MILESTONES
+        .prefix(tranche_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|(milestone_id, milestone)| MilestoneEntry {
+                milestone_id,
+                lapsed: milestone.is_lapsed(now),
+                milestone,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(MilestonesResponse { milestones })
+}
+
+fn query_withdrawal_history(
+    deps: Deps<SeiQueryWrapper>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    kind: Option<WithdrawalKind>,
+) -> StdResult<WithdrawalHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let min = start_after.map(Bound::exclusive);
+    let withdrawals = match kind {
+        Some(kind) => WITHDRAWALS_BY_KIND
+            .prefix(kind.key())
+            .keys(deps.storage, min, None, Order::Ascending)
+            .take(limit)
+            .map(|id| {
+                let id = id?;
+                let withdrawal = WITHDRAWALS.load(deps.storage, id)?;
+                Ok(WithdrawalEntry { id, withdrawal })
+            })
+            .collect::<StdResult<_>>()?,
+        None => WITHDRAWALS
+            .range(deps.storage, min, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(id, withdrawal)| WithdrawalEntry { id, withdrawal }))
+            .collect::<StdResult<_>>()?,
+    };
+    Ok(WithdrawalHistoryResponse { withdrawals })
+}
+
+fn query_expand_schedule_spec(
+    spec: VestingScheduleSpec,
+) -> StdResult<ShowExpandedScheduleResponse> {
+    let (vesting_timestamps, vesting_amounts) =
+        expand_schedule_spec(&spec).map_err(|e| StdError::generic_err(e.to_string()))?;
+    Ok(ShowExpandedScheduleResponse {
+        vesting_timestamps,
+        vesting_amounts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+    use cosmwasm_std::testing::{
+        mock_env, mock_info, MockApi, MockQuerier, MockQuerierCustomHandlerResult, MockStorage,
+        MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{
+        from_json, to_json_binary, Addr, Coin, ContractResult, Decimal, DistributionMsg,
+        FullDelegation, OwnedDeps, StakingMsg, SystemResult, Timestamp, Uint128, Validator,
+    };
+
+    use cw2::{get_contract_version, ContractVersion};
+    use cw20::Cw20ExecuteMsg;
+    use cw_utils::{Duration, Expiration, ThresholdResponse};
+
+    use crate::data_structure::{
+        Clawback, LinearVesting, Milestone, RemainderHandling, SchedulePoint, Tranche,
+        UnbondingPrincipal, ValidationPolicy, VestingPeriod,
+    };
+    use crate::msg::{SeiQueryWrapper, UnbondingDelegationEntry, UnbondingDelegationsResponse};
+    use crate::state::{get_number_of_ops, unbonding_period};
+
+    use super::*;
+
+    const OWNER: &str = "admin0001";
+    const VOTER1: &str = "voter0001";
+    const VOTER2: &str = "voter0002";
+    const VOTER3: &str = "voter0003";
+    const VOTER4: &str = "voter0004";
+    const VOTER5: &str = "voter0005";
+    const VOTER6: &str = "voter0006";
+
+    const UNLOCK_ADDR1: &str = "unlock0001";
+    const REWARD_ADDR1: &str = "reward0001";
+
+    fn mock_dependencies(
+    ) -> OwnedDeps<MockStorage, MockApi, MockQuerier<SeiQueryWrapper>, SeiQueryWrapper> {
+        OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: MockQuerier::new(&[]),
+            custom_query_type: PhantomData::default(),
+        }
+    }
+
+    // this will set up the instantiation for other tests
+    #[track_caller]
+    fn setup_test_case(
+        deps: DepsMut<SeiQueryWrapper>,
+        info: MessageInfo,
+    ) -> Result<Response<Empty>, ContractError> {
+        let env = mock_env();
+        let mut vesting_amounts = vec![Uint128::new(12000000)];
+        let mut vesting_timestamps = vec![env.block.time.plus_seconds(31536000)];
+        for _ in 1..37 {
+            vesting_amounts.push(Uint128::new(1000000));
+            vesting_timestamps.push(vesting_timestamps.last().unwrap().plus_seconds(2592000));
+        }
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![
+                Addr::unchecked(VOTER1),
+                Addr::unchecked(VOTER2),
+                Addr::unchecked(VOTER3),
+                Addr::unchecked(VOTER4),
+            ],
+            ops: vec![Addr::unchecked(VOTER5), Addr::unchecked(VOTER6)],
+            tranche: test_tranche(vesting_amounts, vesting_timestamps),
+            max_voting_period: Duration::Time(3600),
+            admin_voting_threshold_percentage: 75,
+            validation_policy: None,
+            unbonding_period: None,
+        };
+        instantiate(deps, mock_env(), info, instantiate_msg)
+    }
+
+    const CW20_TOKEN_ADDR: &str = "cw20token";
+
+    fn cw20_tranche(amount: u128) -> Tranche {
+        Tranche {
+            denom: CW20_TOKEN_ADDR.to_string(),
+            cw20_token: Some(Addr::unchecked(CW20_TOKEN_ADDR)),
+            ..named_tranche(amount)
+        }
+    }
+
+    fn receive_msg(sender: &str, amount: u128, msg: &ReceiveMsg) -> ExecuteMsg {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: sender.to_string(),
+            amount: Uint128::new(amount),
+            msg: to_json_binary(msg).unwrap(),
+        })
+    }
+
+    fn test_tranche(vesting_amounts: Vec<Uint128>, vesting_timestamps: Vec<Timestamp>) -> Tranche {
+        Tranche {
+            denom: "usei".to_string(),
+            vesting_amounts,
+            vesting_timestamps,
+            unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
+            staking_reward_distribution_address: Addr::unchecked(REWARD_ADDR1),
+            linear_vesting: None,
+            schedule_spec: None,
+            cw20_token: None,
+            schedule_unit: None,
+            milestones: None,
+        }
+    }
+
+    fn named_tranche(amount: u128) -> Tranche {
+        Tranche {
+            unlocked_token_distribution_address: Addr::unchecked("unlock0002"),
+            ..test_tranche(
+                vec![Uint128::new(amount)],
+                vec![mock_env().block.time.plus_seconds(100)],
+            )
+        }
+    }
+
+    #[test]
+    fn test_instantiate_works() {
+        let mut deps = mock_dependencies();
+        let info = mock_info(OWNER, &[]);
+
+        let _max_voting_period = Duration::Time(1234567);
+
+        // No admins fails
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![],
+            ops: vec![Addr::unchecked(VOTER5)],
+            tranche: test_tranche(vec![Uint128::new(1)], vec![mock_env().block.time]),
+            max_voting_period: Duration::Time(3600),
+            admin_voting_threshold_percentage: 75,
+            validation_policy: None,
+            unbonding_period: None,
+        };
+        let err =
+            instantiate(deps.as_mut(), mock_env(), info.clone(), instantiate_msg).unwrap_err();
+        assert_eq!(err, ContractError::NoAdmins {});
+
+        // Zero ops fails
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![Addr::unchecked(VOTER1)],
+            ops: vec![],
+            tranche: test_tranche(vec![Uint128::new(1)], vec![mock_env().block.time]),
+            max_voting_period: Duration::Time(3600),
+            admin_voting_threshold_percentage: 75,
+            validation_policy: None,
+            unbonding_period: None,
+        };
+        let err =
+            instantiate(deps.as_mut(), mock_env(), info.clone(), instantiate_msg).unwrap_err();
+        assert_eq!(err, ContractError::NoOps {},);
+
+        // Invalid vesting schedule
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![Addr::unchecked(VOTER1)],
+            ops: vec![Addr::unchecked(VOTER5)],
+            tranche: test_tranche(vec![], vec![mock_env().block.time]),
+            max_voting_period: Duration::Time(3600),
+            admin_voting_threshold_percentage: 75,
+            validation_policy: None,
+            unbonding_period: None,
+        };
+        let err =
+            instantiate(deps.as_mut(), mock_env(), info.clone(), instantiate_msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidTranche("mismatched vesting amounts and schedule".to_string()),
+        );
+
+        // insufficient funds
+        let err = setup_test_case(deps.as_mut(), info).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidTranche("insufficient deposit for the vesting plan".to_string()),
+        );
+
+        // happy path
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info).unwrap();
+
+        // Verify
+        assert_eq!(
+            ContractVersion {
+                contract: CONTRACT_NAME.to_string(),
+                version: CONTRACT_VERSION.to_string(),
+            },
+            get_contract_version(&deps.storage).unwrap()
+        )
+    }
+
+    #[test]
+    fn delegate_work() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let msg = ExecuteMsg::Delegate {
+            validator: "val".to_string(),
+            amount: Uint128::new(100),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(1, res.messages.len());
+    }
+
+    #[test]
+    fn delegate_unauthorized() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let msg = ExecuteMsg::Delegate {
+            validator: "val".to_string(),
+            amount: Uint128::new(100),
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    }
+
+    #[test]
+    fn redelegate_work() {
+        let mut deps = staked_deps(&[("val1", 100)]);
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let msg = ExecuteMsg::Redelegate {
+            src_validator: "val1".to_string(),
+            dst_validator: "val2".to_string(),
+            amount: Uint128::new(100),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(1, res.messages.len());
+    }
+
+    #[test]
+    fn redelegate_unauthorized() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let msg = ExecuteMsg::Redelegate {
+            src_validator: "val1".to_string(),
+            dst_validator: "val2".to_string(),
+            amount: Uint128::new(100),
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    }
+
+    #[test]
+    fn undelegate_work() {
+        let mut deps = staked_deps(&[("val1", 100)]);
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        // cannot undelegate more than is delegated
+        let msg = ExecuteMsg::Undelegate {
+            validator: "val1".to_string(),
+            amount: Uint128::new(101),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidDelegation("101 exceeds the 100 delegated to val1".to_string())
+        );
+
+        let msg = ExecuteMsg::Undelegate {
+            validator: "val1".to_string(),
+            amount: Uint128::new(100),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(1, res.messages.len());
+    }
+
+    #[test]
+    fn undelegate_unauthorized() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let msg = ExecuteMsg::Undelegate {
+            validator: "val".to_string(),
+            amount: Uint128::new(100),
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    }
+
+    fn staked_deps(
+        delegations: &[(&str, u128)],
+    ) -> OwnedDeps<MockStorage, MockApi, MockQuerier<SeiQueryWrapper>, SeiQueryWrapper> {
+        let mut deps = mock_dependencies();
+        let validators: Vec<Validator> = ["val1", "val2", "val3"]
+            .iter()
+            .map(|address| Validator {
+                address: address.to_string(),
+                commission: Decimal::zero(),
+                max_commission: Decimal::zero(),
+                max_change_rate: Decimal::zero(),
+            })
+            .collect();
+        let delegations: Vec<FullDelegation> = delegations
+            .iter()
+            .map(|(validator, amount)| FullDelegation {
+                delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
+                validator: validator.to_string(),
+                amount: Coin::new(*amount, "usei"),
+                can_redelegate: Coin::new(*amount, "usei"),
+                accumulated_rewards: vec![],
+            })
+            .collect();
+        deps.querier
+            .update_staking("usei", &validators, &delegations);
+        deps
+    }
+
+    #[test]
+    fn delegate_many_work() {
+        let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(48000000, "usei"));
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let msg = ExecuteMsg::DelegateMany {
+            delegations: vec![
+                ("val1".to_string(), Uint128::new(100)),
+                ("val2".to_string(), Uint128::new(200)),
+            ],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages
+                .iter()
+                .map(|msg| msg.msg.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                CosmosMsg::Staking(StakingMsg::Delegate {
+                    validator: "val1".to_string(),
+                    amount: Coin::new(100, "usei"),
+                }),
+                CosmosMsg::Staking(StakingMsg::Delegate {
+                    validator: "val2".to_string(),
+                    amount: Coin::new(200, "usei"),
+                }),
+            ]
+        );
+        assert_eq!(
+            res.attributes
+                .iter()
+                .map(|attr| (attr.key.as_str(), attr.value.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("action", "delegate_many"),
+                ("total", "300"),
+                ("validator", "val1"),
+                ("amount", "100"),
+                ("validator", "val2"),
+                ("amount", "200"),
+            ]
+        );
+
+        // withdrawn rewards in the balance are not principal
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(48000000 + 1000, "usei"));
+        let msg = ExecuteMsg::DelegateMany {
+            delegations: vec![
+                ("val1".to_string(), Uint128::new(40000000)),
+                ("val2".to_string(), Uint128::new(7999701)),
+            ],
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidDelegation(
+                "47999701 exceeds the liquid principal of 47999700".to_string()
+            )
+        );
+
+        let msg = ExecuteMsg::DelegateMany {
+            delegations: vec![("val1".to_string(), Uint128::zero())],
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        let msg = ExecuteMsg::DelegateMany {
+            delegations: vec![],
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        let msg = ExecuteMsg::DelegateMany {
+            delegations: vec![
+                ("val1".to_string(), Uint128::MAX),
+                ("val2".to_string(), Uint128::new(1)),
+            ],
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidDelegation("amounts overflow".to_string())
+        );
+    }
+
+    #[test]
+    fn delegate_many_unauthorized() {
+        let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(48000000, "usei"));
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let msg = ExecuteMsg::DelegateMany {
+            delegations: vec![("val1".to_string(), Uint128::new(100))],
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn undelegate_many_work() {
+        let mut deps = staked_deps(&[("val1", 1000), ("val2", 2000)]);
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let msg = ExecuteMsg::UndelegateMany {
+            undelegations: vec![
+                ("val1".to_string(), Uint128::new(1000)),
+                ("val2".to_string(), Uint128::new(500)),
+            ],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(2, res.messages.len());
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Staking(StakingMsg::Undelegate {
+                validator: "val2".to_string(),
+                amount: Coin::new(500, "usei"),
+            })
+        );
+
+        // amounts are checked per validator, across entries
+        let msg = ExecuteMsg::UndelegateMany {
+            undelegations: vec![
+                ("val2".to_string(), Uint128::new(1500)),
+                ("val1".to_string(), Uint128::new(100)),
+                ("val2".to_string(), Uint128::new(501)),
+            ],
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidDelegation("2001 exceeds the 2000 delegated to val2".to_string())
+        );
+
+        let msg = ExecuteMsg::UndelegateMany {
+            undelegations: vec![("val3".to_string(), Uint128::new(1))],
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    }
+
+    #[test]
+    fn redelegate_many_work() {
+        let mut deps = staked_deps(&[("val1", 1000)]);
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let redelegation = |dst_validator: &str, amount: u128| Redelegation {
+            src_validator: "val1".to_string(),
+            dst_validator: dst_validator.to_string(),
+            amount: Uint128::new(amount),
+        };
+        let msg = ExecuteMsg::RedelegateMany {
+            redelegations: vec![redelegation("val2", 400), redelegation("val3", 600)],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Staking(StakingMsg::Redelegate {
+                src_validator: "val1".to_string(),
+                dst_validator: "val3".to_string(),
+                amount: Coin::new(600, "usei"),
+            })
+        );
+        assert_eq!(
+            res.attributes
+                .iter()
+                .filter(|attr| attr.key == "dst_validator")
+                .count(),
+            2
+        );
+
+        let msg = ExecuteMsg::RedelegateMany {
+            redelegations: vec![redelegation("val2", 400), redelegation("val3", 601)],
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    }
+
+    #[test]
+    fn test_validator_allowlist() {
+        let mut deps = staked_deps(&[("val2", 100)]);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(48000000, "usei"));
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let delegate = |validator: &str| ExecuteMsg::Delegate {
+            validator: validator.to_string(),
+            amount: Uint128::new(100),
+        };
+        // an empty allowlist places no restriction
+        execute(deps.as_mut(), mock_env(), info.clone(), delegate("val2")).unwrap();
+
+        let msg = ExecuteMsg::ProposeUpdateAllowedValidator {
+            validator: "val1".to_string(),
+            remove: false,
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+        execute(deps.as_mut(), mock_env(), mock_info(VOTER1, &[]), msg).unwrap();
+        let msg = ExecuteMsg::InternalUpdateAllowedValidator {
+            validator: "val1".to_string(),
+            remove: false,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let self_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        execute(deps.as_mut(), mock_env(), self_info.clone(), msg).unwrap();
+
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ListAllowedValidators {},
+        )
+        .unwrap();
+        let res: AllowedValidatorListResponse = from_json(&bin).unwrap();
+        assert_eq!(res.validators, vec!["val1".to_string()]);
+
+        execute(deps.as_mut(), mock_env(), info.clone(), delegate("val1")).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), delegate("val2")).unwrap_err();
+        assert_eq!(err, ContractError::ValidatorNotAllowed("val2".to_string()));
+        let msg = ExecuteMsg::Redelegate {
+            src_validator: "val1".to_string(),
+            dst_validator: "val2".to_string(),
+            amount: Uint128::new(100),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::ValidatorNotAllowed("val2".to_string()));
+        let msg = ExecuteMsg::DelegateMany {
+            delegations: vec![
+                ("val1".to_string(), Uint128::new(100)),
+                ("val2".to_string(), Uint128::new(100)),
+            ],
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::ValidatorNotAllowed("val2".to_string()));
+        // moving stake away from a validator is not restricted
+        let msg = ExecuteMsg::Undelegate {
+            validator: "val2".to_string(),
+            amount: Uint128::new(100),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::InternalUpdateAllowedValidator {
+            validator: "val1".to_string(),
+            remove: true,
+        };
+        execute(deps.as_mut(), mock_env(), self_info, msg).unwrap();
+        execute(deps.as_mut(), mock_env(), info, delegate("val2")).unwrap();
+    }
+
+    #[test]
+    fn test_delegation_cap() {
+        let mut deps = staked_deps(&[("val1", 9000000)]);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(39000000, "usei"));
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let msg = ExecuteMsg::ProposeUpdateDelegationCap {
+            cap: Some(DelegationCap::Percentage { percentage: 101 }),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(VOTER1, &[]), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidDelegation(
+                "cap percentage must be between 1 and 100".to_string()
+            )
+        );
+        let cap = Some(DelegationCap::Percentage { percentage: 20 });
+        let msg = ExecuteMsg::ProposeUpdateDelegationCap { cap: cap.clone() };
+        execute(deps.as_mut(), mock_env(), mock_info(VOTER1, &[]), msg).unwrap();
+        let msg = ExecuteMsg::InternalUpdateDelegationCap { cap: cap.clone() };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let self_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        execute(deps.as_mut(), mock_env(), self_info.clone(), msg).unwrap();
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let res: ShowConfigResponse = from_json(&bin).unwrap();
+        assert_eq!(res.delegation_cap, cap);
+
+        // 20% of 48000000 is 9600000, of which 9000000 is already delegated to val1
+        let delegate = |validator: &str, amount: u128| ExecuteMsg::Delegate {
+            validator: validator.to_string(),
+            amount: Uint128::new(amount),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            delegate("val1", 600000),
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            delegate("val1", 600001),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DelegationCapExceeded {
+                validator: "val1".to_string(),
+                delegated: 9600001,
+                cap: 9600000,
+            }
+        );
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            delegate("val2", 9600000),
+        )
+        .unwrap();
+        let msg = ExecuteMsg::Redelegate {
+            src_validator: "val2".to_string(),
+            dst_validator: "val1".to_string(),
+            amount: Uint128::new(700000),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        // batches are checked against the sum per validator
+        let msg = ExecuteMsg::DelegateMany {
+            delegations: vec![
+                ("val2".to_string(), Uint128::new(5000000)),
+                ("val3".to_string(), Uint128::new(5000000)),
+                ("val2".to_string(), Uint128::new(5000000)),
+            ],
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DelegationCapExceeded {
+                validator: "val2".to_string(),
+                delegated: 10000000,
+                cap: 9600000,
+            }
+        );
+
+        let msg = ExecuteMsg::InternalUpdateDelegationCap {
+            cap: Some(DelegationCap::Amount {
+                amount: Uint128::new(100),
+            }),
+        };
+        execute(deps.as_mut(), mock_env(), self_info.clone(), msg).unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            delegate("val2", 101),
+        )
+        .unwrap_err();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            delegate("val2", 100),
+        )
+        .unwrap();
+
+        let msg = ExecuteMsg::InternalUpdateDelegationCap { cap: None };
+        execute(deps.as_mut(), mock_env(), self_info, msg).unwrap();
+        execute(deps.as_mut(), mock_env(), info, delegate("val1", 10000000)).unwrap();
+    }
+
+    #[test]
+    fn initiate_withdraw_unlocked_work() {
+        let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(100000000, "usei"));
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let msg = ExecuteMsg::InitiateWithdrawUnlocked {
+            amount: Uint128::new(12000000),
+        };
+        let mut env = mock_env();
+        let mut block = env.block;
+        block.time = block.time.plus_seconds(31536000);
+        env.block = block;
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        assert_eq!(1, res.messages.len());
+        assert_eq!(
+            12000000,
+            WITHDRAWN_UNLOCKED
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn initiate_withdraw_unlocked_unauthorized() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let msg = ExecuteMsg::InitiateWithdrawUnlocked {
+            amount: Uint128::new(12000000),
+        };
+        let mut env = mock_env();
+        let mut block = env.block;
+        block.time = block.time.plus_seconds(31536000);
+        env.block = block;
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn test_withdraw_unlocked_undelegates_shortfall() {
+        let mut deps = mock_dependencies();
+        let validator = |address: &str| Validator {
+            address: address.to_string(),
+            commission: Decimal::zero(),
+            max_commission: Decimal::zero(),
+            max_change_rate: Decimal::zero(),
+        };
+        let delegation = |validator: &str, amount: u128| FullDelegation {
+            delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
+            validator: validator.to_string(),
+            amount: Coin::new(amount, "usei"),
+            can_redelegate: Coin::new(0, "usei"),
+            accumulated_rewards: vec![],
+        };
+        deps.querier.update_staking(
+            "usei",
+            &[validator("val1"), validator("val2")],
+            &[delegation("val1", 5000000), delegation("val2", 6000000)],
+        );
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(2000000, "usei"));
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(31536000);
+        let msg = ExecuteMsg::InitiateWithdrawUnlocked {
+            amount: Uint128::new(12000000),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InsufficientLiquidBalance {
+                shortfall: 10000000
+            }
+        );
+
+        let self_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let enable = ExecuteMsg::InternalUpdateUndelegateForWithdrawals { enabled: true };
+        execute(deps.as_mut(), env.clone(), self_info, enable).unwrap();
+
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages
+                .iter()
+                .map(|msg| msg.msg.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                CosmosMsg::Staking(StakingMsg::Undelegate {
+                    validator: "val2".to_string(),
+                    amount: Coin::new(6000000, "usei"),
+                }),
+                CosmosMsg::Staking(StakingMsg::Undelegate {
+                    validator: "val1".to_string(),
+                    amount: Coin::new(4000000, "usei"),
+                }),
+            ]
+        );
+        assert_eq!(
+            12000000,
+            WITHDRAWN_UNLOCKED
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
+
+        let msg = QueryMsg::PendingWithdrawals {
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg.clone()).unwrap();
+        let res: PendingWithdrawalsResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            res.withdrawals,
+            vec![PendingWithdrawalEntry {
+                id: 1,
+                withdrawal: PendingWithdrawal {
+                    tranche_id: DEFAULT_TRANCHE_ID.to_string(),
+                    recipient: Addr::unchecked(UNLOCK_ADDR1),
+                    amount: Uint128::new(12000000),
+                    time: env.block.time,
+                },
+            }]
+        );
+
+        // nothing is paid out before unbonding completes
+        let claim = ExecuteMsg::ClaimPendingWithdrawals {};
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), claim.clone()).unwrap();
+        assert!(res.messages.is_empty());
+
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(12000000, "usei"));
+        let res = execute(deps.as_mut(), env.clone(), info, claim).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: UNLOCK_ADDR1.to_string(),
+                amount: coins(12000000, "usei"),
+            })
+        );
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PendingWithdrawalsResponse = from_json(&bin).unwrap();
+        assert!(res.withdrawals.is_empty());
+        assert_eq!(
+            1,
+            WITHDRAWALS
+                .range(deps.as_ref().storage, None, None, Order::Ascending)
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_withdrawal_history() {
+        let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(100000000, "usei"));
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(31536000);
+        let msg = ExecuteMsg::InitiateWithdrawUnlocked {
+            amount: Uint128::new(12000000),
+        };
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let self_info = mock_info(mock_env().contract.address.as_str(), &[]);
+        let msg = ExecuteMsg::InternalWithdrawLocked {
+            dst: Addr::unchecked("destination"),
+            proposer: Some(Addr::unchecked(VOTER1)),
+        };
+        execute(deps.as_mut(), env.clone(), self_info, msg).unwrap();
+
+        let msg = QueryMsg::WithdrawalHistory {
+            start_after: None,
+            limit: None,
+            kind: None,
+        };
+        let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: WithdrawalHistoryResponse = from_json(&bin).unwrap();
+        assert_eq!(2, res.withdrawals.len());
+        assert_eq!(
+            WithdrawalEntry {
+                id: 1,
+                withdrawal: WithdrawalRecord {
+                    height: env.block.height,
+                    time: env.block.time,
+                    kind: WithdrawalKind::Unlocked,
+                    tranche_id: Some(DEFAULT_TRANCHE_ID.to_string()),
+                    denom: "usei".to_string(),
+                    amount: Uint128::new(12000000),
+                    recipient: Addr::unchecked(UNLOCK_ADDR1),
+                    sender: Addr::unchecked(VOTER5),
+                },
+            },
+            res.withdrawals[0]
+        );
+        assert_eq!(WithdrawalKind::Locked, res.withdrawals[1].withdrawal.kind);
+        assert_eq!(Uint128::new(36000000), res.withdrawals[1].withdrawal.amount);
+        assert_eq!(
+            Addr::unchecked("destination"),
+            res.withdrawals[1].withdrawal.recipient
+        );
+        // recorded as sent by the proposer, not the contract executing the proposal
+        assert_eq!(
+            Addr::unchecked(VOTER1),
+            res.withdrawals[1].withdrawal.sender
+        );
+
+        let msg = QueryMsg::WithdrawalHistory {
+            start_after: None,
+            limit: None,
+            kind: Some(WithdrawalKind::Locked),
+        };
+        let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: WithdrawalHistoryResponse = from_json(&bin).unwrap();
+        assert_eq!(1, res.withdrawals.len());
+        assert_eq!(2, res.withdrawals[0].id);
+
+        let msg = QueryMsg::WithdrawalHistory {
+            start_after: Some(1),
+            limit: None,
+            kind: Some(WithdrawalKind::Unlocked),
+        };
+        let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: WithdrawalHistoryResponse = from_json(&bin).unwrap();
+        assert!(res.withdrawals.is_empty());
+
+        let msg = QueryMsg::WithdrawalHistory {
+            start_after: Some(1),
+            limit: Some(1),
+            kind: None,
+        };
+        let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: WithdrawalHistoryResponse = from_json(&bin).unwrap();
+        assert_eq!(1, res.withdrawals.len());
+        assert_eq!(2, res.withdrawals[0].id);
+    }
+
+    #[test]
+    fn initiate_withdraw_unlocked_linear_work() {
+        let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(100000000, "usei"));
+
+        let env = mock_env();
+        let info = mock_info(VOTER5, &[Coin::new(1000, "usei".to_string())]);
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![Addr::unchecked(VOTER1)],
+            ops: vec![Addr::unchecked(VOTER5)],
+            tranche: Tranche {
+                linear_vesting: Some(LinearVesting {
+                    start: env.block.time,
+                    cliff: Some(env.block.time.plus_seconds(100)),
+                }),
+                ..test_tranche(
+                    vec![Uint128::new(1000)],
+                    vec![env.block.time.plus_seconds(1000)],
+                )
+            },
+            max_voting_period: Duration::Time(3600),
+            admin_voting_threshold_percentage: 75,
+            validation_policy: None,
+            unbonding_period: None,
+        };
+        instantiate(deps.as_mut(), env.clone(), info.clone(), instantiate_msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(99);
+        let msg = QueryMsg::TotalVested {};
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: ShowTotalVestedResponse = from_json(&bin).unwrap();
+        assert_eq!(res.vested_amount, Uint128::new(0));
+
+        env.block.time = env.block.time.plus_seconds(151);
+        let msg = QueryMsg::TotalVested {};
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: ShowTotalVestedResponse = from_json(&bin).unwrap();
+        assert_eq!(res.vested_amount, Uint128::new(250));
+
+        let msg = ExecuteMsg::InitiateWithdrawUnlocked {
+            amount: Uint128::new(250),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        assert_eq!(1, res.messages.len());
+        assert_eq!(
+            250,
+            WITHDRAWN_UNLOCKED
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
+        assert_eq!(
+            vec![750],
+            load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1
+        );
+
+        let msg = ExecuteMsg::InitiateWithdrawUnlocked {
+            amount: Uint128::new(1),
+        };
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(err, ContractError::NoSufficientUnlockedTokens {});
+    }
+
+    #[test]
+    fn initiate_withdraw_reward_work() {
+        let validator1 = "val1";
+        let validator2 = "val2";
+        let mut deps = mock_dependencies();
+        deps.querier.update_staking(
+            "usei",
+            &[
+                Validator {
+                    address: validator1.to_string(),
+                    commission: Decimal::zero(),
+                    max_commission: Decimal::zero(),
+                    max_change_rate: Decimal::zero(),
+                },
+                Validator {
+                    address: validator2.to_string(),
+                    commission: Decimal::zero(),
+                    max_commission: Decimal::zero(),
+                    max_change_rate: Decimal::zero(),
+                },
+            ],
+            &[
+                FullDelegation {
+                    delegator: Addr::unchecked(mock_env().contract.address),
+                    validator: validator1.to_string(),
+                    amount: Coin::new(1000000, "usei"),
+                    can_redelegate: Coin::new(0, "usei"),
+                    accumulated_rewards: vec![Coin::new(10, "usei"), Coin::new(20, "usei")],
+                },
+                FullDelegation {
+                    delegator: Addr::unchecked(mock_env().contract.address),
+                    validator: validator2.to_string(),
+                    amount: Coin::new(500000, "usei"),
+                    can_redelegate: Coin::new(0, "usei"),
+                    accumulated_rewards: vec![Coin::new(5, "usei")],
+                },
+            ],
+        );
+        deps.querier.update_balance(
+            mock_env().contract.address.clone(),
+            vec![Coin::new(48000000 - 1500000 + 100, "usei")],
+        );
+        // principal: 48000000 - 1500000 (delegations).
+        // Withdrawn rewards: balance - principal = 100
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        let msg = ExecuteMsg::DelegateMany {
+            delegations: vec![
+                (validator1.to_string(), Uint128::new(1000000)),
+                (validator2.to_string(), Uint128::new(500000)),
+            ],
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::InitiateWithdrawReward {};
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        // one withdrawal per validator, then a single payout
+        assert_eq!(3, res.messages.len());
+        assert_eq!(
+            res.messages[2].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: REWARD_ADDR1.to_string(),
+                amount: coins(35 + 100, "usei"),
+            })
+        );
+        assert_eq!(
+            35 + 100,
+            WITHDRAWN_STAKING_REWARDS
+                .load(deps.as_ref().storage)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_split_by_delegation_large_amounts() {
+        let mut deps = staked_deps(&[("val1", 2 * 10u128.pow(30)), ("val2", 10u128.pow(30))]);
+        DENOM
+            .save(deps.as_mut().storage, &"usei".to_string())
+            .unwrap();
+        let shares = split_by_delegation(deps.as_ref(), &mock_env(), 10u128.pow(30)).unwrap();
+        assert_eq!(
+            shares,
+            vec![
+                ("val1".to_string(), 666666666666666666666666666667),
+                ("val2".to_string(), 333333333333333333333333333333),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compound_rewards() {
+        let delegation = |validator: &str, amount: u128, rewards: u128| FullDelegation {
+            delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
+            validator: validator.to_string(),
+            amount: Coin::new(amount, "usei"),
+            can_redelegate: Coin::new(0, "usei"),
+            accumulated_rewards: vec![Coin::new(rewards, "usei")],
+        };
+        let mut deps = staked_deps(&[]);
+        deps.querier.update_staking(
+            "usei",
+            &[],
+            &[
+                delegation("val1", 1000000, 30),
+                delegation("val2", 500000, 5),
+            ],
+        );
+        // 100 of the balance are rewards withdrawn by earlier staking actions
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(48000000 - 1500000 + 100, "usei"));
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        let msg = ExecuteMsg::DelegateMany {
+            delegations: vec![
+                ("val1".to_string(), Uint128::new(1000000)),
+                ("val2".to_string(), Uint128::new(500000)),
+            ],
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let policy = RewardPolicy::Split {
+            compound_percentage: 40,
+            validator: None,
+        };
+        let msg = ExecuteMsg::InternalUpdateRewardPolicy {
+            policy: policy.clone(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let self_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        execute(deps.as_mut(), mock_env(), self_info.clone(), msg).unwrap();
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let res: ShowConfigResponse = from_json(&bin).unwrap();
+        assert_eq!(res.reward_policy, policy);
+
+        // 135 in rewards, of which 54 are compounded in proportion to the delegations
+        let msg = ExecuteMsg::InitiateWithdrawReward {};
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+        assert_eq!(
+            res.messages
+                .iter()
+                .map(|msg| msg.msg.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                CosmosMsg::Distribution(DistributionMsg::WithdrawDelegatorReward {
+                    validator: "val1".to_string(),
+                }),
+                CosmosMsg::Distribution(DistributionMsg::WithdrawDelegatorReward {
+                    validator: "val2".to_string(),
+                }),
+                CosmosMsg::Staking(StakingMsg::Delegate {
+                    validator: "val1".to_string(),
+                    amount: Coin::new(36, "usei"),
+                }),
+                CosmosMsg::Staking(StakingMsg::Delegate {
+                    validator: "val2".to_string(),
+                    amount: Coin::new(18, "usei"),
+                }),
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: REWARD_ADDR1.to_string(),
+                    amount: coins(81, "usei"),
+                }),
+            ]
+        );
+        assert_eq!(54, COMPOUNDED_REWARDS.load(deps.as_ref().storage).unwrap());
+        assert_eq!(
+            81,
+            WITHDRAWN_STAKING_REWARDS
+                .load(deps.as_ref().storage)
+                .unwrap()
+        );
+
+        // once the messages are executed, the compounded rewards are not mistaken for principal
+        deps.querier.update_staking(
+            "usei",
+            &[],
+            &[
+                delegation("val1", 1000036, 0),
+                delegation("val2", 500018, 0),
+            ],
+        );
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(48000000 - 1500000, "usei"));
+        assert_eq!(
+            0,
+            calculate_withdrawn_rewards(deps.as_ref(), mock_env()).unwrap()
+        );
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::Info {}).unwrap();
+        let res: ShowInfoResponse = from_json(&bin).unwrap();
+        assert_eq!(Uint128::new(54), res.compounded_staking_rewards);
+
+        deps.querier.update_staking(
+            "usei",
+            &[],
+            &[
+                delegation("val1", 1000036, 10),
+                delegation("val2", 500018, 0),
+            ],
+        );
+        let msg = ExecuteMsg::InternalUpdateRewardPolicy {
+            policy: RewardPolicy::Compound {
+                validator: Some("val3".to_string()),
+            },
+        };
+        execute(deps.as_mut(), mock_env(), self_info.clone(), msg).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::InitiateWithdrawReward {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[2].msg,
+            CosmosMsg::Staking(StakingMsg::Delegate {
+                validator: "val3".to_string(),
+                amount: Coin::new(10, "usei"),
+            })
+        );
+        assert_eq!(3, res.messages.len());
+        assert_eq!(64, COMPOUNDED_REWARDS.load(deps.as_ref().storage).unwrap());
+
+        // rewards are paid out while the compound validator is not allowed
+        ALLOWED_VALIDATORS
+            .save(deps.as_mut().storage, "val1", &EmptyStruct {})
+            .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::InitiateWithdrawReward {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[2].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: REWARD_ADDR1.to_string(),
+                amount: coins(10, "usei"),
+            })
+        );
+        assert_eq!(3, res.messages.len());
+        assert_eq!(64, COMPOUNDED_REWARDS.load(deps.as_ref().storage).unwrap());
+        ALLOWED_VALIDATORS.remove(deps.as_mut().storage, "val1");
+
+        // rewards that do not fit under the delegation cap are paid out instead
+        deps.querier.update_staking(
+            "usei",
+            &[],
+            &[
+                delegation("val1", 1000036, 10),
+                delegation("val2", 500018, 20),
+            ],
+        );
+        let msg = ExecuteMsg::InternalUpdateRewardPolicy {
+            policy: RewardPolicy::Compound { validator: None },
+        };
+        execute(deps.as_mut(), mock_env(), self_info, msg).unwrap();
+        DELEGATION_CAP
+            .save(
+                deps.as_mut().storage,
+                &DelegationCap::Amount {
+                    amount: Uint128::new(1000036 + 5),
+                },
+            )
+            .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::InitiateWithdrawReward {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[2..]
+                .iter()
+                .map(|msg| msg.msg.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                CosmosMsg::Staking(StakingMsg::Delegate {
+                    validator: "val1".to_string(),
+                    amount: Coin::new(5, "usei"),
+                }),
+                CosmosMsg::Staking(StakingMsg::Delegate {
+                    validator: "val2".to_string(),
+                    amount: Coin::new(10, "usei"),
+                }),
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: REWARD_ADDR1.to_string(),
+                    amount: coins(15, "usei"),
+                }),
+            ]
+        );
+        assert_eq!(79, COMPOUNDED_REWARDS.load(deps.as_ref().storage).unwrap());
+    }
+
+    #[test]
+    fn test_principal_ledger() {
+        let mut deps = staked_deps(&[("val1", 1000), ("val2", 500)]);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(48000000, "usei"));
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let msg = ExecuteMsg::DelegateMany {
+            delegations: vec![
+                ("val1".to_string(), Uint128::new(1000)),
+                ("val2".to_string(), Uint128::new(500)),
+            ],
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::Redelegate {
+            src_validator: "val1".to_string(),
+            dst_validator: "val3".to_string(),
+            amount: Uint128::new(300),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::Undelegate {
+            validator: "val2".to_string(),
+            amount: Uint128::new(200),
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let completion_time = mock_env()
+            .block
+            .time
+            .plus_seconds(unbonding_period(deps.as_ref().storage).unwrap());
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::PrincipalLedger {}).unwrap();
+        let res: PrincipalLedgerResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            res,
+            PrincipalLedgerResponse {
+                delegations: vec![
+                    ("val1".to_string(), Uint128::new(700)),
+                    ("val2".to_string(), Uint128::new(300)),
+                    ("val3".to_string(), Uint128::new(300)),
+                ],
+                unbonding: vec![UnbondingPrincipal {
+                    amount: Uint128::new(200),
+                    completion_time,
+                }],
+                delegated: Uint128::new(1300),
+                unbonding_total: Uint128::new(200),
+                liquid: Uint128::new(48000000 - 1500),
+                reported_unbonding: None,
+            }
+        );
+
+        // matured unbondings are liquid again
+        let mut env = mock_env();
+        env.block.time = completion_time;
+        let bin = query(deps.as_ref(), env.clone(), QueryMsg::PrincipalLedger {}).unwrap();
+        let res: PrincipalLedgerResponse = from_json(&bin).unwrap();
+        assert!(res.unbonding.is_empty());
+        assert_eq!(Uint128::zero(), res.unbonding_total);
+        assert_eq!(Uint128::new(48000000 - 1300), res.liquid);
+
+        // withdrawn rewards cannot be delegated as principal
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(48000000 + 1000, "usei"));
+        let msg = ExecuteMsg::Delegate {
+            validator: "val1".to_string(),
+            amount: Uint128::new(48000000 - 1300 + 1),
+        };
+        let err = execute(deps.as_mut(), env, mock_info(VOTER5, &[]), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidDelegation(
+                "47998701 exceeds the liquid principal of 47998700".to_string()
+            )
+        );
+        // nor can redelegations move more than the source holds
+        let msg = ExecuteMsg::Redelegate {
+            src_validator: "val2".to_string(),
+            dst_validator: "val3".to_string(),
+            amount: Uint128::new(501),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(VOTER5, &[]), msg).unwrap_err();
+    }
+
+    #[test]
+    fn test_portfolio() {
+        let delegation = |validator: &str, amount: u128, rewards: u128| FullDelegation {
+            delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
+            validator: validator.to_string(),
+            amount: Coin::new(amount, "usei"),
+            can_redelegate: Coin::new(0, "usei"),
+            accumulated_rewards: vec![Coin::new(rewards, "usei")],
+        };
+        let mut deps = staked_deps(&[]);
+        deps.querier.update_staking(
+            "usei",
+            &[],
+            &[delegation("val1", 1000, 30), delegation("val2", 500, 5)],
+        );
+        // 100 of the balance are withdrawn rewards
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(48000000 - 1500 + 100, "usei"));
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        let msg = ExecuteMsg::DelegateMany {
+            delegations: vec![
+                ("val1".to_string(), Uint128::new(1000)),
+                ("val2".to_string(), Uint128::new(500)),
+            ],
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::Portfolio {}).unwrap();
+        let res: PortfolioResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            res,
+            PortfolioResponse {
+                delegations: vec![
+                    PortfolioDelegation {
+                        validator: "val1".to_string(),
+                        amount: Uint128::new(1000),
+                        pending_rewards: Uint128::new(30),
+                    },
+                    PortfolioDelegation {
+                        validator: "val2".to_string(),
+                        amount: Uint128::new(500),
+                        pending_rewards: Uint128::new(5),
+                    },
+                ],
+                total_delegated: Uint128::new(1500),
+                total_pending_rewards: Uint128::new(35),
+                unbonding: None,
+                liquid_principal: Uint128::new(48000000 - 1500),
+                liquid_rewards: Uint128::new(100),
+                staked_principal_share: Decimal::from_ratio(1500u128, 48000000u128),
+            }
+        );
+
+        // unbonding entries are reported where the chain supports them
+        deps.querier = deps.querier.with_custom_handler(
+            |_: &SeiQueryWrapper| -> MockQuerierCustomHandlerResult {
+                let res = UnbondingDelegationsResponse {
+                    entries: vec![UnbondingDelegationEntry {
+                        creation_height: 1,
+                        completion_time: "2022-10-12T00:00:00Z".to_string(),
+                        initial_balance: Uint128::new(10),
+                        balance: Uint128::new(10),
+                    }],
+                };
+                MockQuerierCustomHandlerResult::Ok(ContractResult::Ok(
+                    to_json_binary(&res).unwrap(),
+                ))
+            },
+        );
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::Portfolio {}).unwrap();
+        let res: PortfolioResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            Some(vec![UnbondingDelegationEntry {
+                creation_height: 1,
+                completion_time: "2022-10-12T00:00:00Z".to_string(),
+                initial_balance: Uint128::new(10),
+                balance: Uint128::new(10),
+            }]),
+            res.unbonding
+        );
+    }
+
+    #[test]
+    fn test_staking_reward_split() {
+        let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(48000000 + 1001, "usei"));
+        deps.querier = deps.querier.with_custom_handler(
+            |_: &SeiQueryWrapper| -> MockQuerierCustomHandlerResult {
+                let res = UnbondingDelegationsResponse { entries: vec![] };
+                MockQuerierCustomHandlerResult::Ok(ContractResult::Ok(
+                    to_json_binary(&res).unwrap(),
+                ))
+            },
+        );
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let recipient = |address: &str, weight: u64| WeightedRecipient {
+            address: Addr::unchecked(address),
+            weight,
+        };
+        let msg = ExecuteMsg::ProposeUpdateStakingRewardSplit {
+            recipients: vec![recipient("beneficiary", 7000), recipient("treasury", 2000)],
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(VOTER1, &[]), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidDistribution(
+                "weights must add up to 10000 basis points".to_string()
+            )
+        );
+        let recipients = vec![
+            recipient("beneficiary", 7000),
+            recipient("treasury", 2000),
+            recipient("ops", 1000),
+        ];
+        let msg = ExecuteMsg::ProposeUpdateStakingRewardSplit {
+            recipients: recipients.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(VOTER1, &[]), msg).unwrap();
+        let msg = ExecuteMsg::InternalUpdateStakingRewardSplit {
+            recipients: recipients.clone(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let self_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        execute(deps.as_mut(), mock_env(), self_info.clone(), msg).unwrap();
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::Info {}).unwrap();
+        let res: ShowInfoResponse = from_json(&bin).unwrap();
+        assert_eq!(res.staking_reward_split, Some(recipients));
+
+        // the first recipient receives the rounding dust
+        let msg = ExecuteMsg::InitiateWithdrawReward {};
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+        let send = |address: &str, amount: u128| {
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: address.to_string(),
+                amount: coins(amount, "usei"),
+            })
+        };
+        assert_eq!(
+            res.messages
+                .iter()
+                .map(|msg| msg.msg.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                send("beneficiary", 701),
+                send("treasury", 200),
+                send("ops", 100)
+            ]
+        );
+
+        let update_address = ExecuteMsg::InternalUpdateStakingRewardDistributionAddress {
+            staking_reward_distribution_address: Addr::unchecked("treasury"),
+        };
+        execute(deps.as_mut(), mock_env(), self_info, update_address).unwrap();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(48000000 + 50, "usei"));
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            res.messages
+                .iter()
+                .map(|msg| msg.msg.clone())
+                .collect::<Vec<_>>(),
+            vec![send("treasury", 50)]
+        );
+
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::RewardRecipients {}).unwrap();
+        let res: RewardRecipientsResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            res.recipients,
+            vec![
+                RewardRecipientEntry {
+                    address: Addr::unchecked("beneficiary"),
+                    withdrawn: Uint128::new(701),
+                },
+                RewardRecipientEntry {
+                    address: Addr::unchecked("ops"),
+                    withdrawn: Uint128::new(100),
+                },
+                RewardRecipientEntry {
+                    address: Addr::unchecked("treasury"),
+                    withdrawn: Uint128::new(250),
+                },
+            ]
+        );
+        assert_eq!(
+            1051,
+            WITHDRAWN_STAKING_REWARDS
+                .load(deps.as_ref().storage)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn initiate_withdraw_reward_unauthorized() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let msg = ExecuteMsg::InitiateWithdrawReward {};
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    }
+
+    #[test]
+    fn test_propose_update_admin_works() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let info = mock_info(VOTER1, &[]);
+        let new_admin = Addr::unchecked("new_admin1");
+        let proposal = ExecuteMsg::ProposeUpdateAdmin {
+            admin: new_admin.clone(),
+            remove: false,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
+
+        // Verify
+        assert_eq!(
+            res,
+            Response::new()
+                .add_attribute("action", "propose")
+                .add_attribute("sender", VOTER1)
+                .add_attribute("proposal_id", 1.to_string())
+                .add_attribute("status", "Open")
+        );
+
+        // Verify admin has updated after internal call
+        let internal_update = ExecuteMsg::InternalUpdateAdmin {
+            admin: new_admin.clone(),
+            remove: false,
+        };
+        let internal_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            internal_info,
+            internal_update.clone(),
+        )
+        .unwrap();
+        let result = match ADMINS.load(deps.as_ref().storage, &Addr::unchecked(new_admin.clone())) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(ContractError::Unauthorized {}),
+        };
+        assert_eq!(result, Ok(()))
+    }
+
+    #[test]
+    fn test_propose_update_admin_unauthorized() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let info = mock_info(OWNER, &[]);
+        let proposal = ExecuteMsg::ProposeUpdateAdmin {
+            admin: Addr::unchecked("new_admin1"),
+            remove: false,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn test_propose_update_unlocked_distribution_address_works() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let info = mock_info(VOTER1, &[]);
+        let new_addr = Addr::unchecked("new_unlock1");
+        let proposal = ExecuteMsg::ProposeUpdateUnlockedDistributionAddress {
+            unlocked_distribution_address: new_addr.clone(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), proposal.clone()).unwrap();
+
+        // Verify
+        assert_eq!(
+            res,
+            Response::new()
+                .add_attribute("action", "propose")
+                .add_attribute("sender", VOTER1)
+                .add_attribute("proposal_id", 1.to_string())
+                .add_attribute("status", "Open")
+        );
+
+        // Verify address has updated after internal call
+        let internal_update = ExecuteMsg::InternalUpdateUnlockedDistributionAddress {
+            unlocked_distribution_address: new_addr.clone(),
+        };
+        let internal_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            internal_info,
+            internal_update.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            UNLOCK_DISTRIBUTION_ADDRESS
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap(),
+            new_addr
+        );
+    }
+
+    #[test]
+    fn test_propose_update_stakign_reward_distribution_address_works() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let info = mock_info(VOTER1, &[]);
+        let new_addr = Addr::unchecked("new_staking1");
+        let proposal = ExecuteMsg::ProposeUpdateStakingRewardDistributionAddress {
+            staking_reward_distribution_address: new_addr.clone(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), proposal.clone()).unwrap();
+
+        // Verify
+        assert_eq!(
+            res,
+            Response::new()
+                .add_attribute("action", "propose")
+                .add_attribute("sender", VOTER1)
+                .add_attribute("proposal_id", 1.to_string())
+                .add_attribute("status", "Open")
+        );
+
+        // Verify address has updated after internal call
+        let internal_update = ExecuteMsg::InternalUpdateStakingRewardDistributionAddress {
+            staking_reward_distribution_address: new_addr.clone(),
+        };
+        let internal_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            internal_info,
+            internal_update.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            STAKING_REWARD_ADDRESS.load(deps.as_ref().storage).unwrap(),
+            new_addr
+        );
+    }
+
+    #[test]
+    fn test_propose_emergency_withdraw_works() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let info = mock_info(VOTER1, &[]);
+        let proposal = ExecuteMsg::ProposeEmergencyWithdraw {
+            dst: Addr::unchecked("destination"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
+
+        // Verify
+        assert_eq!(
+            res,
+            Response::new()
+                .add_attribute("action", "propose")
+                .add_attribute("sender", VOTER1)
+                .add_attribute("proposal_id", 1.to_string())
+                .add_attribute("status", "Open")
+        );
+    }
+
+    #[test]
+    fn test_propose_emergency_withdraw_unauthorized() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let info = mock_info(OWNER, &[]);
+        let proposal = ExecuteMsg::ProposeEmergencyWithdraw {
+            dst: Addr::unchecked("destination"),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn test_propose_gov_vote_works() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let info = mock_info(VOTER1, &[]);
+        let proposal = ExecuteMsg::ProposeGovVote {
+            gov_proposal_id: 1,
+            gov_vote: VoteOption::Yes,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
+
+        // Verify
+        assert_eq!(
+            res,
+            Response::new()
+                .add_attribute("action", "propose")
+                .add_attribute("sender", VOTER1)
+                .add_attribute("proposal_id", 1.to_string())
+                .add_attribute("status", "Open")
+        );
+    }
+
+    #[test]
+    fn test_vote_works() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let info = mock_info(VOTER1, &[]);
+        let proposal = ExecuteMsg::ProposeUpdateAdmin {
+            admin: Addr::unchecked("new_admin1"),
+            remove: false,
+        };
+        execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
+
+        let info = mock_info(VOTER2, &[]);
+        let vote2 = ExecuteMsg::VoteProposal { proposal_id: 1 };
+        execute(deps.as_mut(), mock_env(), info, vote2.clone()).unwrap();
+
+        let info = mock_info(VOTER3, &[]);
+        let vote3 = ExecuteMsg::VoteProposal { proposal_id: 1 };
+        execute(deps.as_mut(), mock_env(), info, vote3.clone()).unwrap();
+    }
+
+    #[test]
+    fn test_vote_expired() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let info = mock_info(VOTER1, &[]);
+        let proposal = ExecuteMsg::ProposeUpdateAdmin {
+            admin: Addr::unchecked("new_admin1"),
+            remove: false,
+        };
+        execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
+
+        let info = mock_info(VOTER2, &[]);
+        let vote2 = ExecuteMsg::VoteProposal { proposal_id: 1 };
+        execute(deps.as_mut(), mock_env(), info, vote2.clone()).unwrap();
+
+        let info = mock_info(VOTER3, &[]);
+        let vote3 = ExecuteMsg::VoteProposal { proposal_id: 1 };
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3601);
+        let err = execute(deps.as_mut(), env, info, vote3.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Expired {});
+    }
+
+    #[test]
+    fn test_process_update_admin_works() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let info = mock_info(VOTER1, &[]);
+        let proposal = ExecuteMsg::ProposeUpdateAdmin {
+            admin: Addr::unchecked("new_admin1"),
+            remove: false,
+        };
+        execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
+
+        let info = mock_info(VOTER2, &[]);
+        let vote2 = ExecuteMsg::VoteProposal { proposal_id: 1 };
+        execute(deps.as_mut(), mock_env(), info, vote2.clone()).unwrap();
 
-fn query_config(deps: Deps<SeiQueryWrapper>) -> StdResult<ShowConfigResponse> {
-    Ok(ShowConfigResponse {
-        max_voting_period: MAX_VOTING_PERIOD.load(deps.storage)?,
-        admin_voting_threshold: ADMIN_VOTING_THRESHOLD.load(deps.storage)?,
-    })
-}
+        let info = mock_info(VOTER3, &[]);
+        let vote3 = ExecuteMsg::VoteProposal { proposal_id: 1 };
+        execute(deps.as_mut(), mock_env(), info, vote3.clone()).unwrap();
 
-fn query_total_vested(deps: Deps<SeiQueryWrapper>, env: Env) -> StdResult<ShowTotalVestedResponse> {
-    let vested_amount = total_vested_amount(deps.storage, env.block.time)?;
-    Ok(ShowTotalVestedResponse {
-        vested_amount: vested_amount,
-    })
-}
+        let info = mock_info(VOTER3, &[]);
+        let process = ExecuteMsg::ProcessProposal { proposal_id: 1 };
+        let res = execute(deps.as_mut(), mock_env(), info, process.clone()).unwrap();
 
-#[cfg(test)]
-mod tests {
-    use core::marker::PhantomData;
-    use cosmwasm_std::testing::{
-        mock_env, mock_info, MockApi, MockQuerier, MockQuerierCustomHandlerResult, MockStorage,
-        MOCK_CONTRACT_ADDR,
-    };
-    use cosmwasm_std::{
-        from_binary, to_json_binary, Addr, Coin, ContractResult, Decimal, FullDelegation,
-        OwnedDeps, SystemResult, Timestamp, Uint128, Validator,
-    };
+        assert_eq!(1, res.messages.len());
+    }
 
-    use cw2::{get_contract_version, ContractVersion};
-    use cw_utils::{Duration, Expiration, ThresholdResponse};
+    #[test]
+    fn test_process_proposal_premature() {
+        let mut deps = mock_dependencies();
 
-    use crate::data_structure::Tranche;
-    use crate::msg::{SeiQueryWrapper, UnbondingDelegationEntry, UnbondingDelegationsResponse};
-    use crate::state::get_number_of_ops;
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
 
-    use super::*;
+        let info = mock_info(VOTER1, &[]);
+        let proposal = ExecuteMsg::ProposeUpdateAdmin {
+            admin: Addr::unchecked("new_admin1"),
+            remove: false,
+        };
+        execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
 
-    const OWNER: &str = "admin0001";
-    const VOTER1: &str = "voter0001";
-    const VOTER2: &str = "voter0002";
-    const VOTER3: &str = "voter0003";
-    const VOTER4: &str = "voter0004";
-    const VOTER5: &str = "voter0005";
-    const VOTER6: &str = "voter0006";
+        let info = mock_info(VOTER2, &[]);
+        let vote2 = ExecuteMsg::VoteProposal { proposal_id: 1 };
+        execute(deps.as_mut(), mock_env(), info, vote2.clone()).unwrap();
 
-    const UNLOCK_ADDR1: &str = "unlock0001";
-    const REWARD_ADDR1: &str = "reward0001";
+        let info = mock_info(VOTER3, &[]);
+        let process = ExecuteMsg::ProcessProposal { proposal_id: 1 };
+        let err = execute(deps.as_mut(), mock_env(), info, process.clone()).unwrap_err();
 
-    fn mock_dependencies(
-    ) -> OwnedDeps<MockStorage, MockApi, MockQuerier<SeiQueryWrapper>, SeiQueryWrapper> {
-        OwnedDeps {
-            storage: MockStorage::default(),
-            api: MockApi::default(),
-            querier: MockQuerier::new(&[]),
-            custom_query_type: PhantomData::default(),
-        }
+        assert_eq!(err, ContractError::WrongExecuteStatus {});
     }
 
-    // this will set up the instantiation for other tests
-    #[track_caller]
-    fn setup_test_case(
-        deps: DepsMut<SeiQueryWrapper>,
-        info: MessageInfo,
-    ) -> Result<Response<Empty>, ContractError> {
-        let env = mock_env();
-        let mut vesting_amounts = vec![12000000u128];
-        let mut vesting_timestamps = vec![env.block.time.plus_seconds(31536000)];
-        for _ in 1..37 {
-            vesting_amounts.push(1000000u128);
-            vesting_timestamps.push(vesting_timestamps.last().unwrap().plus_seconds(2592000));
-        }
-        let instantiate_msg = InstantiateMsg {
-            admins: vec![
-                Addr::unchecked(VOTER1),
-                Addr::unchecked(VOTER2),
-                Addr::unchecked(VOTER3),
-                Addr::unchecked(VOTER4),
-            ],
-            ops: vec![Addr::unchecked(VOTER5), Addr::unchecked(VOTER6)],
-            tranche: Tranche {
-                denom: "usei".to_string(),
-                vesting_amounts,
-                vesting_timestamps,
-                unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-                staking_reward_distribution_address: Addr::unchecked(REWARD_ADDR1),
-            },
-            max_voting_period: Duration::Time(3600),
-            admin_voting_threshold_percentage: 75,
+    #[test]
+    fn test_process_update_admin_double_process() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+
+        let info = mock_info(VOTER1, &[]);
+        let proposal = ExecuteMsg::ProposeUpdateAdmin {
+            admin: Addr::unchecked("new_admin1"),
+            remove: false,
         };
-        instantiate(deps, mock_env(), info, instantiate_msg)
+        execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
+
+        let info = mock_info(VOTER2, &[]);
+        let vote2 = ExecuteMsg::VoteProposal { proposal_id: 1 };
+        execute(deps.as_mut(), mock_env(), info, vote2.clone()).unwrap();
+
+        let info = mock_info(VOTER3, &[]);
+        let vote3 = ExecuteMsg::VoteProposal { proposal_id: 1 };
+        execute(deps.as_mut(), mock_env(), info, vote3.clone()).unwrap();
+
+        let info = mock_info(VOTER3, &[]);
+        let process = ExecuteMsg::ProcessProposal { proposal_id: 1 };
+        execute(deps.as_mut(), mock_env(), info, process.clone()).unwrap();
+        let info = mock_info(VOTER3, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, process.clone()).unwrap_err();
+
+        assert_eq!(err, ContractError::WrongExecuteStatus {});
     }
 
     #[test]
-    fn test_instantiate_works() {
+    fn test_process_gov_vote_works() {
         let mut deps = mock_dependencies();
-        let info = mock_info(OWNER, &[]);
-
-        let _max_voting_period = Duration::Time(1234567);
 
-        // No admins fails
-        let instantiate_msg = InstantiateMsg {
-            admins: vec![],
-            ops: vec![Addr::unchecked(VOTER5)],
-            tranche: Tranche {
-                denom: "usei".to_string(),
-                vesting_amounts: vec![1],
-                vesting_timestamps: vec![mock_env().block.time],
-                unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-                staking_reward_distribution_address: Addr::unchecked(REWARD_ADDR1),
-            },
-            max_voting_period: Duration::Time(3600),
-            admin_voting_threshold_percentage: 75,
-        };
-        let err =
-            instantiate(deps.as_mut(), mock_env(), info.clone(), instantiate_msg).unwrap_err();
-        assert_eq!(err, ContractError::NoAdmins {});
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
 
-        // Zero ops fails
-        let instantiate_msg = InstantiateMsg {
-            admins: vec![Addr::unchecked(VOTER1)],
-            ops: vec![],
-            tranche: Tranche {
-                denom: "usei".to_string(),
-                vesting_amounts: vec![1],
-                vesting_timestamps: vec![mock_env().block.time],
-                unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-                staking_reward_distribution_address: Addr::unchecked(REWARD_ADDR1),
-            },
-            max_voting_period: Duration::Time(3600),
-            admin_voting_threshold_percentage: 75,
+        let info = mock_info(VOTER1, &[]);
+        let proposal = ExecuteMsg::ProposeGovVote {
+            gov_proposal_id: 1,
+            gov_vote: VoteOption::Yes,
         };
-        let err =
-            instantiate(deps.as_mut(), mock_env(), info.clone(), instantiate_msg).unwrap_err();
-        assert_eq!(err, ContractError::NoOps {},);
+        execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
 
-        // Invalid vesting schedule
-        let instantiate_msg = InstantiateMsg {
-            admins: vec![Addr::unchecked(VOTER1)],
-            ops: vec![Addr::unchecked(VOTER5)],
-            tranche: Tranche {
-                denom: "usei".to_string(),
-                vesting_amounts: vec![],
-                vesting_timestamps: vec![mock_env().block.time],
-                unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-                staking_reward_distribution_address: Addr::unchecked(REWARD_ADDR1),
-            },
-            max_voting_period: Duration::Time(3600),
-            admin_voting_threshold_percentage: 75,
-        };
-        let err =
-            instantiate(deps.as_mut(), mock_env(), info.clone(), instantiate_msg).unwrap_err();
-        assert_eq!(
-            err,
-            ContractError::InvalidTranche("mismatched vesting amounts and schedule".to_string()),
-        );
+        let info = mock_info(VOTER2, &[]);
+        let vote2 = ExecuteMsg::VoteProposal { proposal_id: 1 };
+        execute(deps.as_mut(), mock_env(), info, vote2.clone()).unwrap();
 
-        // insufficient funds
-        let err = setup_test_case(deps.as_mut(), info).unwrap_err();
-        assert_eq!(
-            err,
-            ContractError::InvalidTranche("insufficient deposit for the vesting plan".to_string()),
-        );
+        let info = mock_info(VOTER3, &[]);
+        let vote3 = ExecuteMsg::VoteProposal { proposal_id: 1 };
+        execute(deps.as_mut(), mock_env(), info, vote3.clone()).unwrap();
 
-        // happy path
-        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info).unwrap();
+        let info = mock_info(VOTER3, &[]);
+        let process = ExecuteMsg::ProcessProposal { proposal_id: 1 };
+        let res = execute(deps.as_mut(), mock_env(), info, process.clone()).unwrap();
 
-        // Verify
-        assert_eq!(
-            ContractVersion {
-                contract: CONTRACT_NAME.to_string(),
-                version: CONTRACT_VERSION.to_string(),
-            },
-            get_contract_version(&deps.storage).unwrap()
-        )
+        assert_eq!(1, res.messages.len());
     }
 
     #[test]
-    fn delegate_work() {
+    fn test_execute_internal_update_admin_works() {
         let mut deps = mock_dependencies();
 
-        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
         setup_test_case(deps.as_mut(), info.clone()).unwrap();
 
-        let msg = ExecuteMsg::Delegate {
-            validator: "val".to_string(),
-            amount: 100,
+        let info = mock_info(mock_env().contract.address.as_str(), &[]);
+        let msg = ExecuteMsg::InternalUpdateAdmin {
+            admin: Addr::unchecked("new_admin1"),
+            remove: false,
         };
-        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(1, res.messages.len());
+        execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+        ADMINS
+            .load(deps.as_ref().storage, &Addr::unchecked("new_admin1"))
+            .unwrap();
+        assert_eq!(5, get_number_of_admins(deps.as_ref().storage));
     }
 
     #[test]
-    fn delegate_unauthorized() {
+    fn test_execute_internal_update_admin_remove_works() {
         let mut deps = mock_dependencies();
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
         setup_test_case(deps.as_mut(), info.clone()).unwrap();
 
-        let msg = ExecuteMsg::Delegate {
-            validator: "val".to_string(),
-            amount: 100,
+        let info = mock_info(mock_env().contract.address.as_str(), &[]);
+        let msg = ExecuteMsg::InternalUpdateAdmin {
+            admin: Addr::unchecked(VOTER1),
+            remove: true,
         };
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+        ADMINS
+            .load(deps.as_ref().storage, &Addr::unchecked(VOTER1))
+            .unwrap_err();
+        assert_eq!(3, get_number_of_admins(deps.as_ref().storage));
     }
 
     #[test]
-    fn redelegate_work() {
+    fn test_execute_internal_update_admin_unauthorized() {
         let mut deps = mock_dependencies();
 
-        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
         setup_test_case(deps.as_mut(), info.clone()).unwrap();
 
-        let msg = ExecuteMsg::Redelegate {
-            src_validator: "val1".to_string(),
-            dst_validator: "val2".to_string(),
-            amount: 100,
+        let info = mock_info(VOTER1, &[]);
+        let msg = ExecuteMsg::InternalUpdateAdmin {
+            admin: Addr::unchecked("new_admin1"),
+            remove: false,
         };
-        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(1, res.messages.len());
+        let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        ADMINS
+            .load(deps.as_ref().storage, &Addr::unchecked("new_admin1"))
+            .unwrap_err();
+        assert_eq!(4, get_number_of_admins(deps.as_ref().storage));
     }
 
     #[test]
-    fn redelegate_unauthorized() {
+    fn test_execute_update_op_works() {
         let mut deps = mock_dependencies();
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
         setup_test_case(deps.as_mut(), info.clone()).unwrap();
 
-        let msg = ExecuteMsg::Redelegate {
-            src_validator: "val1".to_string(),
-            dst_validator: "val2".to_string(),
-            amount: 100,
+        let info = mock_info(VOTER1, &[]);
+        let msg = ExecuteMsg::UpdateOp {
+            op: Addr::unchecked("new_op1"),
+            remove: false,
         };
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+        OPS.load(deps.as_ref().storage, &Addr::unchecked("new_op1"))
+            .unwrap();
+        assert_eq!(3, get_number_of_ops(deps.as_ref().storage));
     }
 
     #[test]
-    fn undelegate_work() {
+    fn test_execute_update_op_remove_works() {
         let mut deps = mock_dependencies();
 
-        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
         setup_test_case(deps.as_mut(), info.clone()).unwrap();
 
-        let msg = ExecuteMsg::Undelegate {
-            validator: "val".to_string(),
-            amount: 100,
+        let info = mock_info(VOTER1, &[]);
+        let msg = ExecuteMsg::UpdateOp {
+            op: Addr::unchecked(VOTER5),
+            remove: true,
         };
-        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(1, res.messages.len());
+        execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+        OPS.load(deps.as_ref().storage, &Addr::unchecked(VOTER5))
+            .unwrap_err();
+        assert_eq!(1, get_number_of_ops(deps.as_ref().storage));
     }
 
     #[test]
-    fn undelegate_unauthorized() {
+    fn test_execute_update_op_unauthorized() {
         let mut deps = mock_dependencies();
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
         setup_test_case(deps.as_mut(), info.clone()).unwrap();
 
-        let msg = ExecuteMsg::Undelegate {
-            validator: "val".to_string(),
-            amount: 100,
+        let info = mock_info(VOTER5, &[]);
+        let msg = ExecuteMsg::UpdateOp {
+            op: Addr::unchecked("new_op1"),
+            remove: false,
         };
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        OPS.load(deps.as_ref().storage, &Addr::unchecked("new_op1"))
+            .unwrap_err();
+        assert_eq!(2, get_number_of_ops(deps.as_ref().storage));
     }
 
     #[test]
-    fn initiate_withdraw_unlocked_work() {
+    fn test_execute_internal_withdraw_locked_works() {
         let mut deps = mock_dependencies();
 
-        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
         setup_test_case(deps.as_mut(), info.clone()).unwrap();
 
-        let msg = ExecuteMsg::InitiateWithdrawUnlocked { amount: 12000000 };
-        let mut env = mock_env();
-        let mut block = env.block;
-        block.time = block.time.plus_seconds(31536000);
-        env.block = block;
-        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let info = mock_info(mock_env().contract.address.as_str(), &[]);
+        let proposal = ExecuteMsg::InternalWithdrawLocked {
+            dst: Addr::unchecked("destination"),
+            proposer: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
         assert_eq!(1, res.messages.len());
         assert_eq!(
-            12000000,
-            WITHDRAWN_UNLOCKED.load(deps.as_ref().storage).unwrap()
+            vec![] as Vec<u128>,
+            load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1
+        );
+        assert_eq!(
+            vec![] as Vec<Timestamp>,
+            load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0
+        );
+        assert_eq!(
+            48000000,
+            WITHDRAWN_LOCKED
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
         );
     }
 
     #[test]
-    fn initiate_withdraw_unlocked_unauthorized() {
+    fn test_add_tranche_works() {
         let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(100000000, "usei"));
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
 
-        let msg = ExecuteMsg::InitiateWithdrawUnlocked { amount: 12000000 };
-        let mut env = mock_env();
-        let mut block = env.block;
-        block.time = block.time.plus_seconds(31536000);
-        env.block = block;
-        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-        assert_eq!(err, ContractError::Unauthorized {});
-    }
+        let info = mock_info(VOTER1, &[Coin::new(1000, "usei".to_string())]);
+        let msg = ExecuteMsg::AddTranche {
+            tranche_id: "advisor".to_string(),
+            tranche: named_tranche(1000),
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    #[test]
-    fn initiate_withdraw_reward_work() {
-        let validator1 = "val1";
-        let validator2 = "val2";
-        let mut deps = mock_dependencies();
-        deps.querier.update_staking(
-            "usei",
-            &[
-                Validator {
-                    address: validator1.to_string(),
-                    commission: Decimal::zero(),
-                    max_commission: Decimal::zero(),
-                    max_change_rate: Decimal::zero(),
-                },
-                Validator {
-                    address: validator2.to_string(),
-                    commission: Decimal::zero(),
-                    max_commission: Decimal::zero(),
-                    max_change_rate: Decimal::zero(),
-                },
-            ],
-            &[
-                FullDelegation {
-                    delegator: Addr::unchecked(mock_env().contract.address),
-                    validator: validator1.to_string(),
-                    amount: Coin::new(1000000, "usei"),
-                    can_redelegate: Coin::new(0, "usei"),
-                    accumulated_rewards: vec![Coin::new(10, "usei"), Coin::new(20, "usei")],
-                },
-                FullDelegation {
-                    delegator: Addr::unchecked(mock_env().contract.address),
-                    validator: validator2.to_string(),
-                    amount: Coin::new(500000, "usei"),
-                    can_redelegate: Coin::new(0, "usei"),
-                    accumulated_rewards: vec![Coin::new(5, "usei")],
-                },
-            ],
-        );
-        deps.querier.update_balance(
-            mock_env().contract.address.clone(),
-            vec![Coin::new(48000000 - 1500000 + 100, "usei")],
-        );
-        // principal: 48000000 - 1500000 (delegations).
-        // Withdrawn rewards: principal - balance (100) + 10 = 110
-        deps.querier = deps.querier.with_custom_handler(
-            |_: &SeiQueryWrapper| -> MockQuerierCustomHandlerResult {
-                let res = UnbondingDelegationsResponse {
-                    entries: vec![UnbondingDelegationEntry {
-                        creation_height: 1,
-                        completion_time: "".to_string(),
-                        initial_balance: Uint128::new(10),
-                        balance: Uint128::new(10),
-                    }],
-                };
-                return MockQuerierCustomHandlerResult::Ok(ContractResult::Ok(
-                    to_json_binary(&res).unwrap(),
-                ));
-            },
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::ListTranches {}).unwrap();
+        let res: TrancheListResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            res.tranche_ids,
+            vec!["advisor".to_string(), DEFAULT_TRANCHE_ID.to_string()]
         );
 
-        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let msg = QueryMsg::TrancheTotalVested {
+            tranche_id: "advisor".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: ShowTotalVestedResponse = from_json(&bin).unwrap();
+        assert_eq!(res.vested_amount, Uint128::new(1000));
+        let bin = query(deps.as_ref(), env.clone(), QueryMsg::TotalVested {}).unwrap();
+        let res: ShowTotalVestedResponse = from_json(&bin).unwrap();
+        assert_eq!(res.vested_amount, Uint128::new(0));
 
-        let msg = ExecuteMsg::InitiateWithdrawReward {};
-        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(5, res.messages.len());
+        let info = mock_info(VOTER5, &[]);
+        let msg = ExecuteMsg::InitiateWithdrawTrancheUnlocked {
+            tranche_id: "advisor".to_string(),
+            amount: Uint128::new(400),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
         assert_eq!(
-            35 + 110,
-            WITHDRAWN_STAKING_REWARDS
-                .load(deps.as_ref().storage)
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "unlock0002".to_string(),
+                amount: coins(400, "usei"),
+            })
+        );
+
+        let msg = QueryMsg::TrancheInfo {
+            tranche_id: "advisor".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: ShowInfoResponse = from_json(&bin).unwrap();
+        assert_eq!(res.vesting_amounts, vec![Uint128::new(600)]);
+        assert_eq!(res.withdrawn_unlocked, Uint128::new(400));
+        assert_eq!(
+            res.unlock_distribution_address,
+            Addr::unchecked("unlock0002")
+        );
+        assert_eq!(
+            0,
+            WITHDRAWN_UNLOCKED
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
                 .unwrap()
         );
     }
 
     #[test]
-    fn initiate_withdraw_reward_unauthorized() {
+    fn test_add_height_tranche_works() {
         let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(100000000, "usei"));
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
 
-        let msg = ExecuteMsg::InitiateWithdrawReward {};
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    }
+        let height = mock_env().block.height;
+        let mut tranche = named_tranche(1000);
+        tranche.vesting_amounts = vec![Uint128::new(400), Uint128::new(600)];
+        tranche.vesting_timestamps = vec![
+            Timestamp::from_nanos(height + 10),
+            Timestamp::from_nanos(height + 20),
+        ];
+        tranche.schedule_unit = Some(ScheduleUnit::Height);
+        let info = mock_info(VOTER1, &[Coin::new(1000, "usei".to_string())]);
+        let msg = ExecuteMsg::AddTranche {
+            tranche_id: "advisor".to_string(),
+            tranche,
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    #[test]
-    fn test_propose_update_admin_works() {
-        let mut deps = mock_dependencies();
+        // a timestamp far past the first height has no effect on a height based schedule
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(31536000);
+        let msg = QueryMsg::TrancheTotalVested {
+            tranche_id: "advisor".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg.clone()).unwrap();
+        let res: ShowTotalVestedResponse = from_json(&bin).unwrap();
+        assert_eq!(res.vested_amount, Uint128::new(0));
 
-        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        env.block.height = height + 10;
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: ShowTotalVestedResponse = from_json(&bin).unwrap();
+        assert_eq!(res.vested_amount, Uint128::new(400));
 
-        let info = mock_info(VOTER1, &[]);
-        let new_admin = Addr::unchecked("new_admin1");
-        let proposal = ExecuteMsg::ProposeUpdateAdmin {
-            admin: new_admin.clone(),
-            remove: false,
+        let msg = QueryMsg::NextUnlock {
+            tranche_id: Some("advisor".to_string()),
         };
-        let res = execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
-
-        // Verify
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: NextUnlockResponse = from_json(&bin).unwrap();
         assert_eq!(
-            res,
-            Response::new()
-                .add_attribute("action", "propose")
-                .add_attribute("sender", VOTER1)
-                .add_attribute("proposal_id", 1.to_string())
-                .add_attribute("status", "Open")
+            NextUnlockResponse {
+                schedule_unit: ScheduleUnit::Height,
+                timestamp: Some(Timestamp::from_nanos(height + 20)),
+                amount: Uint128::new(600),
+            },
+            res
         );
 
-        // Verify admin has updated after internal call
-        let internal_update = ExecuteMsg::InternalUpdateAdmin {
-            admin: new_admin.clone(),
-            remove: false,
+        let info = mock_info(VOTER5, &[]);
+        let msg = ExecuteMsg::InitiateWithdrawTrancheUnlocked {
+            tranche_id: "advisor".to_string(),
+            amount: Uint128::new(400),
         };
-        let internal_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
-        execute(
-            deps.as_mut(),
-            mock_env(),
-            internal_info,
-            internal_update.clone(),
-        )
-        .unwrap();
-        let result = match ADMINS.load(deps.as_ref().storage, &Addr::unchecked(new_admin.clone())) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(ContractError::Unauthorized {}),
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let msg = QueryMsg::TrancheInfo {
+            tranche_id: "advisor".to_string(),
         };
-        assert_eq!(result, Ok(()))
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: ShowInfoResponse = from_json(&bin).unwrap();
+        assert_eq!(res.schedule_unit, ScheduleUnit::Height);
+        assert_eq!(res.vesting_amounts, vec![Uint128::new(600)]);
+        assert_eq!(res.withdrawn_unlocked, Uint128::new(400));
+
+        let msg = QueryMsg::VestedAt {
+            tranche_id: Some("advisor".to_string()),
+            at: SchedulePoint::Height(height + 20),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: ShowTotalVestedResponse = from_json(&bin).unwrap();
+        assert_eq!(res.vested_amount, Uint128::new(600));
+
+        // a time point cannot be projected onto a height schedule
+        let msg = QueryMsg::VestedAt {
+            tranche_id: Some("advisor".to_string()),
+            at: SchedulePoint::Time(env.block.time),
+        };
+        query(deps.as_ref(), env, msg).unwrap_err();
     }
 
     #[test]
-    fn test_propose_update_admin_unauthorized() {
+    fn test_add_tranche_invalid() {
         let mut deps = mock_dependencies();
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
 
-        let info = mock_info(OWNER, &[]);
-        let proposal = ExecuteMsg::ProposeUpdateAdmin {
-            admin: Addr::unchecked("new_admin1"),
-            remove: false,
+        let info = mock_info(VOTER5, &[Coin::new(1000, "usei".to_string())]);
+        let msg = ExecuteMsg::AddTranche {
+            tranche_id: "advisor".to_string(),
+            tranche: named_tranche(1000),
         };
-        let err = execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap_err();
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
         assert_eq!(err, ContractError::Unauthorized {});
+
+        let info = mock_info(VOTER1, &[Coin::new(1000, "usei".to_string())]);
+        let msg = ExecuteMsg::AddTranche {
+            tranche_id: DEFAULT_TRANCHE_ID.to_string(),
+            tranche: named_tranche(1000),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidTranche("tranche default already exists".to_string())
+        );
+
+        let mut tranche = named_tranche(1000);
+        tranche.denom = "uatom".to_string();
+        let msg = ExecuteMsg::AddTranche {
+            tranche_id: "advisor".to_string(),
+            tranche,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidTranche(
+                "tranche denom must match the contract denom".to_string()
+            )
+        );
+
+        let msg = ExecuteMsg::AddTranche {
+            tranche_id: "advisor".to_string(),
+            tranche: named_tranche(1001),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidTranche("insufficient deposit for the vesting plan".to_string())
+        );
+
+        let info = mock_info(VOTER5, &[]);
+        let msg = ExecuteMsg::InitiateWithdrawTrancheUnlocked {
+            tranche_id: "advisor".to_string(),
+            amount: Uint128::new(1),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::TrancheNotFound("advisor".to_string()));
     }
 
     #[test]
-    fn test_propose_update_unlocked_distribution_address_works() {
+    fn test_update_tranche_unlocked_distribution_address_works() {
         let mut deps = mock_dependencies();
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
+        let info = mock_info(VOTER1, &[Coin::new(1000, "usei".to_string())]);
+        let msg = ExecuteMsg::AddTranche {
+            tranche_id: "advisor".to_string(),
+            tranche: named_tranche(1000),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
 
-        let info = mock_info(VOTER1, &[]);
-        let new_addr = Addr::unchecked("new_unlock1");
-        let proposal = ExecuteMsg::ProposeUpdateUnlockedDistributionAddress {
+        let new_addr = Addr::unchecked("new_unlock2");
+        let proposal = ExecuteMsg::ProposeUpdateTrancheUnlockedDistributionAddress {
+            tranche_id: "advisor".to_string(),
             unlocked_distribution_address: new_addr.clone(),
         };
-        let res = execute(deps.as_mut(), mock_env(), info.clone(), proposal.clone()).unwrap();
-
-        // Verify
-        assert_eq!(
-            res,
-            Response::new()
-                .add_attribute("action", "propose")
-                .add_attribute("sender", VOTER1)
-                .add_attribute("proposal_id", 1.to_string())
-                .add_attribute("status", "Open")
-        );
+        execute(deps.as_mut(), mock_env(), info, proposal).unwrap();
 
-        // Verify address has updated after internal call
-        let internal_update = ExecuteMsg::InternalUpdateUnlockedDistributionAddress {
+        let internal_update = ExecuteMsg::InternalUpdateTrancheUnlockedDistributionAddress {
+            tranche_id: "advisor".to_string(),
             unlocked_distribution_address: new_addr.clone(),
         };
         let internal_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
-        execute(
-            deps.as_mut(),
-            mock_env(),
-            internal_info,
-            internal_update.clone(),
-        )
-        .unwrap();
+        execute(deps.as_mut(), mock_env(), internal_info, internal_update).unwrap();
         assert_eq!(
             UNLOCK_DISTRIBUTION_ADDRESS
-                .load(deps.as_ref().storage)
+                .load(deps.as_ref().storage, "advisor")
                 .unwrap(),
             new_addr
         );
+        assert_eq!(
+            UNLOCK_DISTRIBUTION_ADDRESS
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap(),
+            Addr::unchecked(UNLOCK_ADDR1)
+        );
     }
 
     #[test]
-    fn test_propose_update_stakign_reward_distribution_address_works() {
+    fn test_update_unlocked_distribution_split_works() {
         let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(100000000, "usei"));
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
 
-        let info = mock_info(VOTER1, &[]);
-        let new_addr = Addr::unchecked("new_staking1");
-        let proposal = ExecuteMsg::ProposeUpdateStakingRewardDistributionAddress {
-            staking_reward_distribution_address: new_addr.clone(),
+        let recipients = vec![
+            WeightedRecipient {
+                address: Addr::unchecked("team"),
+                weight: 2,
+            },
+            WeightedRecipient {
+                address: Addr::unchecked("advisor"),
+                weight: 1,
+            },
+        ];
+        let proposal = ExecuteMsg::ProposeUpdateUnlockedDistributionSplit {
+            tranche_id: None,
+            recipients: vec![],
         };
-        let res = execute(deps.as_mut(), mock_env(), info.clone(), proposal.clone()).unwrap();
-
-        // Verify
+        let err = execute(deps.as_mut(), mock_env(), mock_info(VOTER1, &[]), proposal).unwrap_err();
         assert_eq!(
-            res,
-            Response::new()
-                .add_attribute("action", "propose")
-                .add_attribute("sender", VOTER1)
-                .add_attribute("proposal_id", 1.to_string())
-                .add_attribute("status", "Open")
+            err,
+            ContractError::InvalidDistribution("there must be at least one recipient".to_string())
         );
+        let proposal = ExecuteMsg::ProposeUpdateUnlockedDistributionSplit {
+            tranche_id: None,
+            recipients: recipients.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(VOTER1, &[]), proposal).unwrap();
 
-        // Verify address has updated after internal call
-        let internal_update = ExecuteMsg::InternalUpdateStakingRewardDistributionAddress {
-            staking_reward_distribution_address: new_addr.clone(),
+        let internal_update = ExecuteMsg::InternalUpdateUnlockedDistributionSplit {
+            tranche_id: DEFAULT_TRANCHE_ID.to_string(),
+            recipients: recipients.clone(),
         };
         let internal_info = mock_info(MOCK_CONTRACT_ADDR, &[]);
         execute(
             deps.as_mut(),
             mock_env(),
-            internal_info,
-            internal_update.clone(),
+            internal_info.clone(),
+            internal_update,
         )
         .unwrap();
-        assert_eq!(
-            STAKING_REWARD_ADDRESS.load(deps.as_ref().storage).unwrap(),
-            new_addr
-        );
-    }
-
-    #[test]
-    fn test_propose_emergency_withdraw_works() {
-        let mut deps = mock_dependencies();
 
-        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::Info {}).unwrap();
+        let res: ShowInfoResponse = from_json(&bin).unwrap();
+        assert_eq!(Some(recipients), res.unlock_distribution_split);
 
-        let info = mock_info(VOTER1, &[]);
-        let proposal = ExecuteMsg::ProposeEmergencyWithdraw {
-            dst: Addr::unchecked("destination"),
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(31536000);
+        let msg = ExecuteMsg::InitiateWithdrawUnlocked {
+            amount: Uint128::new(1000001),
         };
-        let res = execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
-
-        // Verify
+        let res = execute(deps.as_mut(), env, mock_info(VOTER5, &[]), msg).unwrap();
         assert_eq!(
-            res,
-            Response::new()
-                .add_attribute("action", "propose")
-                .add_attribute("sender", VOTER1)
-                .add_attribute("proposal_id", 1.to_string())
-                .add_attribute("status", "Open")
+            vec![
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "team".to_string(),
+                    amount: coins(666668, "usei"),
+                }),
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "advisor".to_string(),
+                    amount: coins(333333, "usei"),
+                }),
+            ],
+            res.messages
+                .into_iter()
+                .map(|m| m.msg)
+                .collect::<Vec<CosmosMsg>>()
         );
-    }
-
-    #[test]
-    fn test_propose_emergency_withdraw_unauthorized() {
-        let mut deps = mock_dependencies();
 
-        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
-
-        let info = mock_info(OWNER, &[]);
-        let proposal = ExecuteMsg::ProposeEmergencyWithdraw {
-            dst: Addr::unchecked("destination"),
+        // setting a single address again replaces the split
+        let internal_update = ExecuteMsg::InternalUpdateUnlockedDistributionAddress {
+            unlocked_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
         };
-        let err = execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap_err();
-        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), internal_info, internal_update).unwrap();
+        assert!(!UNLOCK_DISTRIBUTION_SPLIT.has(deps.as_ref().storage, DEFAULT_TRANCHE_ID));
     }
 
     #[test]
-    fn test_propose_gov_vote_works() {
+    fn test_execute_internal_withdraw_locked_all_tranches() {
         let mut deps = mock_dependencies();
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
-
-        let info = mock_info(VOTER1, &[]);
-        let proposal = ExecuteMsg::ProposeGovVote {
-            gov_proposal_id: 1,
-            gov_vote: VoteOption::Yes,
+        setup_test_case(deps.as_mut(), info).unwrap();
+        let info = mock_info(VOTER1, &[Coin::new(1000, "usei".to_string())]);
+        let msg = ExecuteMsg::AddTranche {
+            tranche_id: "advisor".to_string(),
+            tranche: named_tranche(1000),
         };
-        let res = execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        // Verify
+        let info = mock_info(mock_env().contract.address.as_str(), &[]);
+        let proposal = ExecuteMsg::InternalWithdrawLocked {
+            dst: Addr::unchecked("destination"),
+            proposer: Some(Addr::unchecked(VOTER1)),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, proposal).unwrap();
         assert_eq!(
-            res,
-            Response::new()
-                .add_attribute("action", "propose")
-                .add_attribute("sender", VOTER1)
-                .add_attribute("proposal_id", 1.to_string())
-                .add_attribute("status", "Open")
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "destination".to_string(),
+                amount: coins(48001000, "usei"),
+            })
+        );
+        assert_eq!(
+            1000,
+            WITHDRAWN_LOCKED
+                .load(deps.as_ref().storage, "advisor")
+                .unwrap()
+        );
+        assert_eq!(
+            48000000,
+            WITHDRAWN_LOCKED
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
         );
     }
 
     #[test]
-    fn test_vote_works() {
+    fn test_add_cw20_tranche_works() {
         let mut deps = mock_dependencies();
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
 
         let info = mock_info(VOTER1, &[]);
-        let proposal = ExecuteMsg::ProposeUpdateAdmin {
-            admin: Addr::unchecked("new_admin1"),
-            remove: false,
+        let msg = ExecuteMsg::AddTranche {
+            tranche_id: "advisor".to_string(),
+            tranche: cw20_tranche(1000),
         };
-        execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidTranche(
+                "CW20 tranches must be funded through the CW20 receive hook".to_string()
+            )
+        );
 
-        let info = mock_info(VOTER2, &[]);
-        let vote2 = ExecuteMsg::VoteProposal { proposal_id: 1 };
-        execute(deps.as_mut(), mock_env(), info, vote2.clone()).unwrap();
+        let add_tranche = ReceiveMsg::AddTranche {
+            tranche_id: "advisor".to_string(),
+            tranche: Box::new(cw20_tranche(1000)),
+        };
+        let info = mock_info("othertoken", &[]);
+        let msg = receive_msg(VOTER1, 1000, &add_tranche);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidTranche("tranche must vest the received CW20 token".to_string())
+        );
 
-        let info = mock_info(VOTER3, &[]);
-        let vote3 = ExecuteMsg::VoteProposal { proposal_id: 1 };
-        execute(deps.as_mut(), mock_env(), info, vote3.clone()).unwrap();
-    }
+        let info = mock_info(CW20_TOKEN_ADDR, &[]);
+        let msg = receive_msg(VOTER5, 1000, &add_tranche);
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
 
-    #[test]
-    fn test_vote_expired() {
-        let mut deps = mock_dependencies();
+        let msg = receive_msg(VOTER1, 999, &add_tranche);
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidTranche("insufficient deposit for the vesting plan".to_string())
+        );
 
-        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        let msg = receive_msg(VOTER1, 1000, &add_tranche);
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
 
-        let info = mock_info(VOTER1, &[]);
-        let proposal = ExecuteMsg::ProposeUpdateAdmin {
-            admin: Addr::unchecked("new_admin1"),
-            remove: false,
+        let top_up = ReceiveMsg::TopUp {
+            tranche_id: "advisor".to_string(),
+            vesting_timestamps: vec![mock_env().block.time.plus_seconds(200)],
+            vesting_amounts: vec![Uint128::new(500)],
         };
-        execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
+        let msg = receive_msg(VOTER1, 500, &top_up);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg = ExecuteMsg::TopUp {
+            tranche_id: Some("advisor".to_string()),
+            vesting_timestamps: vec![mock_env().block.time.plus_seconds(200)],
+            vesting_amounts: vec![Uint128::new(500)],
+        };
+        let info = mock_info(VOTER1, &[Coin::new(500, "usei".to_string())]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidTranche(
+                "CW20 tranches must be funded through the CW20 receive hook".to_string()
+            )
+        );
+        // native funds outside the tranches do not back CW20 amendments
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(48000000 + 5000, "usei"));
+        let amendment = ScheduleAmendment {
+            cutoff: mock_env().block.time,
+            vesting_timestamps: vec![mock_env().block.time.plus_seconds(300)],
+            vesting_amounts: vec![Uint128::new(1600)],
+            added_amount: Uint128::new(100),
+            clawed_back_amount: Uint128::zero(),
+            clawback_dst: None,
+        };
+        let expected = ContractError::InvalidAmendment(
+            "CW20 tranches must be topped up through the CW20 receive hook".to_string(),
+        );
+        let msg = ExecuteMsg::ProposeAmendSchedule {
+            tranche_id: Some("advisor".to_string()),
+            amendment: amendment.clone(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(VOTER1, &[]), msg).unwrap_err();
+        assert_eq!(err, expected);
+        let msg = ExecuteMsg::InternalAmendSchedule {
+            tranche_id: "advisor".to_string(),
+            amendment,
+            proposer: Addr::unchecked(VOTER1),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MOCK_CONTRACT_ADDR, &[]),
+            msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, expected);
 
-        let info = mock_info(VOTER2, &[]);
-        let vote2 = ExecuteMsg::VoteProposal { proposal_id: 1 };
-        execute(deps.as_mut(), mock_env(), info, vote2.clone()).unwrap();
+        let msg = QueryMsg::TrancheInfo {
+            tranche_id: "advisor".to_string(),
+        };
+        let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: ShowInfoResponse = from_json(&bin).unwrap();
+        assert_eq!(CW20_TOKEN_ADDR.to_string(), res.denom);
+        assert_eq!(Some(Addr::unchecked(CW20_TOKEN_ADDR)), res.cw20_token);
+        assert_eq!(
+            vec![Uint128::new(1000), Uint128::new(500)],
+            res.vesting_amounts
+        );
+        // CW20 tranches are not part of the native principal
+        assert_eq!(
+            48000000,
+            sum_over_native_tranches(deps.as_ref().storage, &TOTAL_AMOUNT).unwrap()
+        );
 
-        let info = mock_info(VOTER3, &[]);
-        let vote3 = ExecuteMsg::VoteProposal { proposal_id: 1 };
         let mut env = mock_env();
-        env.block.time = env.block.time.plus_seconds(3601);
-        let err = execute(deps.as_mut(), env, info, vote3.clone()).unwrap_err();
-        assert_eq!(err, ContractError::Expired {});
+        env.block.time = env.block.time.plus_seconds(100);
+        let info = mock_info(VOTER5, &[]);
+        let msg = ExecuteMsg::InitiateWithdrawTrancheUnlocked {
+            tranche_id: "advisor".to_string(),
+            amount: Uint128::new(1000),
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: CW20_TOKEN_ADDR.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "unlock0002".to_string(),
+                    amount: Uint128::new(1000),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
     }
 
     #[test]
-    fn test_process_update_admin_works() {
+    fn test_execute_internal_withdraw_locked_cw20_tranche() {
         let mut deps = mock_dependencies();
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
+        let info = mock_info(CW20_TOKEN_ADDR, &[]);
+        let msg = receive_msg(
+            VOTER1,
+            1000,
+            &ReceiveMsg::AddTranche {
+                tranche_id: "advisor".to_string(),
+                tranche: Box::new(cw20_tranche(1000)),
+            },
+        );
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        let info = mock_info(VOTER1, &[]);
-        let proposal = ExecuteMsg::ProposeUpdateAdmin {
-            admin: Addr::unchecked("new_admin1"),
-            remove: false,
+        let info = mock_info(mock_env().contract.address.as_str(), &[]);
+        let proposal = ExecuteMsg::InternalWithdrawLocked {
+            dst: Addr::unchecked("destination"),
+            proposer: Some(Addr::unchecked(VOTER1)),
         };
-        execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
-
-        let info = mock_info(VOTER2, &[]);
-        let vote2 = ExecuteMsg::VoteProposal { proposal_id: 1 };
-        execute(deps.as_mut(), mock_env(), info, vote2.clone()).unwrap();
-
-        let info = mock_info(VOTER3, &[]);
-        let vote3 = ExecuteMsg::VoteProposal { proposal_id: 1 };
-        execute(deps.as_mut(), mock_env(), info, vote3.clone()).unwrap();
-
-        let info = mock_info(VOTER3, &[]);
-        let process = ExecuteMsg::ProcessProposal { proposal_id: 1 };
-        let res = execute(deps.as_mut(), mock_env(), info, process.clone()).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), info, proposal).unwrap();
+        assert_eq!(2, res.messages.len());
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: CW20_TOKEN_ADDR.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "destination".to_string(),
+                    amount: Uint128::new(1000),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "destination".to_string(),
+                amount: coins(48000000, "usei"),
+            })
+        );
 
+        // only cw20 funds are locked now, so no native funds are sent
+        let info = mock_info(CW20_TOKEN_ADDR, &[]);
+        let msg = receive_msg(
+            VOTER1,
+            500,
+            &ReceiveMsg::AddTranche {
+                tranche_id: "partner".to_string(),
+                tranche: Box::new(cw20_tranche(500)),
+            },
+        );
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info(mock_env().contract.address.as_str(), &[]);
+        let proposal = ExecuteMsg::InternalWithdrawLocked {
+            dst: Addr::unchecked("destination"),
+            proposer: Some(Addr::unchecked(VOTER1)),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, proposal).unwrap();
         assert_eq!(1, res.messages.len());
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: CW20_TOKEN_ADDR.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "destination".to_string(),
+                    amount: Uint128::new(500),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
     }
 
     #[test]
-    fn test_process_proposal_premature() {
+    fn test_release_vested_works() {
         let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(100000000, "usei"));
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
 
-        let info = mock_info(VOTER1, &[]);
-        let proposal = ExecuteMsg::ProposeUpdateAdmin {
-            admin: Addr::unchecked("new_admin1"),
-            remove: false,
-        };
-        execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(31536000 + 2592000);
+        let keeper = mock_info("keeper", &[]);
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            keeper.clone(),
+            ExecuteMsg::ReleaseVested {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::PermissionlessReleaseDisabled {});
 
-        let info = mock_info(VOTER2, &[]);
-        let vote2 = ExecuteMsg::VoteProposal { proposal_id: 1 };
-        execute(deps.as_mut(), mock_env(), info, vote2.clone()).unwrap();
+        let msg = ExecuteMsg::ProposeUpdatePermissionlessRelease { enabled: true };
+        execute(deps.as_mut(), mock_env(), mock_info(VOTER1, &[]), msg).unwrap();
+        let msg = ExecuteMsg::InternalUpdatePermissionlessRelease { enabled: true };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(VOTER1, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let info = mock_info(mock_env().contract.address.as_str(), &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        let info = mock_info(VOTER3, &[]);
-        let process = ExecuteMsg::ProcessProposal { proposal_id: 1 };
-        let err = execute(deps.as_mut(), mock_env(), info, process.clone()).unwrap_err();
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            keeper.clone(),
+            ExecuteMsg::ReleaseVested {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: UNLOCK_ADDR1.to_string(),
+                amount: coins(13000000, "usei"),
+            })
+        );
+        assert_eq!(
+            13000000,
+            WITHDRAWN_UNLOCKED
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
 
-        assert_eq!(err, ContractError::WrongExecuteStatus {});
+        // nothing left to release until the next vesting timestamp
+        let res = execute(deps.as_mut(), env, keeper, ExecuteMsg::ReleaseVested {}).unwrap();
+        assert_eq!(0, res.messages.len());
     }
 
     #[test]
-    fn test_process_update_admin_double_process() {
+    fn test_propose_clawback_works() {
         let mut deps = mock_dependencies();
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
 
         let info = mock_info(VOTER1, &[]);
-        let proposal = ExecuteMsg::ProposeUpdateAdmin {
-            admin: Addr::unchecked("new_admin1"),
-            remove: false,
+        let proposal = ExecuteMsg::ProposeClawback {
+            tranche_id: None,
+            dst: Addr::unchecked("destination"),
+            clawback: Clawback::Amount {
+                amount: Uint128::new(1000000),
+            },
         };
-        execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
-
-        let info = mock_info(VOTER2, &[]);
-        let vote2 = ExecuteMsg::VoteProposal { proposal_id: 1 };
-        execute(deps.as_mut(), mock_env(), info, vote2.clone()).unwrap();
-
-        let info = mock_info(VOTER3, &[]);
-        let vote3 = ExecuteMsg::VoteProposal { proposal_id: 1 };
-        execute(deps.as_mut(), mock_env(), info, vote3.clone()).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), proposal).unwrap();
+        assert_eq!(
+            res,
+            Response::new()
+                .add_attribute("action", "propose")
+                .add_attribute("sender", VOTER1)
+                .add_attribute("proposal_id", 1.to_string())
+                .add_attribute("status", "Open")
+        );
 
-        let info = mock_info(VOTER3, &[]);
-        let process = ExecuteMsg::ProcessProposal { proposal_id: 1 };
-        execute(deps.as_mut(), mock_env(), info, process.clone()).unwrap();
-        let info = mock_info(VOTER3, &[]);
-        let err = execute(deps.as_mut(), mock_env(), info, process.clone()).unwrap_err();
+        let proposal = ExecuteMsg::ProposeClawback {
+            tranche_id: None,
+            dst: Addr::unchecked("destination"),
+            clawback: Clawback::After {
+                cutoff: Some(mock_env().block.time.minus_seconds(1)),
+            },
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), proposal).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidClawback("cutoff cannot be in the past".to_string())
+        );
 
-        assert_eq!(err, ContractError::WrongExecuteStatus {});
+        let proposal = ExecuteMsg::ProposeClawback {
+            tranche_id: Some("advisor".to_string()),
+            dst: Addr::unchecked("destination"),
+            clawback: Clawback::After { cutoff: None },
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, proposal).unwrap_err();
+        assert_eq!(err, ContractError::TrancheNotFound("advisor".to_string()));
     }
 
     #[test]
-    fn test_process_gov_vote_works() {
+    fn test_execute_internal_clawback_works() {
         let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(100000000, "usei"));
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
 
-        let info = mock_info(VOTER1, &[]);
-        let proposal = ExecuteMsg::ProposeGovVote {
-            gov_proposal_id: 1,
-            gov_vote: VoteOption::Yes,
+        // the first 12000000 vest at the cutoff, plus 1000000 for each month after it
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(31536000 + 2592000);
+        let info = mock_info(mock_env().contract.address.as_str(), &[]);
+        let msg = ExecuteMsg::InternalClawback {
+            tranche_id: DEFAULT_TRANCHE_ID.to_string(),
+            dst: Addr::unchecked("destination"),
+            clawback: Clawback::After { cutoff: None },
+            proposer: Addr::unchecked(VOTER1),
         };
-        execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
-
-        let info = mock_info(VOTER2, &[]);
-        let vote2 = ExecuteMsg::VoteProposal { proposal_id: 1 };
-        execute(deps.as_mut(), mock_env(), info, vote2.clone()).unwrap();
-
-        let info = mock_info(VOTER3, &[]);
-        let vote3 = ExecuteMsg::VoteProposal { proposal_id: 1 };
-        execute(deps.as_mut(), mock_env(), info, vote3.clone()).unwrap();
-
-        let info = mock_info(VOTER3, &[]);
-        let process = ExecuteMsg::ProcessProposal { proposal_id: 1 };
-        let res = execute(deps.as_mut(), mock_env(), info, process.clone()).unwrap();
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "destination".to_string(),
+                amount: coins(35000000, "usei"),
+            })
+        );
+        assert_eq!(
+            35000000,
+            WITHDRAWN_LOCKED
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
+        assert_eq!(
+            vec![12000000, 1000000],
+            load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1
+        );
+        assert_eq!(
+            Addr::unchecked(VOTER1),
+            WITHDRAWALS.load(deps.as_ref().storage, 1).unwrap().sender
+        );
 
-        assert_eq!(1, res.messages.len());
+        // already vested tokens remain withdrawable
+        let info = mock_info(VOTER5, &[]);
+        let msg = ExecuteMsg::InitiateWithdrawUnlocked {
+            amount: Uint128::new(13000000),
+        };
+        execute(deps.as_mut(), env, info, msg).unwrap();
     }
 
     #[test]
-    fn test_execute_internal_update_admin_works() {
+    fn test_reconcile() {
         let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(48000100, "usei"));
+        deps.querier = deps.querier.with_custom_handler(
+            |_: &SeiQueryWrapper| -> MockQuerierCustomHandlerResult {
+                let res = UnbondingDelegationsResponse { entries: vec![] };
+                MockQuerierCustomHandlerResult::Ok(ContractResult::Ok(
+                    to_json_binary(&res).unwrap(),
+                ))
+            },
+        );
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info).unwrap();
 
-        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::Reconcile {}).unwrap();
+        let res: ReconcileResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            ReconcileResponse {
+                tranches: vec![TrancheReconciliation {
+                    tranche_id: DEFAULT_TRANCHE_ID.to_string(),
+                    cw20_token: None,
+                    total_amount: Uint128::new(48000000),
+                    remaining_amount: Uint128::new(48000000),
+                    withdrawn_unlocked: Uint128::new(0),
+                    withdrawn_locked: Uint128::new(0),
+                    accounted_amount: Uint128::new(48000000),
+                }],
+                expected_holdings: Uint128::new(48000000),
+                bank_balance: Uint128::new(48000100),
+                staked: Uint128::new(0),
+                unbonding: Uint128::new(0),
+                surplus: Uint128::new(100),
+                shortfall: Uint128::new(0),
+            },
+            res
+        );
 
-        let info = mock_info(mock_env().contract.address.as_str(), &[]);
-        let msg = ExecuteMsg::InternalUpdateAdmin {
-            admin: Addr::unchecked("new_admin1"),
-            remove: false,
+        let msg = ExecuteMsg::ProposeCorrectTotalAmount {
+            tranche_id: None,
+            explanation: "nothing to explain".to_string(),
         };
-        execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
-        ADMINS
-            .load(deps.as_ref().storage, &Addr::unchecked("new_admin1"))
+        assert_eq!(
+            ContractError::InvalidCorrection(
+                "tranche default has no accounting discrepancy".to_string()
+            ),
+            execute(deps.as_mut(), mock_env(), mock_info(VOTER1, &[]), msg).unwrap_err()
+        );
+
+        // a migration that forgot to update the total amount
+        TOTAL_AMOUNT
+            .save(&mut deps.storage, DEFAULT_TRANCHE_ID, &50000000)
             .unwrap();
-        assert_eq!(5, get_number_of_admins(deps.as_ref().storage));
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::Reconcile {}).unwrap();
+        let res: ReconcileResponse = from_json(&bin).unwrap();
+        assert_eq!(Uint128::new(50000000), res.tranches[0].total_amount);
+        assert_eq!(Uint128::new(48000000), res.tranches[0].accounted_amount);
+
+        let msg = ExecuteMsg::ProposeCorrectTotalAmount {
+            tranche_id: None,
+            explanation: "".to_string(),
+        };
+        assert_eq!(
+            ContractError::InvalidCorrection("explanation cannot be empty".to_string()),
+            execute(deps.as_mut(), mock_env(), mock_info(VOTER1, &[]), msg).unwrap_err()
+        );
+        let msg = ExecuteMsg::ProposeCorrectTotalAmount {
+            tranche_id: None,
+            explanation: "amounts replaced by the 0.1.5 migration".to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(VOTER1, &[]), msg).unwrap();
+        let proposal = PROPOSALS.load(deps.as_ref().storage, 1).unwrap();
+        assert_eq!(
+            "correct total amount of tranche default from 50000000 to 48000000: amounts replaced by the 0.1.5 migration",
+            proposal.title
+        );
+
+        let self_info = mock_info(mock_env().contract.address.as_str(), &[]);
+        let msg = ExecuteMsg::InternalCorrectTotalAmount {
+            tranche_id: DEFAULT_TRANCHE_ID.to_string(),
+            total_amount: Uint128::new(47000000),
+        };
+        assert_eq!(
+            ContractError::InvalidCorrection(
+                "accounting of tranche default changed since the proposal".to_string()
+            ),
+            execute(deps.as_mut(), mock_env(), self_info.clone(), msg).unwrap_err()
+        );
+        let msg = ExecuteMsg::InternalCorrectTotalAmount {
+            tranche_id: DEFAULT_TRANCHE_ID.to_string(),
+            total_amount: Uint128::new(48000000),
+        };
+        execute(deps.as_mut(), mock_env(), self_info, msg).unwrap();
+        assert_eq!(
+            48000000,
+            TOTAL_AMOUNT
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
     }
 
     #[test]
-    fn test_execute_internal_update_admin_remove_works() {
+    fn test_milestones_work() {
         let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(100000000, "usei"));
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
 
-        let info = mock_info(mock_env().contract.address.as_str(), &[]);
-        let msg = ExecuteMsg::InternalUpdateAdmin {
-            admin: Addr::unchecked(VOTER1),
-            remove: true,
+        let deadline = mock_env().block.time.plus_seconds(1000);
+        let mut tranche = named_tranche(0);
+        tranche.vesting_amounts = vec![];
+        tranche.vesting_timestamps = vec![];
+        tranche.milestones = Some(vec![
+            Milestone {
+                milestone_id: "mainnet".to_string(),
+                amount: Uint128::new(500),
+                deadline: None,
+            },
+            Milestone {
+                milestone_id: "audit".to_string(),
+                amount: Uint128::new(300),
+                deadline: Some(deadline),
+            },
+        ]);
+        let info = mock_info(VOTER1, &[Coin::new(800, "usei".to_string())]);
+        let msg = ExecuteMsg::AddTranche {
+            tranche_id: "advisor".to_string(),
+            tranche,
         };
-        execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
-        ADMINS
-            .load(deps.as_ref().storage, &Addr::unchecked(VOTER1))
-            .unwrap_err();
-        assert_eq!(3, get_number_of_admins(deps.as_ref().storage));
-    }
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            800,
+            TOTAL_AMOUNT.load(deps.as_ref().storage, "advisor").unwrap()
+        );
 
-    #[test]
-    fn test_execute_internal_update_admin_unauthorized() {
-        let mut deps = mock_dependencies();
+        let info = mock_info(VOTER1, &[]);
+        let proposal = ExecuteMsg::ProposeMilestoneReached {
+            tranche_id: Some("advisor".to_string()),
+            milestone_id: "mainnet".to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), proposal).unwrap();
+        let proposal = ExecuteMsg::ProposeMilestoneReached {
+            tranche_id: Some("advisor".to_string()),
+            milestone_id: "testnet".to_string(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, proposal).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidMilestone("milestone testnet not found".to_string())
+        );
 
-        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        let self_info = mock_info(mock_env().contract.address.as_str(), &[]);
+        let msg = ExecuteMsg::InternalMilestoneReached {
+            tranche_id: "advisor".to_string(),
+            milestone_id: "mainnet".to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), self_info.clone(), msg.clone()).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), self_info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidMilestone(
+                "milestone mainnet has already been reached".to_string()
+            )
+        );
 
-        let info = mock_info(VOTER1, &[]);
-        let msg = ExecuteMsg::InternalUpdateAdmin {
-            admin: Addr::unchecked("new_admin1"),
-            remove: false,
+        let msg = QueryMsg::TrancheTotalVested {
+            tranche_id: "advisor".to_string(),
         };
-        let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
-        assert_eq!(err, ContractError::Unauthorized {});
-        ADMINS
-            .load(deps.as_ref().storage, &Addr::unchecked("new_admin1"))
-            .unwrap_err();
-        assert_eq!(4, get_number_of_admins(deps.as_ref().storage));
-    }
+        let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: ShowTotalVestedResponse = from_json(&bin).unwrap();
+        assert_eq!(res.vested_amount, Uint128::new(500));
 
-    #[test]
-    fn test_execute_update_op_works() {
-        let mut deps = mock_dependencies();
+        let msg = ExecuteMsg::InitiateWithdrawTrancheUnlocked {
+            tranche_id: "advisor".to_string(),
+            amount: Uint128::new(500),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(VOTER5, &[]), msg).unwrap();
 
-        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        // the audit milestone lapses past its deadline and can then be clawed back
+        let mut env = mock_env();
+        env.block.time = deadline.plus_seconds(1);
+        let msg = QueryMsg::Milestones {
+            tranche_id: Some("advisor".to_string()),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: MilestonesResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            res.milestones,
+            vec![MilestoneEntry {
+                milestone_id: "audit".to_string(),
+                milestone: MilestoneState {
+                    amount: Uint128::new(300),
+                    deadline: Some(SchedulePoint::Time(deadline)),
+                    reached: false,
+                },
+                lapsed: true,
+            }]
+        );
 
-        let info = mock_info(VOTER1, &[]);
-        let msg = ExecuteMsg::UpdateOp {
-            op: Addr::unchecked("new_op1"),
-            remove: false,
+        let msg = ExecuteMsg::InternalMilestoneReached {
+            tranche_id: "advisor".to_string(),
+            milestone_id: "audit".to_string(),
         };
-        execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
-        OPS.load(deps.as_ref().storage, &Addr::unchecked("new_op1"))
-            .unwrap();
-        assert_eq!(3, get_number_of_ops(deps.as_ref().storage));
+        let err = execute(deps.as_mut(), env.clone(), self_info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidMilestone("milestone audit has lapsed".to_string())
+        );
+
+        let msg = ExecuteMsg::InternalClawback {
+            tranche_id: "advisor".to_string(),
+            dst: Addr::unchecked("destination"),
+            clawback: Clawback::LapsedMilestones {},
+            proposer: Addr::unchecked(VOTER1),
+        };
+        let res = execute(deps.as_mut(), env, self_info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "destination".to_string(),
+                amount: coins(300, "usei"),
+            })
+        );
+        assert_eq!(
+            300,
+            WITHDRAWN_LOCKED
+                .load(deps.as_ref().storage, "advisor")
+                .unwrap()
+        );
+        assert!(MILESTONES
+            .prefix("advisor")
+            .range(deps.as_ref().storage, None, None, Order::Ascending)
+            .next()
+            .is_none());
     }
 
     #[test]
-    fn test_execute_update_op_remove_works() {
+    fn test_propose_amend_schedule_works() {
         let mut deps = mock_dependencies();
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
 
+        let env = mock_env();
         let info = mock_info(VOTER1, &[]);
-        let msg = ExecuteMsg::UpdateOp {
-            op: Addr::unchecked(VOTER5),
-            remove: true,
+        let mut amendment = ScheduleAmendment {
+            cutoff: env.block.time,
+            vesting_timestamps: vec![env.block.time.plus_seconds(100)],
+            vesting_amounts: vec![Uint128::new(47000000)],
+            added_amount: Uint128::new(0),
+            clawed_back_amount: Uint128::new(1000000),
+            clawback_dst: Some(Addr::unchecked("destination")),
         };
-        execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
-        OPS.load(deps.as_ref().storage, &Addr::unchecked(VOTER5))
-            .unwrap_err();
-        assert_eq!(1, get_number_of_ops(deps.as_ref().storage));
+        let proposal = ExecuteMsg::ProposeAmendSchedule {
+            tranche_id: None,
+            amendment: amendment.clone(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), proposal).unwrap();
+        assert_eq!(
+            res,
+            Response::new()
+                .add_attribute("action", "propose")
+                .add_attribute("sender", VOTER1)
+                .add_attribute("proposal_id", 1.to_string())
+                .add_attribute("status", "Open")
+        );
+
+        amendment.clawback_dst = None;
+        let proposal = ExecuteMsg::ProposeAmendSchedule {
+            tranche_id: None,
+            amendment: amendment.clone(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), proposal).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidAmendment(
+                "clawback destination must be set if and only if funds are clawed back".to_string()
+            )
+        );
+
+        amendment.vesting_amounts = vec![Uint128::new(0)];
+        let proposal = ExecuteMsg::ProposeAmendSchedule {
+            tranche_id: None,
+            amendment,
+        };
+        let err = execute(deps.as_mut(), env, info, proposal).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidTranche("zero vesting amount is not allowed".to_string())
+        );
     }
 
     #[test]
-    fn test_execute_update_op_unauthorized() {
+    fn test_execute_internal_amend_schedule_works() {
         let mut deps = mock_dependencies();
+        deps.querier.update_balance(
+            mock_env().contract.address.clone(),
+            vec![Coin::new(49000000, "usei")],
+        );
+        deps.querier = deps.querier.with_custom_handler(
+            |_: &SeiQueryWrapper| -> MockQuerierCustomHandlerResult {
+                let res = UnbondingDelegationsResponse { entries: vec![] };
+                MockQuerierCustomHandlerResult::Ok(ContractResult::Ok(
+                    to_json_binary(&res).unwrap(),
+                ))
+            },
+        );
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
 
-        let info = mock_info(VOTER5, &[]);
-        let msg = ExecuteMsg::UpdateOp {
-            op: Addr::unchecked("new_op1"),
-            remove: false,
+        // 12000000 + 12 * 1000000 vest by the cutoff and are left untouched
+        let env = mock_env();
+        let cutoff = env.block.time.plus_seconds(31536000 + 12 * 2592000);
+        let mut amendment = ScheduleAmendment {
+            cutoff,
+            vesting_timestamps: vec![cutoff.plus_seconds(1000)],
+            vesting_amounts: vec![Uint128::new(26000000)],
+            added_amount: Uint128::new(2000000),
+            clawed_back_amount: Uint128::new(0),
+            clawback_dst: None,
         };
-        let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
-        assert_eq!(err, ContractError::Unauthorized {});
-        OPS.load(deps.as_ref().storage, &Addr::unchecked("new_op1"))
-            .unwrap_err();
-        assert_eq!(2, get_number_of_ops(deps.as_ref().storage));
+        let info = mock_info(env.contract.address.as_str(), &[]);
+        let msg = ExecuteMsg::InternalAmendSchedule {
+            tranche_id: DEFAULT_TRANCHE_ID.to_string(),
+            amendment: amendment.clone(),
+            proposer: Addr::unchecked(VOTER1),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidAmendment(
+                "insufficient unallocated funds to add to the schedule".to_string()
+            )
+        );
+
+        amendment.vesting_amounts = vec![Uint128::new(25000000)];
+        amendment.added_amount = Uint128::new(1000000);
+        let msg = ExecuteMsg::InternalAmendSchedule {
+            tranche_id: DEFAULT_TRANCHE_ID.to_string(),
+            amendment,
+            proposer: Addr::unchecked(VOTER1),
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+        assert_eq!(
+            49000000,
+            TOTAL_AMOUNT
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
+        let vesting_amounts = load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+            .unwrap()
+            .1;
+        assert_eq!(14, vesting_amounts.len());
+        assert_eq!(25000000, *vesting_amounts.last().unwrap());
+        assert_eq!(
+            cutoff.plus_seconds(1000),
+            *load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0
+                .last()
+                .unwrap()
+        );
     }
 
     #[test]
-    fn test_execute_internal_withdraw_locked_works() {
+    fn test_top_up_works() {
         let mut deps = mock_dependencies();
 
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
-        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        setup_test_case(deps.as_mut(), info).unwrap();
 
-        let info = mock_info(mock_env().contract.address.as_str(), &[]);
-        let proposal = ExecuteMsg::InternalWithdrawLocked {
-            dst: Addr::unchecked("destination"),
+        let env = mock_env();
+        let first_ts = env.block.time.plus_seconds(31536000);
+        let msg = ExecuteMsg::TopUp {
+            tranche_id: None,
+            vesting_timestamps: vec![first_ts, first_ts.plus_seconds(1)],
+            vesting_amounts: vec![Uint128::new(1000), Uint128::new(2000)],
         };
-        let res = execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap();
-        assert_eq!(1, res.messages.len());
+
+        let info = mock_info(VOTER5, &[Coin::new(3000, "usei".to_string())]);
+        let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let info = mock_info(VOTER1, &[Coin::new(2999, "usei".to_string())]);
+        let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
         assert_eq!(
-            vec![] as Vec<u128>,
-            VESTING_AMOUNTS.load(deps.as_ref().storage).unwrap()
+            err,
+            ContractError::InvalidTranche("insufficient deposit for the vesting plan".to_string())
         );
+
+        let info = mock_info(VOTER1, &[Coin::new(3000, "usei".to_string())]);
+        execute(deps.as_mut(), env, info, msg).unwrap();
         assert_eq!(
-            vec![] as Vec<Timestamp>,
-            VESTING_TIMESTAMPS.load(deps.as_ref().storage).unwrap()
+            48003000,
+            TOTAL_AMOUNT
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
         );
+        let vesting_amounts = load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+            .unwrap()
+            .1;
+        assert_eq!(38, vesting_amounts.len());
+        assert_eq!(vec![12001000, 2000, 1000000], vesting_amounts[..3].to_vec());
         assert_eq!(
-            48000000,
-            WITHDRAWN_LOCKED.load(deps.as_ref().storage).unwrap()
+            first_ts.plus_seconds(1),
+            load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0[1]
         );
     }
 
@@ -1760,21 +6298,30 @@ mod tests {
         let info = mock_info(VOTER1, &[]);
         let proposal = ExecuteMsg::InternalWithdrawLocked {
             dst: Addr::unchecked("destination"),
+            proposer: Some(Addr::unchecked(VOTER1)),
         };
         let err = execute(deps.as_mut(), mock_env(), info, proposal.clone()).unwrap_err();
         assert_eq!(ContractError::Unauthorized {}, err);
         assert_eq!(
             37,
-            VESTING_AMOUNTS.load(deps.as_ref().storage).unwrap().len()
+            load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1
+                .len()
         );
         assert_eq!(
             37,
-            VESTING_TIMESTAMPS
-                .load(deps.as_ref().storage)
+            load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
                 .unwrap()
+                .0
                 .len()
         );
-        assert_eq!(0, WITHDRAWN_LOCKED.load(deps.as_ref().storage).unwrap());
+        assert_eq!(
+            0,
+            WITHDRAWN_LOCKED
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
     }
 
     #[test]
@@ -1803,7 +6350,7 @@ mod tests {
             .unwrap();
         let msg = QueryMsg::ListProposals {};
         let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let res: ProposalListResponse = from_binary(&bin).unwrap();
+        let res: ProposalListResponse = from_json(&bin).unwrap();
         assert_eq!(
             res.proposals,
             vec![ProposalResponse {
@@ -1848,7 +6395,7 @@ mod tests {
             .unwrap();
         let msg = QueryMsg::ListVotes { proposal_id: 1 };
         let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let res: VoteListResponse = from_binary(&bin).unwrap();
+        let res: VoteListResponse = from_json(&bin).unwrap();
         assert_eq!(
             res.votes,
             vec![VoteInfo {
@@ -1868,7 +6415,7 @@ mod tests {
         setup_test_case(deps.as_mut(), info.clone()).unwrap();
         let msg = QueryMsg::ListAdmins {};
         let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let res: AdminListResponse = from_binary(&bin).unwrap();
+        let res: AdminListResponse = from_json(&bin).unwrap();
         assert_eq!(
             res.admins,
             vec![
@@ -1888,7 +6435,7 @@ mod tests {
         setup_test_case(deps.as_mut(), info.clone()).unwrap();
         let msg = QueryMsg::ListOps {};
         let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let res: OpListResponse = from_binary(&bin).unwrap();
+        let res: OpListResponse = from_json(&bin).unwrap();
         assert_eq!(
             res.ops,
             vec![Addr::unchecked(VOTER5), Addr::unchecked(VOTER6),]
@@ -1903,18 +6450,31 @@ mod tests {
         setup_test_case(deps.as_mut(), info.clone()).unwrap();
         let msg = QueryMsg::Info {};
         let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let res: ShowInfoResponse = from_binary(&bin).unwrap();
+        let res: ShowInfoResponse = from_json(&bin).unwrap();
         assert_eq!(
             res,
             ShowInfoResponse {
                 denom: "usei".to_string(),
-                vesting_timestamps: VESTING_TIMESTAMPS.load(deps.as_ref().storage).unwrap(),
-                vesting_amounts: VESTING_AMOUNTS.load(deps.as_ref().storage).unwrap(),
+                cw20_token: None,
+                schedule_unit: ScheduleUnit::Time,
+                vesting_timestamps: load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                    .unwrap()
+                    .0,
+                vesting_amounts: load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                    .unwrap()
+                    .1
+                    .into_iter()
+                    .map(Uint128::new)
+                    .collect(),
+                linear_vesting: None,
                 unlock_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
+                unlock_distribution_split: None,
                 staking_reward_address: Addr::unchecked(REWARD_ADDR1),
-                withdrawn_staking_rewards: 0,
-                withdrawn_locked: 0,
-                withdrawn_unlocked: 0,
+                staking_reward_split: None,
+                withdrawn_staking_rewards: Uint128::new(0),
+                compounded_staking_rewards: Uint128::new(0),
+                withdrawn_locked: Uint128::new(0),
+                withdrawn_unlocked: Uint128::new(0),
             }
         );
     }
@@ -1927,7 +6487,7 @@ mod tests {
         setup_test_case(deps.as_mut(), info.clone()).unwrap();
         let msg = QueryMsg::Config {};
         let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let res: ShowConfigResponse = from_binary(&bin).unwrap();
+        let res: ShowConfigResponse = from_json(&bin).unwrap();
         assert_eq!(
             res,
             ShowConfigResponse {
@@ -1935,10 +6495,70 @@ mod tests {
                 admin_voting_threshold: Threshold::AbsolutePercentage {
                     percentage: Decimal::percent(75)
                 },
+                permissionless_release: false,
+                undelegate_for_withdrawals: false,
+                validation_policy: ValidationPolicy::default(),
+                delegation_cap: None,
+                reward_policy: RewardPolicy::Send {},
             }
         );
     }
 
+    #[test]
+    fn test_validation_policy() {
+        let mut deps = mock_dependencies();
+        let policy = ValidationPolicy {
+            max_horizon: None,
+            max_entries: Some(2),
+            min_interval: Some(60),
+            allow_past_start: false,
+        };
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![Addr::unchecked(VOTER1)],
+            ops: vec![Addr::unchecked(VOTER5)],
+            tranche: named_tranche(100),
+            max_voting_period: Duration::Time(3600),
+            admin_voting_threshold_percentage: 75,
+            validation_policy: Some(policy.clone()),
+            unbonding_period: None,
+        };
+        let info = mock_info(OWNER, &[Coin::new(100, "usei".to_string())]);
+        instantiate(deps.as_mut(), mock_env(), info, instantiate_msg).unwrap();
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let res: ShowConfigResponse = from_json(&bin).unwrap();
+        assert_eq!(policy, res.validation_policy);
+
+        // the policy applies to the schedule that results from a top-up
+        let now = mock_env().block.time;
+        let info = mock_info(VOTER1, &[Coin::new(50, "usei".to_string())]);
+        let msg = ExecuteMsg::TopUp {
+            tranche_id: None,
+            vesting_timestamps: vec![now.plus_seconds(130)],
+            vesting_amounts: vec![Uint128::new(50)],
+        };
+        assert_eq!(
+            ContractError::InvalidTranche("vesting entries must be at least 60 apart".to_string()),
+            execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err()
+        );
+        let msg = ExecuteMsg::TopUp {
+            tranche_id: None,
+            vesting_timestamps: vec![now.plus_seconds(200)],
+            vesting_amounts: vec![Uint128::new(50)],
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::TopUp {
+            tranche_id: None,
+            vesting_timestamps: vec![now.plus_seconds(300)],
+            vesting_amounts: vec![Uint128::new(50)],
+        };
+        assert_eq!(
+            ContractError::InvalidTranche(
+                "vesting schedule cannot have more than 2 entries".to_string()
+            ),
+            execute(deps.as_mut(), mock_env(), info, msg).unwrap_err()
+        );
+    }
+
     #[test]
     fn test_query_total_vested_amount() {
         let mut deps = mock_dependencies();
@@ -1946,12 +6566,234 @@ mod tests {
         let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
         setup_test_case(deps.as_mut(), info.clone()).unwrap();
         let msg = QueryMsg::TotalVested {};
-        let vesting_timestamps = VESTING_TIMESTAMPS.load(deps.as_ref().storage);
+        let vesting_timestamps = load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+            .unwrap()
+            .0;
+        let mut env = mock_env();
+        env.block.time = *(vesting_timestamps.first().unwrap());
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: ShowTotalVestedResponse = from_json(&bin).unwrap();
+        assert_eq!(res.vested_amount, Uint128::new(12000000));
+    }
+
+    #[test]
+    fn test_query_vesting_schedule() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info).unwrap();
+        let first_ts = mock_env().block.time.plus_seconds(31536000);
+
+        let msg = QueryMsg::VestingSchedule {
+            tranche_id: None,
+            start_after: None,
+            limit: Some(2),
+        };
+        let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: VestingScheduleResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            vec![
+                VestingEvent {
+                    index: 0,
+                    timestamp: first_ts,
+                    amount: Uint128::new(12000000),
+                    cumulative_amount: Uint128::new(12000000),
+                },
+                VestingEvent {
+                    index: 1,
+                    timestamp: first_ts.plus_seconds(2592000),
+                    amount: Uint128::new(1000000),
+                    cumulative_amount: Uint128::new(13000000),
+                },
+            ],
+            res.events
+        );
+
+        let msg = QueryMsg::VestingSchedule {
+            tranche_id: None,
+            start_after: Some(1),
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: VestingScheduleResponse = from_json(&bin).unwrap();
+        assert_eq!(10, res.events.len());
+        assert_eq!(2, res.events[0].index);
+        assert_eq!(Uint128::new(14000000), res.events[0].cumulative_amount);
+
+        // the first entry is consumed and the second one partially withdrawn
+        collect_vested(
+            deps.as_mut().storage,
+            DEFAULT_TRANCHE_ID,
+            first_ts.plus_seconds(2592000),
+            12500000,
+        )
+        .unwrap();
+        let msg = QueryMsg::VestingSchedule {
+            tranche_id: None,
+            start_after: None,
+            limit: Some(2),
+        };
+        let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: VestingScheduleResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            vec![
+                VestingEvent {
+                    index: 1,
+                    timestamp: first_ts.plus_seconds(2592000),
+                    amount: Uint128::new(500000),
+                    cumulative_amount: Uint128::new(500000),
+                },
+                VestingEvent {
+                    index: 2,
+                    timestamp: first_ts.plus_seconds(2 * 2592000),
+                    amount: Uint128::new(1000000),
+                    cumulative_amount: Uint128::new(1500000),
+                },
+            ],
+            res.events
+        );
+        let msg = QueryMsg::VestingSchedule {
+            tranche_id: None,
+            start_after: Some(1),
+            limit: Some(1),
+        };
+        let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: VestingScheduleResponse = from_json(&bin).unwrap();
+        assert_eq!(Uint128::new(1500000), res.events[0].cumulative_amount);
+
+        let msg = QueryMsg::VestingSchedule {
+            tranche_id: Some("advisor".to_string()),
+            start_after: None,
+            limit: None,
+        };
+        query(deps.as_ref(), mock_env(), msg).unwrap_err();
+    }
+
+    #[test]
+    fn test_query_next_unlock_and_vested_at() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info).unwrap();
+        let first_ts = mock_env().block.time.plus_seconds(31536000);
+
+        let msg = QueryMsg::NextUnlock { tranche_id: None };
+        let bin = query(deps.as_ref(), mock_env(), msg.clone()).unwrap();
+        let res: NextUnlockResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            NextUnlockResponse {
+                schedule_unit: ScheduleUnit::Time,
+                timestamp: Some(first_ts),
+                amount: Uint128::new(12000000),
+            },
+            res
+        );
+
+        let mut env = mock_env();
+        env.block.time = first_ts;
+        let bin = query(deps.as_ref(), env, msg.clone()).unwrap();
+        let res: NextUnlockResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            NextUnlockResponse {
+                schedule_unit: ScheduleUnit::Time,
+                timestamp: Some(first_ts.plus_seconds(2592000)),
+                amount: Uint128::new(1000000),
+            },
+            res
+        );
+
         let mut env = mock_env();
-        env.block.time = *(vesting_timestamps.unwrap().first().unwrap());
+        env.block.time = first_ts.plus_seconds(36 * 2592000);
         let bin = query(deps.as_ref(), env, msg).unwrap();
-        let res: ShowTotalVestedResponse = from_binary(&bin).unwrap();
-        assert_eq!(res.vested_amount, 12000000);
+        let res: NextUnlockResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            NextUnlockResponse {
+                schedule_unit: ScheduleUnit::Time,
+                timestamp: None,
+                amount: Uint128::new(0),
+            },
+            res
+        );
+
+        let msg = QueryMsg::VestedAt {
+            tranche_id: None,
+            at: SchedulePoint::Time(first_ts.plus_seconds(2592000)),
+        };
+        let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: ShowTotalVestedResponse = from_json(&bin).unwrap();
+        assert_eq!(Uint128::new(13000000), res.vested_amount);
+    }
+
+    #[test]
+    fn test_instantiate_with_schedule_spec() {
+        let mut deps = mock_dependencies();
+
+        let env = mock_env();
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![Addr::unchecked(VOTER1)],
+            ops: vec![Addr::unchecked(VOTER5)],
+            tranche: Tranche {
+                schedule_spec: Some(VestingScheduleSpec {
+                    start: env.block.time,
+                    cliff_periods: 12,
+                    num_periods: 48,
+                    period: VestingPeriod::Months(1),
+                    total_amount: Uint128::new(48000000),
+                    remainder_handling: RemainderHandling::AddToLast,
+                }),
+                ..test_tranche(vec![], vec![])
+            },
+            max_voting_period: Duration::Time(3600),
+            admin_voting_threshold_percentage: 75,
+            validation_policy: None,
+            unbonding_period: None,
+        };
+        instantiate(deps.as_mut(), env.clone(), info, instantiate_msg).unwrap();
+
+        let vesting_amounts = load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+            .unwrap()
+            .1;
+        let vesting_timestamps = load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+            .unwrap()
+            .0;
+        assert_eq!(37, vesting_amounts.len());
+        assert_eq!(12000000, vesting_amounts[0]);
+        assert_eq!(
+            crate::schedule::add_months(env.block.time, 12).unwrap(),
+            vesting_timestamps[0]
+        );
+        assert_eq!(
+            48000000,
+            TOTAL_AMOUNT
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_query_expand_schedule_spec() {
+        let deps = mock_dependencies();
+        let start = mock_env().block.time;
+        let msg = QueryMsg::ExpandScheduleSpec {
+            spec: VestingScheduleSpec {
+                start,
+                cliff_periods: 0,
+                num_periods: 2,
+                period: VestingPeriod::Time(60),
+                total_amount: Uint128::new(101),
+                remainder_handling: RemainderHandling::AddToFirst,
+            },
+        };
+        let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: ShowExpandedScheduleResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            res,
+            ShowExpandedScheduleResponse {
+                vesting_timestamps: vec![start.plus_seconds(60), start.plus_seconds(120)],
+                vesting_amounts: vec![Uint128::new(51), Uint128::new(50)],
+            }
+        );
     }
 
     #[test]
@@ -1972,6 +6814,14 @@ mod tests {
             _ => panic!("This should raise error on contract type mismatch"),
         };
 
+        deps.querier = deps.querier.with_custom_handler(
+            |_: &SeiQueryWrapper| -> MockQuerierCustomHandlerResult {
+                let res = UnbondingDelegationsResponse { entries: vec![] };
+                MockQuerierCustomHandlerResult::Ok(ContractResult::Ok(
+                    to_json_binary(&res).unwrap(),
+                ))
+            },
+        );
         // set contract version to older one so we can test migrations
         cw2::set_contract_version(&mut deps.storage, CONTRACT_NAME, "0.0.1").unwrap();
 
@@ -1988,4 +6838,269 @@ mod tests {
             _ => panic!("This should raise error on version validation failure"),
         };
     }
+
+    #[test]
+    fn test_migrate_to_multi_tranche() {
+        let mut deps = staked_deps(&[("val1", 1000)]);
+        DENOM.save(&mut deps.storage, &"usei".to_string()).unwrap();
+
+        // the ledger cannot be seeded without the unbonding entries
+        migrate_200_handler(deps.as_mut(), mock_env()).unwrap_err();
+        deps.querier = deps.querier.with_custom_handler(
+            |_: &SeiQueryWrapper| -> MockQuerierCustomHandlerResult {
+                let res = UnbondingDelegationsResponse {
+                    entries: vec![UnbondingDelegationEntry {
+                        creation_height: 1,
+                        completion_time: "2022-10-12T00:00:00Z".to_string(),
+                        initial_balance: Uint128::new(20),
+                        balance: Uint128::new(10),
+                    }],
+                };
+                MockQuerierCustomHandlerResult::Ok(ContractResult::Ok(
+                    to_json_binary(&res).unwrap(),
+                ))
+            },
+        );
+
+        let now = mock_env().block.time;
+        LEGACY_VESTING_TIMESTAMPS
+            .save(&mut deps.storage, &vec![now])
+            .unwrap();
+        LEGACY_VESTING_AMOUNTS
+            .save(&mut deps.storage, &vec![100])
+            .unwrap();
+        LEGACY_TOTAL_AMOUNT.save(&mut deps.storage, &150).unwrap();
+        LEGACY_UNLOCK_DISTRIBUTION_ADDRESS
+            .save(&mut deps.storage, &Addr::unchecked(UNLOCK_ADDR1))
+            .unwrap();
+        LEGACY_WITHDRAWN_UNLOCKED
+            .save(&mut deps.storage, &50)
+            .unwrap();
+        LEGACY_WITHDRAWN_LOCKED.save(&mut deps.storage, &0).unwrap();
+        cw2::set_contract_version(&mut deps.storage, CONTRACT_NAME, "0.1.9").unwrap();
+
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+        assert_eq!(
+            vec![now],
+            load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0
+        );
+        assert_eq!(
+            vec![100],
+            load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1
+        );
+        assert_eq!(
+            150,
+            TOTAL_AMOUNT
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
+        assert_eq!(
+            50,
+            WITHDRAWN_UNLOCKED
+                .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            LEGACY_VESTING_TIMESTAMPS
+                .may_load(deps.as_ref().storage)
+                .unwrap()
+        );
+        // stake and unbonding balances predating the ledger are principal
+        assert_eq!(
+            1000,
+            DELEGATED_PRINCIPAL
+                .load(deps.as_ref().storage, "val1")
+                .unwrap()
+        );
+        assert_eq!(
+            vec![(
+                1,
+                UnbondingPrincipal {
+                    amount: Uint128::new(10),
+                    completion_time: Timestamp::from_seconds(1665532800),
+                }
+            )],
+            UNBONDING_PRINCIPAL
+                .range(deps.as_ref().storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_migrate_from_109() {
+        let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(100000000, "usei"));
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(31536000);
+        let msg = ExecuteMsg::InitiateWithdrawUnlocked {
+            amount: Uint128::new(1000),
+        };
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let msg = QueryMsg::Info {};
+        let bin = query(deps.as_ref(), env.clone(), msg.clone()).unwrap();
+        let before: ShowInfoResponse = from_json(&bin).unwrap();
+
+        // rewrite the tranche in the single-tranche layout of 0.1.9
+        let (vesting_timestamps, vesting_amounts) =
+            load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID).unwrap();
+        save_schedule(&mut deps.storage, DEFAULT_TRANCHE_ID, &[], &[]).unwrap();
+        SCHEDULE_CURSOR.remove(&mut deps.storage, DEFAULT_TRANCHE_ID);
+        LEGACY_VESTING_TIMESTAMPS
+            .save(&mut deps.storage, &vesting_timestamps)
+            .unwrap();
+        LEGACY_VESTING_AMOUNTS
+            .save(&mut deps.storage, &vesting_amounts)
+            .unwrap();
+        let total_amount = TOTAL_AMOUNT
+            .load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+            .unwrap();
+        LEGACY_TOTAL_AMOUNT
+            .save(&mut deps.storage, &total_amount)
+            .unwrap();
+        LEGACY_UNLOCK_DISTRIBUTION_ADDRESS
+            .save(&mut deps.storage, &before.unlock_distribution_address)
+            .unwrap();
+        LEGACY_WITHDRAWN_UNLOCKED
+            .save(&mut deps.storage, &before.withdrawn_unlocked.u128())
+            .unwrap();
+        LEGACY_WITHDRAWN_LOCKED
+            .save(&mut deps.storage, &before.withdrawn_locked.u128())
+            .unwrap();
+        TOTAL_AMOUNT.remove(&mut deps.storage, DEFAULT_TRANCHE_ID);
+        UNLOCK_DISTRIBUTION_ADDRESS.remove(&mut deps.storage, DEFAULT_TRANCHE_ID);
+        WITHDRAWN_UNLOCKED.remove(&mut deps.storage, DEFAULT_TRANCHE_ID);
+        WITHDRAWN_LOCKED.remove(&mut deps.storage, DEFAULT_TRANCHE_ID);
+        deps.querier = deps.querier.with_custom_handler(
+            |_: &SeiQueryWrapper| -> MockQuerierCustomHandlerResult {
+                let res = UnbondingDelegationsResponse { entries: vec![] };
+                MockQuerierCustomHandlerResult::Ok(ContractResult::Ok(
+                    to_json_binary(&res).unwrap(),
+                ))
+            },
+        );
+        cw2::set_contract_version(&mut deps.storage, CONTRACT_NAME, "0.1.9").unwrap();
+
+        migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let after: ShowInfoResponse = from_json(&bin).unwrap();
+        assert_eq!(before, after);
+        assert_eq!(
+            None,
+            LEGACY_VESTING_AMOUNTS
+                .may_load(deps.as_ref().storage)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_migrate_to_indexed_schedule() {
+        let mut deps = mock_dependencies();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(100000000, "usei"));
+
+        let info = mock_info(VOTER5, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info.clone()).unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(31536000);
+        let msg = ExecuteMsg::InitiateWithdrawUnlocked {
+            amount: Uint128::new(1000),
+        };
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let msg = QueryMsg::Info {};
+        let bin = query(deps.as_ref(), env.clone(), msg.clone()).unwrap();
+        let before: ShowInfoResponse = from_json(&bin).unwrap();
+
+        // rewrite the schedule in the vector layout
+        let (vesting_timestamps, vesting_amounts) =
+            load_schedule(deps.as_ref().storage, DEFAULT_TRANCHE_ID).unwrap();
+        save_schedule(&mut deps.storage, DEFAULT_TRANCHE_ID, &[], &[]).unwrap();
+        SCHEDULE_CURSOR.remove(&mut deps.storage, DEFAULT_TRANCHE_ID);
+        LEGACY_TRANCHE_VESTING_TIMESTAMPS
+            .save(&mut deps.storage, DEFAULT_TRANCHE_ID, &vesting_timestamps)
+            .unwrap();
+        LEGACY_TRANCHE_VESTING_AMOUNTS
+            .save(&mut deps.storage, DEFAULT_TRANCHE_ID, &vesting_amounts)
+            .unwrap();
+
+        deps.querier = deps.querier.with_custom_handler(
+            |_: &SeiQueryWrapper| -> MockQuerierCustomHandlerResult {
+                let res = UnbondingDelegationsResponse { entries: vec![] };
+                MockQuerierCustomHandlerResult::Ok(ContractResult::Ok(
+                    to_json_binary(&res).unwrap(),
+                ))
+            },
+        );
+        migrate_200_handler(deps.as_mut(), env.clone()).unwrap();
+
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let after: ShowInfoResponse = from_json(&bin).unwrap();
+        assert_eq!(before, after);
+        assert_eq!(
+            None,
+            LEGACY_TRANCHE_VESTING_AMOUNTS
+                .may_load(deps.as_ref().storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_string_amounts() {
+        let msg: ExecuteMsg =
+            from_json(br#"{"delegate":{"validator":"val","amount":"100"}}"#).unwrap();
+        assert_eq!(
+            msg,
+            ExecuteMsg::Delegate {
+                validator: "val".to_string(),
+                amount: Uint128::new(100),
+            }
+        );
+        // plain numbers are still accepted on input
+        let bare: ExecuteMsg =
+            from_json(br#"{"delegate":{"validator":"val","amount":100}}"#).unwrap();
+        assert_eq!(msg, bare);
+        let msg: ExecuteMsg =
+            from_json(br#"{"delegate_many":{"delegations":[["val1","100"],["val2",200]]}}"#)
+                .unwrap();
+        assert_eq!(
+            msg,
+            ExecuteMsg::DelegateMany {
+                delegations: vec![
+                    ("val1".to_string(), Uint128::new(100)),
+                    ("val2".to_string(), Uint128::new(200)),
+                ],
+            }
+        );
+        let tranche: Tranche = from_json(
+            br#"{"denom":"usei","vesting_timestamps":["1","2"],"vesting_amounts":[100,"200"],
+                "unlocked_token_distribution_address":"addr",
+                "staking_reward_distribution_address":"addr"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            tranche.vesting_amounts,
+            vec![Uint128::new(100), Uint128::new(200)]
+        );
+        from_json::<ExecuteMsg>(br#"{"delegate":{"validator":"val","amount":"abc"}}"#).unwrap_err();
+        from_json::<ExecuteMsg>(br#"{"delegate":{"validator":"val","amount":-1}}"#).unwrap_err();
+
+        let mut deps = mock_dependencies();
+        let info = mock_info(OWNER, &[Coin::new(48000000, "usei".to_string())]);
+        setup_test_case(deps.as_mut(), info).unwrap();
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::Info {}).unwrap();
+        let res = String::from_utf8(bin.to_vec()).unwrap();
+        assert!(res.contains(r#""vesting_amounts":["12000000","#));
+        assert!(res.contains(r#""withdrawn_locked":"0""#));
+    }
 }
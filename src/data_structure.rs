@@ -1,9 +1,13 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Coin, Env, Timestamp};
+use cosmwasm_std::{Addr, Coin, Env, Timestamp, Uint128};
+use serde::{Deserialize, Deserializer};
 
-use crate::ContractError;
+use crate::{schedule::expand_schedule_spec, ContractError};
 
 const HUNDRED_YEARS_IN_SECONDS: u64 = 100 * 365 * 24 * 60 * 60;
+// generous enough for chains producing up to ten blocks per second
+const HUNDRED_YEARS_IN_BLOCKS: u64 = 10 * HUNDRED_YEARS_IN_SECONDS;
+const BASIS_POINTS: u64 = 10_000;
 
 #[cw_serde]
 pub struct EmptyStruct {}
@@ -12,76 +16,829 @@ pub struct EmptyStruct {}
 pub struct Tranche {
     pub denom: String,
     pub vesting_timestamps: Vec<Timestamp>,
-    pub vesting_amounts: Vec<u128>,
+    #[serde(deserialize_with = "deserialize_amounts")]
+    pub vesting_amounts: Vec<Uint128>,
     pub unlocked_token_distribution_address: Addr,
     pub staking_reward_distribution_address: Addr,
+    // if set, each vesting amount streams pro-rata from the previous vesting timestamp (or `start`
+    // for the first amount) to its own timestamp, instead of unlocking all at once
+    pub linear_vesting: Option<LinearVesting>,
+    // compact alternative to listing `vesting_timestamps` and `vesting_amounts` by hand. If set,
+    // both lists must be empty and will be filled in by expanding the spec.
+    pub schedule_spec: Option<VestingScheduleSpec>,
+    // if set, the tranche vests this CW20 token instead of a native denom, and `denom` must be the
+    // token's contract address. Such tranches are funded through the CW20 receive hook.
+    pub cw20_token: Option<Addr>,
+    // defaults to `ScheduleUnit::Time`
+    pub schedule_unit: Option<ScheduleUnit>,
+    // amounts that vest once admins approve the milestone, on top of the schedule above. A tranche
+    // with milestones may leave the schedule empty.
+    pub milestones: Option<Vec<Milestone>>,
 }
 
-impl Tranche {
-    pub fn validate(&self, env: Env, funds: Vec<Coin>) -> Result<(), ContractError> {
-        if self.vesting_amounts.len() != self.vesting_timestamps.len() {
-            return Err(ContractError::InvalidTranche(
-                "mismatched vesting amounts and schedule".to_string(),
-            ));
+// what the points of a vesting schedule (vesting timestamps, linear vesting start and cliff,
+// cutoffs) are measured in. Schedule messages and the vesting math carry block heights in
+// `Timestamp` as their nanosecond value, so that the math is the same for both units. Stored points
+// are `SchedulePoint`s instead.
+#[cw_serde]
+#[derive(Default)]
+pub enum ScheduleUnit {
+    #[default]
+    Time,
+    Height,
+}
+
+impl ScheduleUnit {
+    // the point of the schedule that the current block has reached
+    pub fn now(&self, env: &Env) -> Timestamp {
+        match self {
+            ScheduleUnit::Time => env.block.time,
+            ScheduleUnit::Height => Timestamp::from_nanos(env.block.height),
         }
-        if self.vesting_amounts.is_empty() {
-            return Err(ContractError::InvalidTranche("nothing to vest".to_string()));
+    }
+
+    // converts a duration in seconds or blocks into the nanosecond representation of the schedule.
+    // Durations too long to represent saturate.
+    pub fn span(&self, duration: u64) -> u64 {
+        match self {
+            ScheduleUnit::Time => duration.saturating_mul(1_000_000_000),
+            ScheduleUnit::Height => duration,
         }
-        let mut total_vesting_amount = 0u128;
-        for amount in self.vesting_amounts.iter() {
-            if *amount == 0 {
-                return Err(ContractError::InvalidTranche(
-                    "zero vesting amount is not allowed".to_string(),
+    }
+
+    // the point at a position of the vesting math
+    pub fn point(&self, position: Timestamp) -> SchedulePoint {
+        match self {
+            ScheduleUnit::Time => SchedulePoint::Time(position),
+            ScheduleUnit::Height => SchedulePoint::Height(position.nanos()),
+        }
+    }
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum SchedulePoint {
+    Time(Timestamp),
+    Height(u64),
+}
+
+impl SchedulePoint {
+    pub fn unit(&self) -> ScheduleUnit {
+        match self {
+            SchedulePoint::Time(_) => ScheduleUnit::Time,
+            SchedulePoint::Height(_) => ScheduleUnit::Height,
+        }
+    }
+
+    // the position of the point in the vesting math, see `ScheduleUnit`
+    pub fn position(&self) -> Timestamp {
+        match self {
+            SchedulePoint::Time(time) => *time,
+            SchedulePoint::Height(height) => Timestamp::from_nanos(*height),
+        }
+    }
+}
+
+// guardrails for vesting schedules, set at instantiation and applied to every later schedule
+// change. Durations are in the unit of the schedule, i.e. seconds or blocks.
+#[cw_serde]
+#[derive(Default)]
+pub struct ValidationPolicy {
+    // how far ahead of the current point a vesting entry may be. Defaults to a hundred years.
+    pub max_horizon: Option<u64>,
+    pub max_entries: Option<u32>,
+    // minimum distance between consecutive vesting entries
+    pub min_interval: Option<u64>,
+    // whether vesting entries and linear vesting starts may be in the past, for back-dated grants
+    pub allow_past_start: bool,
+}
+
+impl ValidationPolicy {
+    pub fn max_horizon(&self, unit: &ScheduleUnit) -> u64 {
+        self.max_horizon.unwrap_or(match unit {
+            ScheduleUnit::Time => HUNDRED_YEARS_IN_SECONDS,
+            ScheduleUnit::Height => HUNDRED_YEARS_IN_BLOCKS,
+        })
+    }
+
+    // checks the number of entries of a whole schedule and the spacing between them
+    pub fn validate_layout(
+        &self,
+        vesting_timestamps: &[Timestamp],
+        unit: &ScheduleUnit,
+    ) -> Result<(), ContractError> {
+        if let Some(max_entries) = self.max_entries {
+            if vesting_timestamps.len() > max_entries as usize {
+                return Err(ContractError::InvalidTranche(format!(
+                    "vesting schedule cannot have more than {} entries",
+                    max_entries
+                )));
+            }
+        }
+        if let Some(min_interval) = self.min_interval {
+            if vesting_timestamps.windows(2).any(|pair| {
+                pair[1].nanos().saturating_sub(pair[0].nanos()) < unit.span(min_interval)
+            }) {
+                return Err(ContractError::InvalidTranche(format!(
+                    "vesting entries must be at least {} apart",
+                    min_interval
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// the most ops may delegate to a single validator
+#[cw_serde]
+pub enum DelegationCap {
+    // share of the total amount of the tranches that vest `DENOM`
+    Percentage {
+        percentage: u8,
+    },
+    Amount {
+        #[serde(deserialize_with = "deserialize_amount")]
+        amount: Uint128,
+    },
+}
+
+impl DelegationCap {
+    pub fn validate(&self) -> Result<(), ContractError> {
+        match self {
+            DelegationCap::Percentage { percentage } if *percentage == 0 || *percentage > 100 => {
+                Err(ContractError::InvalidDelegation(
+                    "cap percentage must be between 1 and 100".to_string(),
+                ))
+            }
+            DelegationCap::Amount { amount } if amount.is_zero() => Err(
+                ContractError::InvalidDelegation("cap amount must be positive".to_string()),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn limit(&self, principal: u128) -> u128 {
+        match self {
+            DelegationCap::Percentage { percentage } => principal * *percentage as u128 / 100,
+            DelegationCap::Amount { amount } => amount.u128(),
+        }
+    }
+}
+
+// what happens to staking rewards when ops withdraw them. Compounded rewards are delegated to
+// `validator`, or in proportion to the existing delegations if it is not set. Whatever does not fit
+// under the delegation cap is sent instead.
+#[cw_serde]
+pub enum RewardPolicy {
+    // rewards are sent to the staking reward distribution address
+    Send {},
+    Compound {
+        validator: Option<String>,
+    },
+    // compounds `compound_percentage` of the rewards and sends the rest
+    Split {
+        compound_percentage: u8,
+        validator: Option<String>,
+    },
+}
+
+impl Default for RewardPolicy {
+    fn default() -> Self {
+        RewardPolicy::Send {}
+    }
+}
+
+impl RewardPolicy {
+    pub fn validate(&self) -> Result<(), ContractError> {
+        if let RewardPolicy::Split {
+            compound_percentage,
+            ..
+        } = self
+        {
+            if *compound_percentage > 100 {
+                return Err(ContractError::InvalidDelegation(
+                    "compound percentage cannot exceed 100".to_string(),
                 ));
             }
-            total_vesting_amount += *amount;
         }
-        let mut deposited_amount = 0u128;
-        for fund in funds.iter() {
-            if fund.denom == self.denom {
-                deposited_amount += fund.amount.u128();
+        Ok(())
+    }
+
+    pub fn compound_percentage(&self) -> u8 {
+        match self {
+            RewardPolicy::Send {} => 0,
+            RewardPolicy::Compound { .. } => 100,
+            RewardPolicy::Split {
+                compound_percentage,
+                ..
+            } => *compound_percentage,
+        }
+    }
+
+    pub fn validator(&self) -> Option<&String> {
+        match self {
+            RewardPolicy::Send {} => None,
+            RewardPolicy::Compound { validator } | RewardPolicy::Split { validator, .. } => {
+                validator.as_ref()
             }
         }
-        if total_vesting_amount > deposited_amount {
+    }
+}
+
+#[cw_serde]
+pub enum VestingPeriod {
+    // fixed period length in seconds
+    Time(u64),
+    // calendar months
+    Months(u32),
+}
+
+#[cw_serde]
+pub enum RemainderHandling {
+    AddToFirst,
+    AddToLast,
+}
+
+#[cw_serde]
+pub struct VestingScheduleSpec {
+    pub start: Timestamp,
+    // number of leading periods whose releases are held back and released together at the end of
+    // the last cliff period
+    pub cliff_periods: u32,
+    pub num_periods: u32,
+    pub period: VestingPeriod,
+    #[serde(deserialize_with = "deserialize_amount")]
+    pub total_amount: Uint128,
+    // where `total_amount % num_periods` goes
+    pub remainder_handling: RemainderHandling,
+}
+
+#[cw_serde]
+pub struct Milestone {
+    pub milestone_id: String,
+    #[serde(deserialize_with = "deserialize_amount")]
+    pub amount: Uint128,
+    // if set, the milestone lapses unless it is reached by then (in the tranche's schedule unit),
+    // and its amount can be clawed back
+    pub deadline: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct MilestoneState {
+    // what is left to withdraw once reached
+    pub amount: Uint128,
+    pub deadline: Option<SchedulePoint>,
+    pub reached: bool,
+}
+
+impl MilestoneState {
+    pub fn is_lapsed(&self, now: Timestamp) -> bool {
+        !self.reached && matches!(self.deadline, Some(deadline) if deadline.position() < now)
+    }
+}
+
+#[cw_serde]
+pub struct LinearVesting {
+    pub start: Timestamp,
+    // nothing can be withdrawn before the cliff. At the cliff, everything streamed since `start`
+    // becomes available at once.
+    pub cliff: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct VestingEntry {
+    pub point: SchedulePoint,
+    pub amount: u128,
+    // this entry plus every earlier entry written by the same `save_schedule`
+    pub cumulative: u128,
+}
+
+// tracks the remaining part of an indexed vesting schedule
+#[cw_serde]
+#[derive(Default)]
+pub struct ScheduleCursor {
+    // index of the first remaining entry
+    pub first: u64,
+    // one past the index of the last remaining entry
+    pub end: u64,
+    // how much of the first remaining entry has already been withdrawn
+    pub withdrawn_from_first: u128,
+}
+
+#[cw_serde]
+pub struct LinearVestingState {
+    pub cliff: Option<SchedulePoint>,
+    // start of the segment that streams the first remaining vesting amount
+    pub segment_start: SchedulePoint,
+    // the first remaining vesting amount before any partial withdrawal from it
+    pub segment_amount: Uint128,
+}
+
+impl LinearVestingState {
+    pub fn cliff_position(&self) -> Option<Timestamp> {
+        self.cliff.map(|cliff| cliff.position())
+    }
+
+    pub fn segment_start_position(&self) -> Timestamp {
+        self.segment_start.position()
+    }
+
+    // starts streaming `amount` from `start`, a position of the vesting math
+    pub fn restart_segment(&mut self, start: Timestamp, amount: Uint128) {
+        self.segment_start = self.segment_start.unit().point(start);
+        self.segment_amount = amount;
+    }
+}
+
+#[cw_serde]
+pub enum Clawback {
+    // revokes everything that would vest after the cutoff, which defaults to the time the proposal
+    // is executed
+    After {
+        cutoff: Option<Timestamp>,
+    },
+    // revokes the given amount of unvested tokens, starting from the end of the schedule
+    Amount {
+        #[serde(deserialize_with = "deserialize_amount")]
+        amount: Uint128,
+    },
+    // revokes every milestone whose deadline has passed without it being reached. The other
+    // variants leave milestones alone.
+    LapsedMilestones {},
+}
+
+#[cw_serde]
+pub enum WithdrawalKind {
+    // vested tokens paid out to the unlocked distribution address(es)
+    Unlocked,
+    // staking rewards paid out to the staking reward distribution address
+    Reward,
+    // unvested tokens paid out through an emergency withdrawal or a clawback
+    Locked,
+}
+
+impl WithdrawalKind {
+    // storage key of the per-kind withdrawal index
+    pub fn key(&self) -> &'static str {
+        match self {
+            WithdrawalKind::Unlocked => "unlocked",
+            WithdrawalKind::Reward => "reward",
+            WithdrawalKind::Locked => "locked",
+        }
+    }
+}
+
+#[cw_serde]
+pub struct WithdrawalRecord {
+    pub height: u64,
+    pub time: Timestamp,
+    pub kind: WithdrawalKind,
+    // `None` for staking rewards and for the native part of an emergency withdrawal, which are
+    // pooled across tranches
+    pub tranche_id: Option<String>,
+    // the CW20 token's contract address for CW20 tranches
+    pub denom: String,
+    pub amount: Uint128,
+    pub recipient: Addr,
+    pub sender: Addr,
+}
+
+// a payout of vested tokens that waits for stake to unbond
+#[cw_serde]
+pub struct PendingWithdrawal {
+    pub tranche_id: String,
+    pub recipient: Addr,
+    pub amount: Uint128,
+    // when the withdrawal was initiated
+    pub time: Timestamp,
+}
+
+// undelegated principal, which is liquid again once unbonding completes
+#[cw_serde]
+pub struct UnbondingPrincipal {
+    pub amount: Uint128,
+    pub completion_time: Timestamp,
+}
+
+// a recipient's share of a distribution is its weight divided by the sum of all weights
+#[cw_serde]
+pub struct WeightedRecipient {
+    pub address: Addr,
+    pub weight: u64,
+}
+
+#[cw_serde]
+pub struct ScheduleAmendment {
+    // everything that would vest after the cutoff is replaced by the entries below
+    pub cutoff: Timestamp,
+    pub vesting_timestamps: Vec<Timestamp>,
+    #[serde(deserialize_with = "deserialize_amounts")]
+    pub vesting_amounts: Vec<Uint128>,
+    // the amended entries may vest this much more than the ones they replace. The extra tokens
+    // must already be held by the contract outside of any tranche.
+    #[serde(deserialize_with = "deserialize_amount")]
+    pub added_amount: Uint128,
+    // the amended entries may vest this much less than the ones they replace. The difference is
+    // sent to `clawback_dst`.
+    #[serde(deserialize_with = "deserialize_amount")]
+    pub clawed_back_amount: Uint128,
+    pub clawback_dst: Option<Addr>,
+}
+
+impl Tranche {
+    pub fn with_expanded_schedule(mut self) -> Result<Self, ContractError> {
+        let spec = match &self.schedule_spec {
+            Some(spec) => spec,
+            None => return Ok(self),
+        };
+        if self.schedule_unit() != ScheduleUnit::Time {
+            return Err(ContractError::InvalidTranche(
+                "schedule spec is only supported for time based schedules".to_string(),
+            ));
+        }
+        if !self.vesting_timestamps.is_empty() || !self.vesting_amounts.is_empty() {
             return Err(ContractError::InvalidTranche(
-                "insufficient deposit for the vesting plan".to_string(),
+                "schedule spec cannot be combined with explicit vesting amounts and schedule"
+                    .to_string(),
             ));
         }
-        self.validate_timestamps(env)?;
+        let (vesting_timestamps, vesting_amounts) = expand_schedule_spec(spec)?;
+        self.vesting_timestamps = vesting_timestamps;
+        self.vesting_amounts = vesting_amounts;
+        Ok(self)
+    }
+
+    pub fn validate(
+        &self,
+        env: Env,
+        funds: Vec<Coin>,
+        policy: &ValidationPolicy,
+    ) -> Result<(), ContractError> {
+        let milestone_amount = validate_milestones(self.milestones(), &self.schedule_unit(), &env)?;
+        let scheduled_amount = if milestone_amount > 0
+            && self.vesting_timestamps.is_empty()
+            && self.vesting_amounts.is_empty()
+        {
+            0
+        } else {
+            validate_vesting_amounts(&self.vesting_timestamps, &self.vesting_amounts)?
+        };
+        let total_amount = scheduled_amount
+            .checked_add(milestone_amount)
+            .ok_or_else(|| ContractError::InvalidTranche("tranche amount overflows".to_string()))?;
+        validate_deposit(&funds, &self.denom, total_amount)?;
+        self.validate_timestamps(env.clone(), policy)?;
+        if let Some(linear_vesting) = &self.linear_vesting {
+            self.validate_linear_vesting(env, linear_vesting, policy)?;
+        }
 
         Ok(())
     }
 
-    pub fn validate_timestamps(&self, env: Env) -> Result<(), ContractError> {
-        let mut last_ts_nanos = Timestamp::from_seconds(0).nanos();
-        for ts in &self.vesting_timestamps {
-            let ts_nanos = ts.nanos();
-            if ts_nanos <= last_ts_nanos {
+    pub fn validate_linear_vesting(
+        &self,
+        env: Env,
+        linear_vesting: &LinearVesting,
+        policy: &ValidationPolicy,
+    ) -> Result<(), ContractError> {
+        if self.vesting_timestamps.is_empty() {
+            return Err(ContractError::InvalidTranche(
+                "linear vesting requires a vesting schedule".to_string(),
+            ));
+        }
+        if !policy.allow_past_start && linear_vesting.start < self.schedule_unit().now(&env) {
+            return Err(ContractError::InvalidTranche(
+                "linear vesting start must not be in the past".to_string(),
+            ));
+        }
+        if linear_vesting.start >= self.vesting_timestamps[0] {
+            return Err(ContractError::InvalidTranche(
+                "linear vesting start must precede the first vesting timestamp".to_string(),
+            ));
+        }
+        if let Some(cliff) = linear_vesting.cliff {
+            if cliff < linear_vesting.start || cliff > *self.vesting_timestamps.last().unwrap() {
                 return Err(ContractError::InvalidTranche(
-                    "vesting schedule must be monotonic increasing".to_string(),
+                    "linear vesting cliff must be within the vesting schedule".to_string(),
                 ));
             }
+        }
 
-            // Check if the nanoseconds are at least current
-            if ts_nanos < env.block.time.nanos() {
-                return Err(ContractError::InvalidTranche(
-                    "Timestamp nanoseconds are out of range".to_string(),
-                ));
-            }
+        Ok(())
+    }
 
-            // ts should not be too far in the future (e.g. example not more than 100 years)
-            if ts.seconds() > env.block.time.seconds() + HUNDRED_YEARS_IN_SECONDS {
-                return Err(ContractError::InvalidTranche(
-                    "Timestamp is too far in the future".to_string(),
-                ));
-            }
-            last_ts_nanos = ts_nanos
+    pub fn validate_timestamps(
+        &self,
+        env: Env,
+        policy: &ValidationPolicy,
+    ) -> Result<(), ContractError> {
+        validate_vesting_schedule(&self.vesting_timestamps, &self.schedule_unit(), env, policy)
+    }
+
+    pub fn schedule_unit(&self) -> ScheduleUnit {
+        self.schedule_unit.clone().unwrap_or_default()
+    }
+
+    pub fn milestones(&self) -> &[Milestone] {
+        self.milestones.as_deref().unwrap_or_default()
+    }
+}
+
+impl ScheduleAmendment {
+    // `unit` is the schedule unit of the amended tranche
+    pub fn validate(
+        &self,
+        env: Env,
+        unit: &ScheduleUnit,
+        policy: &ValidationPolicy,
+    ) -> Result<(), ContractError> {
+        validate_vesting_amounts(&self.vesting_timestamps, &self.vesting_amounts)?;
+        validate_vesting_schedule(&self.vesting_timestamps, unit, env.clone(), policy)?;
+        if self.cutoff < unit.now(&env) {
+            return Err(ContractError::InvalidAmendment(
+                "cutoff cannot be in the past".to_string(),
+            ));
+        }
+        if self.vesting_timestamps[0] <= self.cutoff {
+            return Err(ContractError::InvalidAmendment(
+                "amended schedule must start after the cutoff".to_string(),
+            ));
+        }
+        if !self.added_amount.is_zero() && !self.clawed_back_amount.is_zero() {
+            return Err(ContractError::InvalidAmendment(
+                "cannot add and claw back funds in the same amendment".to_string(),
+            ));
+        }
+        if self.clawed_back_amount.is_zero() == self.clawback_dst.is_some() {
+            return Err(ContractError::InvalidAmendment(
+                "clawback destination must be set if and only if funds are clawed back".to_string(),
+            ));
         }
 
         Ok(())
     }
 }
 
+pub fn validate_weighted_recipients(recipients: &[WeightedRecipient]) -> Result<(), ContractError> {
+    if recipients.is_empty() {
+        return Err(ContractError::InvalidDistribution(
+            "there must be at least one recipient".to_string(),
+        ));
+    }
+    for (i, recipient) in recipients.iter().enumerate() {
+        if recipient.weight == 0 {
+            return Err(ContractError::InvalidDistribution(
+                "zero weight is not allowed".to_string(),
+            ));
+        }
+        if recipients[..i]
+            .iter()
+            .any(|other| other.address == recipient.address)
+        {
+            return Err(ContractError::InvalidDistribution(format!(
+                "duplicate recipient {}",
+                recipient.address
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// staking reward splits are weighted in basis points
+pub fn validate_reward_split(recipients: &[WeightedRecipient]) -> Result<(), ContractError> {
+    validate_weighted_recipients(recipients)?;
+    // summed as u128 so that hostile weights cannot overflow
+    if recipients.iter().map(|r| r.weight as u128).sum::<u128>() != BASIS_POINTS as u128 {
+        return Err(ContractError::InvalidDistribution(format!(
+            "weights must add up to {} basis points",
+            BASIS_POINTS
+        )));
+    }
+    Ok(())
+}
+
+// returns the total amount of the milestones
+pub fn validate_milestones(
+    milestones: &[Milestone],
+    unit: &ScheduleUnit,
+    env: &Env,
+) -> Result<u128, ContractError> {
+    let mut total_amount = 0u128;
+    for (i, milestone) in milestones.iter().enumerate() {
+        if milestone.milestone_id.is_empty() {
+            return Err(ContractError::InvalidTranche(
+                "milestone ID cannot be empty".to_string(),
+            ));
+        }
+        if milestones[..i]
+            .iter()
+            .any(|other| other.milestone_id == milestone.milestone_id)
+        {
+            return Err(ContractError::InvalidTranche(format!(
+                "duplicate milestone {}",
+                milestone.milestone_id
+            )));
+        }
+        if milestone.amount.is_zero() {
+            return Err(ContractError::InvalidTranche(
+                "zero milestone amount is not allowed".to_string(),
+            ));
+        }
+        if matches!(milestone.deadline, Some(deadline) if deadline < unit.now(env)) {
+            return Err(ContractError::InvalidTranche(
+                "milestone deadline cannot be in the past".to_string(),
+            ));
+        }
+        total_amount = total_amount
+            .checked_add(milestone.amount.u128())
+            .ok_or_else(|| {
+                ContractError::InvalidTranche("milestone amounts overflow".to_string())
+            })?;
+    }
+
+    Ok(total_amount)
+}
+
+// checks that the schedule is non-empty and has a non-zero amount for every timestamp. Returns the
+// total amount to vest.
+pub fn validate_vesting_amounts(
+    vesting_timestamps: &[Timestamp],
+    vesting_amounts: &[Uint128],
+) -> Result<u128, ContractError> {
+    if vesting_amounts.len() != vesting_timestamps.len() {
+        return Err(ContractError::InvalidTranche(
+            "mismatched vesting amounts and schedule".to_string(),
+        ));
+    }
+    if vesting_amounts.is_empty() {
+        return Err(ContractError::InvalidTranche("nothing to vest".to_string()));
+    }
+    let mut total_vesting_amount = 0u128;
+    for amount in vesting_amounts.iter() {
+        if amount.is_zero() {
+            return Err(ContractError::InvalidTranche(
+                "zero vesting amount is not allowed".to_string(),
+            ));
+        }
+        total_vesting_amount = total_vesting_amount
+            .checked_add(amount.u128())
+            .ok_or_else(|| ContractError::InvalidTranche("vesting amounts overflow".to_string()))?;
+    }
+
+    Ok(total_vesting_amount)
+}
+
+// amounts were plain numbers in messages before they became `Uint128` strings. Inputs accept both,
+// though the JSON parser only reads plain numbers up to `u64::MAX`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AmountInput {
+    String(Uint128),
+    Number(u64),
+}
+
+impl From<AmountInput> for Uint128 {
+    fn from(amount: AmountInput) -> Self {
+        match amount {
+            AmountInput::String(amount) => amount,
+            AmountInput::Number(amount) => Uint128::from(amount),
+        }
+    }
+}
+
+pub fn deserialize_amount<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uint128, D::Error> {
+    Ok(AmountInput::deserialize(deserializer)?.into())
+}
+
+pub fn deserialize_amounts<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Uint128>, D::Error> {
+    Ok(Vec::<AmountInput>::deserialize(deserializer)?
+        .into_iter()
+        .map(Uint128::from)
+        .collect())
+}
+
+pub fn deserialize_validator_amounts<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(String, Uint128)>, D::Error> {
+    Ok(Vec::<(String, AmountInput)>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(validator, amount)| (validator, amount.into()))
+        .collect())
+}
+
+// the vesting math works on plain integers
+pub fn raw_amounts(amounts: &[Uint128]) -> Vec<u128> {
+    amounts.iter().map(|amount| amount.u128()).collect()
+}
+
+pub fn validate_deposit(
+    funds: &[Coin],
+    denom: &str,
+    total_vesting_amount: u128,
+) -> Result<(), ContractError> {
+    let mut deposited_amount = 0u128;
+    for fund in funds.iter() {
+        if fund.denom == denom {
+            deposited_amount += fund.amount.u128();
+        }
+    }
+    if total_vesting_amount > deposited_amount {
+        return Err(ContractError::InvalidTranche(
+            "insufficient deposit for the vesting plan".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn validate_vesting_schedule(
+    vesting_timestamps: &[Timestamp],
+    unit: &ScheduleUnit,
+    env: Env,
+    policy: &ValidationPolicy,
+) -> Result<(), ContractError> {
+    policy.validate_layout(vesting_timestamps, unit)?;
+    match unit {
+        ScheduleUnit::Time => validate_vesting_timestamps(vesting_timestamps, env, policy),
+        ScheduleUnit::Height => validate_vesting_heights(vesting_timestamps, env, policy),
+    }
+}
+
+pub fn validate_vesting_timestamps(
+    vesting_timestamps: &[Timestamp],
+    env: Env,
+    policy: &ValidationPolicy,
+) -> Result<(), ContractError> {
+    let mut last_ts_nanos = Timestamp::from_seconds(0).nanos();
+    for ts in vesting_timestamps {
+        let ts_nanos = ts.nanos();
+        if ts_nanos <= last_ts_nanos {
+            return Err(ContractError::InvalidTranche(
+                "vesting schedule must be monotonic increasing".to_string(),
+            ));
+        }
+
+        // Check if the nanoseconds are at least current
+        if !policy.allow_past_start && ts_nanos < env.block.time.nanos() {
+            return Err(ContractError::InvalidTranche(
+                "Timestamp nanoseconds are out of range".to_string(),
+            ));
+        }
+
+        // ts should not be too far in the future (by default not more than 100 years)
+        let horizon = env
+            .block
+            .time
+            .seconds()
+            .saturating_add(policy.max_horizon(&ScheduleUnit::Time));
+        if ts.seconds() > horizon {
+            return Err(ContractError::InvalidTranche(
+                "Timestamp is too far in the future".to_string(),
+            ));
+        }
+        last_ts_nanos = ts_nanos
+    }
+
+    Ok(())
+}
+
+// block heights are expected as `Timestamp::from_nanos(height)`, see `ScheduleUnit`
+pub fn validate_vesting_heights(
+    vesting_heights: &[Timestamp],
+    env: Env,
+    policy: &ValidationPolicy,
+) -> Result<(), ContractError> {
+    let mut last_height = 0u64;
+    for height in vesting_heights.iter().map(|height| height.nanos()) {
+        if height <= last_height {
+            return Err(ContractError::InvalidTranche(
+                "vesting schedule must be monotonic increasing".to_string(),
+            ));
+        }
+        if !policy.allow_past_start && height < env.block.height {
+            return Err(ContractError::InvalidTranche(
+                "Block height is out of range".to_string(),
+            ));
+        }
+        let horizon = env
+            .block
+            .height
+            .saturating_add(policy.max_horizon(&ScheduleUnit::Height));
+        if height > horizon {
+            return Err(ContractError::InvalidTranche(
+                "Block height is too far in the future".to_string(),
+            ));
+        }
+        last_height = height
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -89,44 +846,55 @@ mod tests {
 
     const UNLOCK_ADDR1: &str = "unlock0001";
 
+    fn test_tranche(vesting_amounts: Vec<Uint128>, vesting_timestamps: Vec<Timestamp>) -> Tranche {
+        Tranche {
+            vesting_amounts,
+            unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
+            vesting_timestamps,
+            denom: "token".to_string(),
+            staking_reward_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
+            linear_vesting: None,
+            schedule_spec: None,
+            cw20_token: None,
+            schedule_unit: None,
+            milestones: None,
+        }
+    }
+
     #[test]
     fn test_validate_success() {
         let env = mock_env();
-        let tranche = Tranche {
-            vesting_amounts: vec![100, 200, 300],
-            unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-            vesting_timestamps: vec![
+        let tranche = test_tranche(
+            vec![Uint128::new(100), Uint128::new(200), Uint128::new(300)],
+            vec![
                 Timestamp::from_seconds(1).plus_nanos(env.block.time.nanos()),
                 Timestamp::from_seconds(2).plus_nanos(env.block.time.nanos()),
                 Timestamp::from_seconds(3).plus_nanos(env.block.time.nanos()),
             ],
-            denom: "token".to_string(),
-            staking_reward_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-        };
+        );
         let funds = vec![Coin {
             denom: "token".to_string(),
             amount: Uint128::from(600u128),
         }];
-        assert!(tranche.validate(env, funds).is_ok());
+        assert!(tranche
+            .validate(env, funds, &ValidationPolicy::default())
+            .is_ok());
     }
 
     #[test]
     fn test_validate_mismatched_amount_timestamp_lengths() {
         let env = mock_env();
-        let tranche = Tranche {
-            vesting_amounts: vec![100, 200],
-            unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-            vesting_timestamps: vec![
+        let tranche = test_tranche(
+            vec![Uint128::new(100), Uint128::new(200)],
+            vec![
                 Timestamp::from_seconds(1).plus_nanos(env.block.time.nanos()),
                 Timestamp::from_seconds(2).plus_nanos(env.block.time.nanos()),
                 Timestamp::from_seconds(3).plus_nanos(env.block.time.nanos()),
             ],
-            denom: "token".to_string(),
-            staking_reward_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-        };
+        );
         let funds = vec![];
         assert!(matches!(
-            tranche.validate(env, funds),
+            tranche.validate(env, funds, &ValidationPolicy::default()),
             Err(ContractError::InvalidTranche(msg)) if msg.contains("mismatched vesting amounts and schedule")
         ));
     }
@@ -134,16 +902,10 @@ mod tests {
     #[test]
     fn test_validate_empty_amounts_and_timestamps() {
         let env = mock_env();
-        let tranche = Tranche {
-            vesting_amounts: vec![],
-            unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-            vesting_timestamps: vec![],
-            denom: "token".to_string(),
-            staking_reward_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-        };
+        let tranche = test_tranche(vec![], vec![]);
         let funds = vec![];
         assert!(matches!(
-            tranche.validate(env, funds),
+            tranche.validate(env, funds, &ValidationPolicy::default()),
             Err(ContractError::InvalidTranche(msg)) if msg.contains("nothing to vest")
         ));
     }
@@ -151,21 +913,18 @@ mod tests {
     #[test]
     fn test_validate_zero_vesting_amount() {
         let env = mock_env();
-        let tranche = Tranche {
-            vesting_amounts: vec![0, 100],
-            unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-            vesting_timestamps: vec![
+        let tranche = test_tranche(
+            vec![Uint128::new(0), Uint128::new(100)],
+            vec![
                 Timestamp::from_seconds(1).plus_nanos(env.block.time.nanos()),
                 Timestamp::from_seconds(2).plus_nanos(env.block.time.nanos()),
             ],
-            denom: "token".to_string(),
-            staking_reward_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-        };
+        );
         let funds = vec![Coin {
             denom: "token".to_string(),
             amount: Uint128::new(100),
         }];
-        let result = tranche.validate(env, funds);
+        let result = tranche.validate(env, funds, &ValidationPolicy::default());
         assert!(matches!(
             result,
             Err(ContractError::InvalidTranche(msg)) if msg.contains("zero vesting amount is not allowed")
@@ -175,21 +934,18 @@ mod tests {
     #[test]
     fn test_validate_insufficient_deposit() {
         let env = mock_env();
-        let tranche = Tranche {
-            vesting_amounts: vec![200, 200],
-            unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-            vesting_timestamps: vec![
+        let tranche = test_tranche(
+            vec![Uint128::new(200), Uint128::new(200)],
+            vec![
                 Timestamp::from_seconds(1).plus_nanos(env.block.time.nanos()),
                 Timestamp::from_seconds(2).plus_nanos(env.block.time.nanos()),
             ],
-            denom: "token".to_string(),
-            staking_reward_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-        };
+        );
         let funds = vec![Coin {
             denom: "token".to_string(),
             amount: Uint128::new(300),
         }];
-        let result = tranche.validate(env, funds);
+        let result = tranche.validate(env, funds, &ValidationPolicy::default());
         assert!(matches!(
             result,
             Err(ContractError::InvalidTranche(msg)) if msg.contains("insufficient deposit for the vesting plan")
@@ -199,21 +955,18 @@ mod tests {
     #[test]
     fn test_validate_non_monotonic_vesting_timestamps() {
         let env = mock_env();
-        let tranche = Tranche {
-            vesting_amounts: vec![100, 100],
-            unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-            vesting_timestamps: vec![
+        let tranche = test_tranche(
+            vec![Uint128::new(100), Uint128::new(100)],
+            vec![
                 Timestamp::from_seconds(2).plus_nanos(env.block.time.nanos()),
                 Timestamp::from_seconds(1).plus_nanos(env.block.time.nanos()),
             ],
-            denom: "token".to_string(),
-            staking_reward_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-        };
+        );
         let funds = vec![Coin {
             denom: "token".to_string(),
             amount: Uint128::new(200),
         }];
-        let result = tranche.validate(env, funds);
+        let result = tranche.validate(env, funds, &ValidationPolicy::default());
         assert!(matches!(
             result,
             Err(ContractError::InvalidTranche(msg)) if msg.contains("vesting schedule must be monotonic increasing")
@@ -223,20 +976,17 @@ mod tests {
     #[test]
     fn test_validate_timestamps_too_early() {
         let env = mock_env();
-        let tranche = Tranche {
-            vesting_amounts: vec![100],
-            unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-            vesting_timestamps: vec![Timestamp::from_seconds(2)
+        let tranche = test_tranche(
+            vec![Uint128::new(100)],
+            vec![Timestamp::from_seconds(2)
                 .plus_nanos(env.block.time.nanos())
                 .minus_seconds(3)],
-            denom: "token".to_string(),
-            staking_reward_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-        };
+        );
         let funds = vec![Coin {
             denom: "token".to_string(),
             amount: Uint128::new(200),
         }];
-        let result = tranche.validate(env, funds);
+        let result = tranche.validate(env, funds, &ValidationPolicy::default());
         assert!(matches!(
             result,
             Err(ContractError::InvalidTranche(msg)) if msg.contains("Timestamp nanoseconds are out of range")
@@ -246,22 +996,424 @@ mod tests {
     #[test]
     fn test_validate_timestamps_too_late() {
         let env = mock_env();
-        let tranche = Tranche {
-            vesting_amounts: vec![100],
-            unlocked_token_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
-            vesting_timestamps: vec![Timestamp::from_seconds(HUNDRED_YEARS_IN_SECONDS + 1)
+        let tranche = test_tranche(
+            vec![Uint128::new(100)],
+            vec![Timestamp::from_seconds(HUNDRED_YEARS_IN_SECONDS + 1)
                 .plus_nanos(env.block.time.nanos())],
+        );
+        let funds = vec![Coin {
             denom: "token".to_string(),
-            staking_reward_distribution_address: Addr::unchecked(UNLOCK_ADDR1),
+            amount: Uint128::new(200),
+        }];
+        let result = tranche.validate(env, funds, &ValidationPolicy::default());
+        assert!(matches!(
+            result,
+            Err(ContractError::InvalidTranche(msg)) if msg.contains("Timestamp is too far in the future")
+        ));
+    }
+
+    #[test]
+    fn test_validate_heights() {
+        let env = mock_env();
+        let mut tranche = Tranche {
+            schedule_unit: Some(ScheduleUnit::Height),
+            ..test_tranche(
+                vec![Uint128::new(100), Uint128::new(100)],
+                vec![
+                    Timestamp::from_nanos(env.block.height),
+                    Timestamp::from_nanos(env.block.height + HUNDRED_YEARS_IN_BLOCKS),
+                ],
+            )
+        };
+        let funds = vec![Coin {
+            denom: "token".to_string(),
+            amount: Uint128::new(200),
+        }];
+        tranche
+            .validate(env.clone(), funds.clone(), &ValidationPolicy::default())
+            .unwrap();
+
+        tranche.vesting_timestamps[0] = Timestamp::from_nanos(env.block.height - 1);
+        assert_eq!(
+            ContractError::InvalidTranche("Block height is out of range".to_string()),
+            tranche
+                .validate(env.clone(), funds.clone(), &ValidationPolicy::default())
+                .unwrap_err()
+        );
+
+        tranche.vesting_timestamps = vec![
+            Timestamp::from_nanos(env.block.height + 1),
+            Timestamp::from_nanos(env.block.height + HUNDRED_YEARS_IN_BLOCKS + 1),
+        ];
+        assert_eq!(
+            ContractError::InvalidTranche("Block height is too far in the future".to_string()),
+            tranche
+                .validate(env.clone(), funds.clone(), &ValidationPolicy::default())
+                .unwrap_err()
+        );
+
+        tranche.vesting_timestamps = vec![
+            Timestamp::from_nanos(env.block.height + 2),
+            Timestamp::from_nanos(env.block.height + 1),
+        ];
+        assert_eq!(
+            ContractError::InvalidTranche(
+                "vesting schedule must be monotonic increasing".to_string()
+            ),
+            tranche
+                .validate(env, funds, &ValidationPolicy::default())
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_with_policy() {
+        let env = mock_env();
+        let mut tranche = test_tranche(
+            vec![Uint128::new(100), Uint128::new(200), Uint128::new(300)],
+            vec![
+                env.block.time.plus_seconds(10),
+                env.block.time.plus_seconds(20),
+                env.block.time.plus_seconds(30),
+            ],
+        );
+        let funds = vec![Coin::new(600, "token")];
+        let policy = ValidationPolicy {
+            max_horizon: Some(30),
+            max_entries: Some(3),
+            min_interval: Some(10),
+            allow_past_start: false,
+        };
+        tranche
+            .validate(env.clone(), funds.clone(), &policy)
+            .unwrap();
+
+        let short_horizon = ValidationPolicy {
+            max_horizon: Some(29),
+            ..policy.clone()
+        };
+        assert_eq!(
+            ContractError::InvalidTranche("Timestamp is too far in the future".to_string()),
+            tranche
+                .validate(env.clone(), funds.clone(), &short_horizon)
+                .unwrap_err()
+        );
+        let fewer_entries = ValidationPolicy {
+            max_entries: Some(2),
+            ..policy.clone()
+        };
+        assert_eq!(
+            ContractError::InvalidTranche(
+                "vesting schedule cannot have more than 2 entries".to_string()
+            ),
+            tranche
+                .validate(env.clone(), funds.clone(), &fewer_entries)
+                .unwrap_err()
+        );
+        // an unbounded horizon must not overflow past the current time
+        let unbounded_horizon = ValidationPolicy {
+            max_horizon: Some(u64::MAX),
+            ..policy.clone()
+        };
+        tranche
+            .validate(env.clone(), funds.clone(), &unbounded_horizon)
+            .unwrap();
+        let unbounded_interval = ValidationPolicy {
+            min_interval: Some(u64::MAX),
+            ..policy.clone()
+        };
+        assert_eq!(
+            ContractError::InvalidTranche(format!(
+                "vesting entries must be at least {} apart",
+                u64::MAX
+            )),
+            tranche
+                .validate(env.clone(), funds.clone(), &unbounded_interval)
+                .unwrap_err()
+        );
+        let wider_interval = ValidationPolicy {
+            min_interval: Some(11),
+            ..policy.clone()
+        };
+        assert_eq!(
+            ContractError::InvalidTranche("vesting entries must be at least 11 apart".to_string()),
+            tranche
+                .validate(env.clone(), funds.clone(), &wider_interval)
+                .unwrap_err()
+        );
+
+        // back-dated grants
+        tranche.vesting_timestamps[0] = env.block.time.minus_seconds(10);
+        tranche.vesting_timestamps[1] = env.block.time.plus_seconds(10);
+        tranche.linear_vesting = Some(LinearVesting {
+            start: env.block.time.minus_seconds(20),
+            cliff: None,
+        });
+        assert_eq!(
+            ContractError::InvalidTranche("Timestamp nanoseconds are out of range".to_string()),
+            tranche
+                .validate(env.clone(), funds.clone(), &policy)
+                .unwrap_err()
+        );
+        let back_dated = ValidationPolicy {
+            allow_past_start: true,
+            ..policy
+        };
+        tranche.validate(env, funds, &back_dated).unwrap();
+    }
+
+    #[test]
+    fn test_validate_milestones() {
+        let env = mock_env();
+        let mut tranche = Tranche {
+            milestones: Some(vec![
+                Milestone {
+                    milestone_id: "a".to_string(),
+                    amount: Uint128::new(100),
+                    deadline: None,
+                },
+                Milestone {
+                    milestone_id: "b".to_string(),
+                    amount: Uint128::new(100),
+                    deadline: Some(env.block.time),
+                },
+            ]),
+            ..test_tranche(vec![], vec![])
         };
         let funds = vec![Coin {
             denom: "token".to_string(),
             amount: Uint128::new(200),
         }];
-        let result = tranche.validate(env, funds);
+        tranche
+            .validate(env.clone(), funds.clone(), &ValidationPolicy::default())
+            .unwrap();
+        assert_eq!(
+            ContractError::InvalidTranche("insufficient deposit for the vesting plan".to_string()),
+            tranche
+                .validate(
+                    env.clone(),
+                    vec![Coin::new(199, "token")],
+                    &ValidationPolicy::default()
+                )
+                .unwrap_err()
+        );
+
+        let mut milestones = tranche.milestones.clone().unwrap();
+        milestones[1].deadline = Some(env.block.time.minus_seconds(1));
+        tranche.milestones = Some(milestones.clone());
+        assert_eq!(
+            ContractError::InvalidTranche("milestone deadline cannot be in the past".to_string()),
+            tranche
+                .validate(env.clone(), funds.clone(), &ValidationPolicy::default())
+                .unwrap_err()
+        );
+
+        milestones[1].deadline = None;
+        milestones[1].milestone_id = "a".to_string();
+        tranche.milestones = Some(milestones.clone());
+        assert_eq!(
+            ContractError::InvalidTranche("duplicate milestone a".to_string()),
+            tranche
+                .validate(env.clone(), funds.clone(), &ValidationPolicy::default())
+                .unwrap_err()
+        );
+
+        milestones[1].milestone_id = "b".to_string();
+        milestones[0].amount = Uint128::MAX;
+        tranche.milestones = Some(milestones.clone());
+        assert_eq!(
+            ContractError::InvalidTranche("milestone amounts overflow".to_string()),
+            tranche
+                .validate(env.clone(), funds.clone(), &ValidationPolicy::default())
+                .unwrap_err()
+        );
+
+        milestones[0].amount = Uint128::new(100);
+        milestones[1].amount = Uint128::new(0);
+        tranche.milestones = Some(milestones);
+        assert_eq!(
+            ContractError::InvalidTranche("zero milestone amount is not allowed".to_string()),
+            tranche
+                .validate(env.clone(), funds.clone(), &ValidationPolicy::default())
+                .unwrap_err()
+        );
+
+        tranche.milestones = None;
+        assert_eq!(
+            ContractError::InvalidTranche("nothing to vest".to_string()),
+            tranche
+                .validate(env, funds, &ValidationPolicy::default())
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_linear_vesting_success() {
+        let env = mock_env();
+        let tranche = Tranche {
+            linear_vesting: Some(LinearVesting {
+                start: env.block.time,
+                cliff: Some(env.block.time.plus_seconds(5)),
+            }),
+            ..test_tranche(
+                vec![Uint128::new(100), Uint128::new(200)],
+                vec![
+                    env.block.time.plus_seconds(10),
+                    env.block.time.plus_seconds(20),
+                ],
+            )
+        };
+        let funds = vec![Coin {
+            denom: "token".to_string(),
+            amount: Uint128::new(300),
+        }];
+        assert!(tranche
+            .validate(env, funds, &ValidationPolicy::default())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_validate_linear_vesting_start_after_first_timestamp() {
+        let env = mock_env();
+        let tranche = Tranche {
+            linear_vesting: Some(LinearVesting {
+                start: env.block.time.plus_seconds(10),
+                cliff: None,
+            }),
+            ..test_tranche(
+                vec![Uint128::new(100)],
+                vec![env.block.time.plus_seconds(10)],
+            )
+        };
+        let funds = vec![Coin {
+            denom: "token".to_string(),
+            amount: Uint128::new(100),
+        }];
+        let result = tranche.validate(env, funds, &ValidationPolicy::default());
         assert!(matches!(
             result,
-            Err(ContractError::InvalidTranche(msg)) if msg.contains("Timestamp is too far in the future")
+            Err(ContractError::InvalidTranche(msg)) if msg.contains("linear vesting start must precede the first vesting timestamp")
         ));
     }
+
+    #[test]
+    fn test_validate_linear_vesting_cliff_out_of_range() {
+        let env = mock_env();
+        let tranche = Tranche {
+            linear_vesting: Some(LinearVesting {
+                start: env.block.time,
+                cliff: Some(env.block.time.plus_seconds(11)),
+            }),
+            ..test_tranche(
+                vec![Uint128::new(100)],
+                vec![env.block.time.plus_seconds(10)],
+            )
+        };
+        let funds = vec![Coin {
+            denom: "token".to_string(),
+            amount: Uint128::new(100),
+        }];
+        let result = tranche.validate(env, funds, &ValidationPolicy::default());
+        assert!(matches!(
+            result,
+            Err(ContractError::InvalidTranche(msg)) if msg.contains("linear vesting cliff must be within the vesting schedule")
+        ));
+    }
+
+    #[test]
+    fn test_validate_expanded_schedule_spec() {
+        let env = mock_env();
+        let tranche = Tranche {
+            schedule_spec: Some(VestingScheduleSpec {
+                start: env.block.time,
+                cliff_periods: 2,
+                num_periods: 4,
+                period: VestingPeriod::Months(1),
+                total_amount: Uint128::new(400),
+                remainder_handling: RemainderHandling::AddToLast,
+            }),
+            ..test_tranche(vec![], vec![])
+        }
+        .with_expanded_schedule()
+        .unwrap();
+        assert_eq!(
+            vec![Uint128::new(200), Uint128::new(100), Uint128::new(100)],
+            tranche.vesting_amounts
+        );
+        assert_eq!(3, tranche.vesting_timestamps.len());
+        let funds = vec![Coin {
+            denom: "token".to_string(),
+            amount: Uint128::new(400),
+        }];
+        assert!(tranche
+            .validate(env, funds, &ValidationPolicy::default())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_expand_schedule_spec_with_explicit_schedule() {
+        let env = mock_env();
+        let result = Tranche {
+            schedule_spec: Some(VestingScheduleSpec {
+                start: env.block.time,
+                cliff_periods: 0,
+                num_periods: 1,
+                period: VestingPeriod::Time(1),
+                total_amount: Uint128::new(100),
+                remainder_handling: RemainderHandling::AddToLast,
+            }),
+            ..test_tranche(
+                vec![Uint128::new(100)],
+                vec![env.block.time.plus_seconds(1)],
+            )
+        }
+        .with_expanded_schedule();
+        assert!(matches!(
+            result,
+            Err(ContractError::InvalidTranche(msg)) if msg.contains("schedule spec cannot be combined")
+        ));
+    }
+
+    #[test]
+    fn test_validate_weighted_recipients() {
+        let mut recipients = vec![
+            WeightedRecipient {
+                address: Addr::unchecked(UNLOCK_ADDR1),
+                weight: 3,
+            },
+            WeightedRecipient {
+                address: Addr::unchecked("unlock0002"),
+                weight: 0,
+            },
+        ];
+        assert_eq!(
+            ContractError::InvalidDistribution("zero weight is not allowed".to_string()),
+            validate_weighted_recipients(&recipients).unwrap_err()
+        );
+        recipients[1].address = Addr::unchecked(UNLOCK_ADDR1);
+        recipients[1].weight = 1;
+        assert_eq!(
+            ContractError::InvalidDistribution(format!("duplicate recipient {}", UNLOCK_ADDR1)),
+            validate_weighted_recipients(&recipients).unwrap_err()
+        );
+        recipients[1].address = Addr::unchecked("unlock0002");
+        assert!(validate_weighted_recipients(&recipients).is_ok());
+
+        assert_eq!(
+            ContractError::InvalidDistribution(
+                "weights must add up to 10000 basis points".to_string()
+            ),
+            validate_reward_split(&recipients).unwrap_err()
+        );
+        recipients[0].weight = u64::MAX;
+        assert_eq!(
+            ContractError::InvalidDistribution(
+                "weights must add up to 10000 basis points".to_string()
+            ),
+            validate_reward_split(&recipients).unwrap_err()
+        );
+        recipients[0].weight = 9000;
+        recipients[1].weight = 1000;
+        assert!(validate_reward_split(&recipients).is_ok());
+    }
 }
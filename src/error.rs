@@ -61,6 +61,43 @@ pub enum ContractError {
 
     #[error("No sufficient vested amount")]
     NoSufficientUnlockedTokens {},
+
+    #[error("Tranche {0} not found")]
+    TrancheNotFound(String),
+
+    #[error("Invalid clawback: {0}")]
+    InvalidClawback(String),
+
+    #[error("Invalid schedule amendment: {0}")]
+    InvalidAmendment(String),
+
+    #[error("Invalid distribution: {0}")]
+    InvalidDistribution(String),
+
+    #[error("Permissionless release of vested tokens is not enabled")]
+    PermissionlessReleaseDisabled {},
+
+    #[error("Invalid milestone: {0}")]
+    InvalidMilestone(String),
+
+    #[error("Insufficient liquid balance to pay out vested tokens, short by {shortfall}")]
+    InsufficientLiquidBalance { shortfall: u128 },
+
+    #[error("Invalid total amount correction: {0}")]
+    InvalidCorrection(String),
+
+    #[error("Invalid delegation: {0}")]
+    InvalidDelegation(String),
+
+    #[error("Validator {0} is not on the allowlist")]
+    ValidatorNotAllowed(String),
+
+    #[error("Delegation to {validator} would reach {delegated}, above the cap of {cap}")]
+    DelegationCapExceeded {
+        validator: String,
+        delegated: u128,
+        cap: u128,
+    },
 }
 
 impl From<semver::Error> for ContractError {
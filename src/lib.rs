@@ -3,6 +3,7 @@ pub mod data_structure;
 mod error;
 pub mod msg;
 pub mod permission;
+pub mod schedule;
 pub mod staking;
 pub mod state;
 pub mod vesting;
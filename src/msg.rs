@@ -1,8 +1,14 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, CustomQuery, Timestamp, Uint128, VoteOption};
+use cosmwasm_std::{Addr, CustomQuery, Decimal, Timestamp, Uint128, VoteOption};
+use cw20::Cw20ReceiveMsg;
 use cw_utils::{Duration, Threshold};
 
-use crate::data_structure::Tranche;
+use crate::data_structure::{
+    deserialize_amount, deserialize_amounts, deserialize_validator_amounts, Clawback,
+    DelegationCap, LinearVestingState, MilestoneState, PendingWithdrawal, RewardPolicy,
+    ScheduleAmendment, SchedulePoint, ScheduleUnit, Tranche, UnbondingPrincipal, ValidationPolicy,
+    VestingScheduleSpec, WeightedRecipient, WithdrawalKind, WithdrawalRecord,
+};
 
 #[cw_serde]
 pub struct MigrateMsg {}
@@ -14,27 +20,71 @@ pub struct InstantiateMsg {
     pub tranche: Tranche,
     pub max_voting_period: Duration,
     pub admin_voting_threshold_percentage: u8,
+    // defaults to `ValidationPolicy::default()`, which matches the rules from before policies
+    pub validation_policy: Option<ValidationPolicy>,
+    // the chain's unbonding period in seconds, defaults to Sei's 21 days
+    pub unbonding_period: Option<u64>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
     Delegate {
         validator: String,
-        amount: u128,
+        #[serde(deserialize_with = "deserialize_amount")]
+        amount: Uint128,
     },
     Redelegate {
         src_validator: String,
         dst_validator: String,
-        amount: u128,
+        #[serde(deserialize_with = "deserialize_amount")]
+        amount: Uint128,
     },
     Undelegate {
         validator: String,
-        amount: u128,
+        #[serde(deserialize_with = "deserialize_amount")]
+        amount: Uint128,
+    },
+    // (validator, amount) pairs, delegated in a single response
+    DelegateMany {
+        #[serde(deserialize_with = "deserialize_validator_amounts")]
+        delegations: Vec<(String, Uint128)>,
+    },
+    UndelegateMany {
+        #[serde(deserialize_with = "deserialize_validator_amounts")]
+        undelegations: Vec<(String, Uint128)>,
+    },
+    RedelegateMany {
+        redelegations: Vec<Redelegation>,
     },
     InitiateWithdrawUnlocked {
-        amount: u128,
+        #[serde(deserialize_with = "deserialize_amount")]
+        amount: Uint128,
+    },
+    InitiateWithdrawTrancheUnlocked {
+        tranche_id: String,
+        #[serde(deserialize_with = "deserialize_amount")]
+        amount: Uint128,
     },
+    AddTranche {
+        tranche_id: String,
+        tranche: Tranche,
+    },
+    // deposits the attached funds into a tranche, vesting as the given entries. `tranche_id`
+    // defaults to the tranche specified at instantiation
+    TopUp {
+        tranche_id: Option<String>,
+        vesting_timestamps: Vec<Timestamp>,
+        #[serde(deserialize_with = "deserialize_amounts")]
+        vesting_amounts: Vec<Uint128>,
+    },
+    // funds CW20 tranches; the wrapped message must be a `ReceiveMsg`
+    Receive(Cw20ReceiveMsg),
     InitiateWithdrawReward {},
+    // sends everything vested so far in every tranche to its unlocked distribution address. Callable
+    // by anyone once enabled through `ProposeUpdatePermissionlessRelease`
+    ReleaseVested {},
+    // pays out pending withdrawals, oldest first, for as long as the liquid balance covers them
+    ClaimPendingWithdrawals {},
     UpdateOp {
         op: Addr,
         remove: bool,
@@ -42,6 +92,17 @@ pub enum ExecuteMsg {
     ProposeEmergencyWithdraw {
         dst: Addr,
     },
+    // `tranche_id` defaults to the tranche specified at instantiation
+    ProposeClawback {
+        tranche_id: Option<String>,
+        dst: Addr,
+        clawback: Clawback,
+    },
+    // `tranche_id` defaults to the tranche specified at instantiation
+    ProposeAmendSchedule {
+        tranche_id: Option<String>,
+        amendment: ScheduleAmendment,
+    },
     ProposeUpdateAdmin {
         admin: Addr,
         remove: bool,
@@ -49,9 +110,59 @@ pub enum ExecuteMsg {
     ProposeUpdateUnlockedDistributionAddress {
         unlocked_distribution_address: Addr,
     },
+    ProposeUpdateTrancheUnlockedDistributionAddress {
+        tranche_id: String,
+        unlocked_distribution_address: Addr,
+    },
+    // splits unlocked tokens among weighted recipients instead of sending them to the unlocked
+    // distribution address, until that address is updated again. `tranche_id` defaults to the
+    // tranche specified at instantiation
+    ProposeUpdateUnlockedDistributionSplit {
+        tranche_id: Option<String>,
+        recipients: Vec<WeightedRecipient>,
+    },
     ProposeUpdateStakingRewardDistributionAddress {
         staking_reward_distribution_address: Addr,
     },
+    // splits staking rewards among recipients weighted in basis points, which must add up to
+    // 10000. The first recipient also receives the rounding dust. Updating the staking reward
+    // distribution address removes the split.
+    ProposeUpdateStakingRewardSplit {
+        recipients: Vec<WeightedRecipient>,
+    },
+    ProposeUpdatePermissionlessRelease {
+        enabled: bool,
+    },
+    // if enabled, a withdrawal of vested tokens that exceeds the liquid balance undelegates the
+    // shortfall and is paid out by `ClaimPendingWithdrawals` once unbonding completes
+    ProposeUpdateUndelegateForWithdrawals {
+        enabled: bool,
+    },
+    // once the allowlist has any validator, ops can only delegate and redelegate to its validators
+    ProposeUpdateAllowedValidator {
+        validator: String,
+        remove: bool,
+    },
+    // `None` lifts the cap
+    ProposeUpdateDelegationCap {
+        cap: Option<DelegationCap>,
+    },
+    ProposeUpdateRewardPolicy {
+        policy: RewardPolicy,
+    },
+    // makes a milestone's amount vest. Milestones that have lapsed can no longer be reached.
+    // `tranche_id` defaults to the tranche specified at instantiation
+    ProposeMilestoneReached {
+        tranche_id: Option<String>,
+        milestone_id: String,
+    },
+    // sets the tranche's total amount to what its accounting components add up to, see
+    // `QueryMsg::Reconcile`. `explanation` names the cause of the discrepancy and becomes part of
+    // the proposal title. `tranche_id` defaults to the tranche specified at instantiation.
+    ProposeCorrectTotalAmount {
+        tranche_id: Option<String>,
+        explanation: String,
+    },
     ProposeGovVote {
         gov_proposal_id: u64,
         gov_vote: VoteOption,
@@ -69,11 +180,84 @@ pub enum ExecuteMsg {
     InternalUpdateUnlockedDistributionAddress {
         unlocked_distribution_address: Addr,
     },
+    InternalUpdateTrancheUnlockedDistributionAddress {
+        tranche_id: String,
+        unlocked_distribution_address: Addr,
+    },
+    InternalUpdateUnlockedDistributionSplit {
+        tranche_id: String,
+        recipients: Vec<WeightedRecipient>,
+    },
     InternalUpdateStakingRewardDistributionAddress {
         staking_reward_distribution_address: Addr,
     },
+    InternalUpdateStakingRewardSplit {
+        recipients: Vec<WeightedRecipient>,
+    },
+    InternalUpdatePermissionlessRelease {
+        enabled: bool,
+    },
+    InternalUpdateUndelegateForWithdrawals {
+        enabled: bool,
+    },
+    InternalUpdateAllowedValidator {
+        validator: String,
+        remove: bool,
+    },
+    InternalUpdateDelegationCap {
+        cap: Option<DelegationCap>,
+    },
+    InternalUpdateRewardPolicy {
+        policy: RewardPolicy,
+    },
     InternalWithdrawLocked {
         dst: Addr,
+        // recorded as the sender of the payout. `None` for proposals made before it was recorded,
+        // whose payouts are recorded as sent by the contract.
+        proposer: Option<Addr>,
+    },
+    InternalClawback {
+        tranche_id: String,
+        dst: Addr,
+        clawback: Clawback,
+        proposer: Addr,
+    },
+    InternalAmendSchedule {
+        tranche_id: String,
+        amendment: ScheduleAmendment,
+        proposer: Addr,
+    },
+    InternalMilestoneReached {
+        tranche_id: String,
+        milestone_id: String,
+    },
+    InternalCorrectTotalAmount {
+        tranche_id: String,
+        #[serde(deserialize_with = "deserialize_amount")]
+        total_amount: Uint128,
+    },
+}
+
+#[cw_serde]
+pub struct Redelegation {
+    pub src_validator: String,
+    pub dst_validator: String,
+    #[serde(deserialize_with = "deserialize_amount")]
+    pub amount: Uint128,
+}
+
+// messages sent through the CW20 receive hook, funded by the received tokens
+#[cw_serde]
+pub enum ReceiveMsg {
+    AddTranche {
+        tranche_id: String,
+        tranche: Box<Tranche>,
+    },
+    TopUp {
+        tranche_id: String,
+        vesting_timestamps: Vec<Timestamp>,
+        #[serde(deserialize_with = "deserialize_amounts")]
+        vesting_amounts: Vec<Uint128>,
     },
 }
 
@@ -88,12 +272,68 @@ pub enum QueryMsg {
     ListAdmins {},
     #[returns(OpListResponse)]
     ListOps {},
+    #[returns(AllowedValidatorListResponse)]
+    ListAllowedValidators {},
+    // staking rewards paid out to each recipient so far
+    #[returns(RewardRecipientsResponse)]
+    RewardRecipients {},
     #[returns(ShowInfoResponse)]
     Info {},
+    #[returns(ShowInfoResponse)]
+    TrancheInfo { tranche_id: String },
+    #[returns(TrancheListResponse)]
+    ListTranches {},
     #[returns(ShowConfigResponse)]
     Config {},
     #[returns(ShowTotalVestedResponse)]
     TotalVested {},
+    #[returns(ShowTotalVestedResponse)]
+    TrancheTotalVested { tranche_id: String },
+    #[returns(ShowExpandedScheduleResponse)]
+    ExpandScheduleSpec { spec: VestingScheduleSpec },
+    // lists the remaining vesting entries after the `start_after` schedule index. `tranche_id`
+    // defaults to the tranche specified at instantiation
+    #[returns(VestingScheduleResponse)]
+    VestingSchedule {
+        tranche_id: Option<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // lists recorded payouts after the `start_after` sequence number, optionally of one kind only
+    #[returns(WithdrawalHistoryResponse)]
+    WithdrawalHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        kind: Option<WithdrawalKind>,
+    },
+    #[returns(NextUnlockResponse)]
+    NextUnlock { tranche_id: Option<String> },
+    // projects how much of the remaining schedule will have vested at `at`, which must be in the
+    // tranche's schedule unit
+    #[returns(ShowTotalVestedResponse)]
+    VestedAt {
+        tranche_id: Option<String>,
+        at: SchedulePoint,
+    },
+    // lists withdrawals waiting for stake to unbond after the `start_after` sequence number
+    #[returns(PendingWithdrawalsResponse)]
+    PendingWithdrawals {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // `tranche_id` defaults to the tranche specified at instantiation
+    #[returns(MilestonesResponse)]
+    Milestones { tranche_id: Option<String> },
+    // checks every tranche's accounting, and the native principal the contract should hold against
+    // what it holds in the bank, staked and unbonding
+    #[returns(ReconcileResponse)]
+    Reconcile {},
+    // where the contract's principal is according to its own accounting
+    #[returns(PrincipalLedgerResponse)]
+    PrincipalLedger {},
+    // the contract's staking position
+    #[returns(PortfolioResponse)]
+    Portfolio {},
 }
 
 #[cw_serde]
@@ -106,27 +346,195 @@ pub struct OpListResponse {
     pub ops: Vec<Addr>,
 }
 
+#[cw_serde]
+pub struct AllowedValidatorListResponse {
+    pub validators: Vec<String>,
+}
+
+#[cw_serde]
+pub struct RewardRecipientEntry {
+    pub address: Addr,
+    pub withdrawn: Uint128,
+}
+
+#[cw_serde]
+pub struct RewardRecipientsResponse {
+    pub recipients: Vec<RewardRecipientEntry>,
+}
+
+#[cw_serde]
+pub struct VestingEvent {
+    // schedule index, to be passed as `start_after` for the next page
+    pub index: u64,
+    pub timestamp: Timestamp,
+    pub amount: Uint128,
+    // everything in the remaining schedule that will have vested by `timestamp`
+    pub cumulative_amount: Uint128,
+}
+
+#[cw_serde]
+pub struct VestingScheduleResponse {
+    // what the event timestamps are measured in
+    pub schedule_unit: ScheduleUnit,
+    pub events: Vec<VestingEvent>,
+}
+
+#[cw_serde]
+pub struct WithdrawalEntry {
+    pub id: u64,
+    pub withdrawal: WithdrawalRecord,
+}
+
+#[cw_serde]
+pub struct WithdrawalHistoryResponse {
+    pub withdrawals: Vec<WithdrawalEntry>,
+}
+
+#[cw_serde]
+pub struct PendingWithdrawalEntry {
+    pub id: u64,
+    pub withdrawal: PendingWithdrawal,
+}
+
+#[cw_serde]
+pub struct PendingWithdrawalsResponse {
+    pub withdrawals: Vec<PendingWithdrawalEntry>,
+}
+
+#[cw_serde]
+pub struct MilestoneEntry {
+    pub milestone_id: String,
+    pub milestone: MilestoneState,
+    // whether the deadline passed before the milestone was reached
+    pub lapsed: bool,
+}
+
+#[cw_serde]
+pub struct MilestonesResponse {
+    // milestones that are fully withdrawn or revoked are no longer listed
+    pub milestones: Vec<MilestoneEntry>,
+}
+
+#[cw_serde]
+pub struct TrancheReconciliation {
+    pub tranche_id: String,
+    pub cw20_token: Option<Addr>,
+    pub total_amount: Uint128,
+    // what the remaining schedule and milestones have yet to pay out
+    pub remaining_amount: Uint128,
+    pub withdrawn_unlocked: Uint128,
+    pub withdrawn_locked: Uint128,
+    // `remaining_amount + withdrawn_unlocked + withdrawn_locked`, which should equal `total_amount`
+    pub accounted_amount: Uint128,
+}
+
+#[cw_serde]
+pub struct ReconcileResponse {
+    pub tranches: Vec<TrancheReconciliation>,
+    // remaining native principal plus pending withdrawals, which are paid out but still held
+    pub expected_holdings: Uint128,
+    pub bank_balance: Uint128,
+    pub staked: Uint128,
+    // as reported by the chain, or the unbonding principal of the principal ledger where the chain
+    // does not report unbonding delegations
+    pub unbonding: Uint128,
+    // holdings beyond the expected ones, e.g. staking rewards that have not been withdrawn
+    pub surplus: Uint128,
+    // expected holdings that are missing
+    pub shortfall: Uint128,
+}
+
+#[cw_serde]
+pub struct PortfolioDelegation {
+    pub validator: String,
+    pub amount: Uint128,
+    pub pending_rewards: Uint128,
+}
+
+#[cw_serde]
+pub struct PortfolioResponse {
+    pub delegations: Vec<PortfolioDelegation>,
+    pub total_delegated: Uint128,
+    pub total_pending_rewards: Uint128,
+    // as reported by the chain, None where the chain does not report unbonding delegations
+    pub unbonding: Option<Vec<UnbondingDelegationEntry>>,
+    pub liquid_principal: Uint128,
+    // rewards already withdrawn to the contract's bank balance
+    pub liquid_rewards: Uint128,
+    // delegated principal over all principal held by the contract
+    pub staked_principal_share: Decimal,
+}
+
+#[cw_serde]
+pub struct PrincipalLedgerResponse {
+    pub delegations: Vec<(String, Uint128)>,
+    pub unbonding: Vec<UnbondingPrincipal>,
+    pub delegated: Uint128,
+    // principal whose unbonding has not completed yet
+    pub unbonding_total: Uint128,
+    pub liquid: Uint128,
+    // the unbonding balance reported by the chain as a cross-check, if the chain supports the query.
+    // It includes unbonding rewards.
+    pub reported_unbonding: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct NextUnlockResponse {
+    pub schedule_unit: ScheduleUnit,
+    // `None` once the whole remaining schedule has vested
+    pub timestamp: Option<Timestamp>,
+    // the amount that becomes withdrawable between now and `timestamp`
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct TrancheListResponse {
+    pub tranche_ids: Vec<String>,
+}
+
 #[cw_serde]
 pub struct ShowInfoResponse {
+    // the token's contract address for CW20 tranches
     pub denom: String,
+    pub cw20_token: Option<Addr>,
+    // what the vesting timestamps and linear vesting points are measured in
+    pub schedule_unit: ScheduleUnit,
     pub vesting_timestamps: Vec<Timestamp>,
-    pub vesting_amounts: Vec<u128>,
+    pub vesting_amounts: Vec<Uint128>,
+    pub linear_vesting: Option<LinearVestingState>,
     pub unlock_distribution_address: Addr,
+    // if set, unlocked tokens are split among these recipients instead
+    pub unlock_distribution_split: Option<Vec<WeightedRecipient>>,
     pub staking_reward_address: Addr,
-    pub withdrawn_staking_rewards: u128,
-    pub withdrawn_unlocked: u128,
-    pub withdrawn_locked: u128,
+    pub staking_reward_split: Option<Vec<WeightedRecipient>>,
+    pub withdrawn_staking_rewards: Uint128,
+    // rewards delegated under a compounding reward policy that are still staked
+    pub compounded_staking_rewards: Uint128,
+    pub withdrawn_unlocked: Uint128,
+    pub withdrawn_locked: Uint128,
 }
 
 #[cw_serde]
 pub struct ShowConfigResponse {
     pub max_voting_period: Duration,
     pub admin_voting_threshold: Threshold,
+    pub permissionless_release: bool,
+    pub undelegate_for_withdrawals: bool,
+    pub validation_policy: ValidationPolicy,
+    pub delegation_cap: Option<DelegationCap>,
+    pub reward_policy: RewardPolicy,
 }
 
 #[cw_serde]
 pub struct ShowTotalVestedResponse {
-    pub vested_amount: u128,
+    pub vested_amount: Uint128,
+    pub cw20_token: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct ShowExpandedScheduleResponse {
+    pub vesting_timestamps: Vec<Timestamp>,
+    pub vesting_amounts: Vec<Uint128>,
 }
 
 #[cw_serde]
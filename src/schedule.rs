@@ -0,0 +1,335 @@
+use cosmwasm_std::{Timestamp, Uint128};
+
+use crate::{
+    data_structure::{RemainderHandling, VestingPeriod, VestingScheduleSpec},
+    ContractError,
+};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// expands a compact schedule spec into the vesting timestamps and amounts that a tranche stores.
+// Release `k` (1-based) happens `k` periods after `start`; every release up to and including the
+// cliff is merged into a single release at the cliff.
+pub fn expand_schedule_spec(
+    spec: &VestingScheduleSpec,
+) -> Result<(Vec<Timestamp>, Vec<Uint128>), ContractError> {
+    if spec.num_periods == 0 {
+        return Err(ContractError::InvalidTranche(
+            "schedule spec must have at least one period".to_string(),
+        ));
+    }
+    if spec.cliff_periods > spec.num_periods {
+        return Err(ContractError::InvalidTranche(
+            "cliff cannot be longer than the vesting schedule".to_string(),
+        ));
+    }
+    match spec.period {
+        VestingPeriod::Time(0) | VestingPeriod::Months(0) => {
+            return Err(ContractError::InvalidTranche(
+                "period length must be positive".to_string(),
+            ))
+        }
+        _ => {}
+    }
+
+    let num_periods = Uint128::from(spec.num_periods);
+    let per_period = spec.total_amount / num_periods;
+    let remainder = spec.total_amount % num_periods;
+    let first_release = std::cmp::max(spec.cliff_periods, 1);
+
+    let mut vesting_timestamps = vec![];
+    let mut vesting_amounts = vec![];
+    for k in first_release..=spec.num_periods {
+        vesting_timestamps.push(period_end(spec.start, &spec.period, k)?);
+        if k == first_release {
+            vesting_amounts.push(per_period * Uint128::from(first_release));
+        } else {
+            vesting_amounts.push(per_period);
+        }
+    }
+    match spec.remainder_handling {
+        RemainderHandling::AddToFirst => vesting_amounts[0] += remainder,
+        RemainderHandling::AddToLast => *vesting_amounts.last_mut().unwrap() += remainder,
+    }
+
+    Ok((vesting_timestamps, vesting_amounts))
+}
+
+fn period_end(
+    start: Timestamp,
+    period: &VestingPeriod,
+    num_periods: u32,
+) -> Result<Timestamp, ContractError> {
+    match period {
+        VestingPeriod::Time(seconds) => seconds
+            .checked_mul(num_periods as u64)
+            .and_then(|offset| offset.checked_mul(1_000_000_000))
+            .and_then(|offset| start.nanos().checked_add(offset))
+            .map(Timestamp::from_nanos),
+        VestingPeriod::Months(months) => add_months(start, *months as u64 * num_periods as u64),
+    }
+    .ok_or_else(|| ContractError::InvalidTranche("schedule spec overflows".to_string()))
+}
+
+// adds calendar months to a timestamp (in UTC), keeping the time of day. If the day of month does
+// not exist in the target month, the last day of that month is used instead (e.g. Jan 31 -> Feb 28).
+// Returns `None` if the result does not fit in a timestamp.
+pub fn add_months(ts: Timestamp, months: u64) -> Option<Timestamp> {
+    let days = ts.seconds() / SECONDS_PER_DAY;
+    let time_of_day_nanos = ts.nanos() - days * SECONDS_PER_DAY * 1_000_000_000;
+    let (year, month, day) = civil_from_days(days);
+
+    let month_index = year
+        .checked_mul(12)?
+        .checked_add(month as u64 - 1)?
+        .checked_add(months)?;
+    let new_year = month_index / 12;
+    let new_month = (month_index % 12) as u32 + 1;
+    let new_day = std::cmp::min(day, days_in_month(new_year, new_month)?);
+
+    days_from_civil(new_year, new_month, new_day)?
+        .checked_mul(SECONDS_PER_DAY * 1_000_000_000)?
+        .checked_add(time_of_day_nanos)
+        .map(Timestamp::from_nanos)
+}
+
+// parses the RFC 3339 times the chain reports in UTC, e.g. "2022-10-12T00:00:00Z", optionally with
+// fractional seconds. Returns `None` for anything else, including dates before 1970.
+pub fn timestamp_from_rfc3339(time: &str) -> Option<Timestamp> {
+    let (date, time) = time.strip_suffix('Z')?.split_once('T')?;
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<u64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let mut time = time.splitn(3, ':').map(|part| part.parse::<u64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&month)
+        || hour > 23
+        || minute > 59
+        || second > 59
+        || fraction.len() > 9
+        || !fraction.bytes().all(|byte| byte.is_ascii_digit())
+    {
+        return None;
+    }
+    let month = month as u32;
+    if day == 0 || day > days_in_month(year, month)? as u64 {
+        return None;
+    }
+    let nanos: u64 = format!("{:0<9}", fraction).parse().ok()?;
+    days_from_civil(year, month, day as u32)?
+        .checked_mul(SECONDS_PER_DAY)?
+        .checked_add(hour * 3_600 + minute * 60 + second)?
+        .checked_mul(1_000_000_000)?
+        .checked_add(nanos)
+        .map(Timestamp::from_nanos)
+}
+
+fn days_in_month(year: u64, month: u32) -> Option<u32> {
+    let (next_year, next_month) = if month == 12 {
+        (year.checked_add(1)?, 1)
+    } else {
+        (year, month + 1)
+    };
+    days_from_civil(next_year, next_month, 1)?
+        .checked_sub(days_from_civil(year, month, 1)?)?
+        .try_into()
+        .ok()
+}
+
+// the two conversions below follow Howard Hinnant's `civil_from_days`/`days_from_civil`
+// algorithms, restricted to dates on or after 1970-01-01.
+fn civil_from_days(days: u64) -> (u64, u32, u32) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_from_civil(year: u64, month: u32, day: u32) -> Option<u64> {
+    let year = if month <= 2 {
+        year.checked_sub(1)?
+    } else {
+        year
+    };
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 } as u64;
+    let doy = ((153 * mp + 2) / 5 + day as u64).checked_sub(1)?;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era.checked_mul(146_097)?
+        .checked_add(doe)?
+        .checked_sub(719_468)
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::Timestamp;
+
+    use super::*;
+
+    // 2024-01-31T12:00:00Z
+    const JAN_31_2024_NOON: u64 = 1706702400;
+
+    #[test]
+    fn test_add_months() {
+        let start = Timestamp::from_seconds(JAN_31_2024_NOON);
+        // 2024-02-29T12:00:00Z (leap year)
+        assert_eq!(
+            Timestamp::from_seconds(1709208000),
+            add_months(start, 1).unwrap()
+        );
+        // 2024-04-30T12:00:00Z
+        assert_eq!(
+            Timestamp::from_seconds(1714478400),
+            add_months(start, 3).unwrap()
+        );
+        // 2025-01-31T12:00:00Z
+        assert_eq!(
+            Timestamp::from_seconds(1738324800),
+            add_months(start, 12).unwrap()
+        );
+        // 2025-02-28T12:00:00Z
+        assert_eq!(
+            Timestamp::from_seconds(1740744000),
+            add_months(start, 13).unwrap()
+        );
+        assert_eq!(start, add_months(start, 0).unwrap());
+        assert_eq!(None, add_months(start, u64::MAX));
+        assert_eq!(None, add_months(start, 12 * 1_000));
+    }
+
+    #[test]
+    fn test_add_months_keeps_nanos() {
+        let start = Timestamp::from_seconds(JAN_31_2024_NOON).plus_nanos(123);
+        assert_eq!(
+            Timestamp::from_seconds(1709208000).plus_nanos(123),
+            add_months(start, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_timestamp_from_rfc3339() {
+        assert_eq!(
+            Some(Timestamp::from_seconds(JAN_31_2024_NOON)),
+            timestamp_from_rfc3339("2024-01-31T12:00:00Z")
+        );
+        assert_eq!(
+            Some(Timestamp::from_seconds(JAN_31_2024_NOON).plus_nanos(120_000_000)),
+            timestamp_from_rfc3339("2024-01-31T12:00:00.12Z")
+        );
+        assert_eq!(None, timestamp_from_rfc3339("2024-01-31T12:00:00"));
+        assert_eq!(None, timestamp_from_rfc3339("2024-02-30T12:00:00Z"));
+        assert_eq!(None, timestamp_from_rfc3339("2024-01-31T24:00:00Z"));
+        assert_eq!(None, timestamp_from_rfc3339("1969-12-31T23:59:59Z"));
+        assert_eq!(None, timestamp_from_rfc3339("99999999999-01-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_civil_conversions_overflow() {
+        assert_eq!(Some(19_753), days_from_civil(2024, 1, 31));
+        assert_eq!(Some(29), days_in_month(2024, 2));
+        assert_eq!(None, days_from_civil(u64::MAX, 3, 1));
+        assert_eq!(None, days_from_civil(0, 1, 1));
+        assert_eq!(None, days_from_civil(1969, 12, 31));
+        assert_eq!(None, days_in_month(u64::MAX, 12));
+    }
+
+    #[test]
+    fn test_expand_monthly_with_cliff() {
+        let spec = VestingScheduleSpec {
+            start: Timestamp::from_seconds(JAN_31_2024_NOON),
+            cliff_periods: 12,
+            num_periods: 48,
+            period: VestingPeriod::Months(1),
+            total_amount: Uint128::new(48_000_005),
+            remainder_handling: RemainderHandling::AddToLast,
+        };
+        let (timestamps, amounts) = expand_schedule_spec(&spec).unwrap();
+        assert_eq!(37, timestamps.len());
+        assert_eq!(37, amounts.len());
+        assert_eq!(Timestamp::from_seconds(1738324800), timestamps[0]);
+        assert_eq!(Timestamp::from_seconds(1740744000), timestamps[1]);
+        assert_eq!(Uint128::new(12_000_000), amounts[0]);
+        assert_eq!(Uint128::new(1_000_000), amounts[1]);
+        assert_eq!(Uint128::new(1_000_005), *amounts.last().unwrap());
+        assert_eq!(Uint128::new(48_000_005), amounts.iter().sum::<Uint128>());
+    }
+
+    #[test]
+    fn test_expand_fixed_period_remainder_first() {
+        let spec = VestingScheduleSpec {
+            start: Timestamp::from_seconds(100),
+            cliff_periods: 0,
+            num_periods: 3,
+            period: VestingPeriod::Time(10),
+            total_amount: Uint128::new(100),
+            remainder_handling: RemainderHandling::AddToFirst,
+        };
+        let (timestamps, amounts) = expand_schedule_spec(&spec).unwrap();
+        assert_eq!(
+            vec![
+                Timestamp::from_seconds(110),
+                Timestamp::from_seconds(120),
+                Timestamp::from_seconds(130)
+            ],
+            timestamps
+        );
+        assert_eq!(
+            vec![Uint128::new(34), Uint128::new(33), Uint128::new(33)],
+            amounts
+        );
+    }
+
+    #[test]
+    fn test_expand_invalid_spec() {
+        let mut spec = VestingScheduleSpec {
+            start: Timestamp::from_seconds(100),
+            cliff_periods: 4,
+            num_periods: 3,
+            period: VestingPeriod::Time(10),
+            total_amount: Uint128::new(100),
+            remainder_handling: RemainderHandling::AddToFirst,
+        };
+        assert_eq!(
+            ContractError::InvalidTranche(
+                "cliff cannot be longer than the vesting schedule".to_string()
+            ),
+            expand_schedule_spec(&spec).unwrap_err()
+        );
+        spec.cliff_periods = 0;
+        spec.num_periods = 0;
+        assert_eq!(
+            ContractError::InvalidTranche(
+                "schedule spec must have at least one period".to_string()
+            ),
+            expand_schedule_spec(&spec).unwrap_err()
+        );
+        spec.num_periods = 3;
+        spec.period = VestingPeriod::Time(u64::MAX / 2);
+        assert_eq!(
+            ContractError::InvalidTranche("schedule spec overflows".to_string()),
+            expand_schedule_spec(&spec).unwrap_err()
+        );
+        spec.period = VestingPeriod::Time(10_000_000_000);
+        assert_eq!(
+            ContractError::InvalidTranche("schedule spec overflows".to_string()),
+            expand_schedule_spec(&spec).unwrap_err()
+        );
+        spec.period = VestingPeriod::Months(u32::MAX);
+        assert_eq!(
+            ContractError::InvalidTranche("schedule spec overflows".to_string()),
+            expand_schedule_spec(&spec).unwrap_err()
+        );
+        spec.period = VestingPeriod::Months(0);
+        assert_eq!(
+            ContractError::InvalidTranche("period length must be positive".to_string()),
+            expand_schedule_spec(&spec).unwrap_err()
+        );
+    }
+}
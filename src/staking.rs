@@ -1,12 +1,13 @@
 use cosmwasm_std::{
-    coins, BankMsg, Coin, Deps, DistributionMsg, Env, QueryRequest, Response, StakingMsg,
-    StdResult, Uint128,
+    Coin, Deps, DistributionMsg, Env, QueryRequest, Response, StakingMsg, StdResult, Uint128,
 };
 use serde::Deserialize;
 
 use crate::{
-    msg::{SeiQuery, SeiQueryWrapper, SeiRoute, UnbondingDelegationsResponse},
-    state::{DENOM, STAKING_REWARD_ADDRESS},
+    msg::{
+        SeiQuery, SeiQueryWrapper, SeiRoute, UnbondingDelegationEntry, UnbondingDelegationsResponse,
+    },
+    state::DENOM,
     ContractError,
 };
 
@@ -41,24 +42,9 @@ pub fn undelegate(response: Response, validator: String, amount: u128, denom: St
     response.add_message(msg)
 }
 
-pub fn withdraw_delegation_rewards(
-    deps: Deps<SeiQueryWrapper>,
-    response: Response,
-    validator: String,
-    amount: u128,
-) -> Result<Response, ContractError> {
-    let msg = DistributionMsg::WithdrawDelegatorReward {
-        validator: validator,
-    };
-    let denom = DENOM.load(deps.storage)?;
-    let to_address = STAKING_REWARD_ADDRESS.load(deps.storage)?;
-    let send_msg = BankMsg::Send {
-        to_address: to_address.to_string(),
-        amount: coins(amount, denom),
-    };
-    let mut new_response = response.add_message(msg);
-    new_response = new_response.add_message(send_msg);
-    Ok(new_response)
+// leaves the withdrawn rewards in the contract's balance
+pub fn withdraw_rewards(response: Response, validator: String) -> Response {
+    response.add_message(DistributionMsg::WithdrawDelegatorReward { validator })
 }
 
 // the `all_delegations` endpoint do not return full delegation info (i.e. no withdrawable delegation reward)
@@ -78,7 +64,10 @@ pub fn get_all_delegated_validators(
         })?)
 }
 
-pub fn get_unbonding_balance(deps: Deps<SeiQueryWrapper>, env: Env) -> StdResult<u128> {
+pub fn get_unbonding_entries(
+    deps: Deps<SeiQueryWrapper>,
+    env: Env,
+) -> StdResult<Vec<UnbondingDelegationEntry>> {
     let request = SeiQueryWrapper {
         route: SeiRoute::Stakingext,
         query_data: SeiQuery::UnbondingDelegations {
@@ -87,8 +76,11 @@ pub fn get_unbonding_balance(deps: Deps<SeiQueryWrapper>, env: Env) -> StdResult
     };
     let wrapped_request = QueryRequest::Custom(request);
     let response: UnbondingDelegationsResponse = deps.querier.query(&wrapped_request)?;
-    Ok(response
-        .entries
+    Ok(response.entries)
+}
+
+pub fn get_unbonding_balance(deps: Deps<SeiQueryWrapper>, env: Env) -> StdResult<u128> {
+    Ok(get_unbonding_entries(deps, env)?
         .iter()
         .map(|entry| -> u128 { entry.balance.u128() })
         .sum())
@@ -98,7 +90,7 @@ pub fn get_delegation_rewards(
     deps: Deps<SeiQueryWrapper>,
     env: Env,
     validator: String,
-) -> Result<u128, ContractError> {
+) -> StdResult<u128> {
     let delegation = deps
         .querier
         .query_delegation(env.contract.address.to_string(), validator)?;
@@ -1,19 +1,163 @@
-use cosmwasm_std::{Addr, StdResult, Storage, Timestamp};
+use cosmwasm_std::{Addr, Order, StdError, StdResult, Storage, Timestamp};
 use cw3::{Ballot, Proposal};
 use cw_storage_plus::{Item, Map};
 use cw_utils::{Duration, Threshold};
 
-use crate::data_structure::EmptyStruct;
+use crate::data_structure::{
+    DelegationCap, EmptyStruct, LinearVestingState, MilestoneState, PendingWithdrawal,
+    RewardPolicy, ScheduleCursor, ScheduleUnit, UnbondingPrincipal, ValidationPolicy, VestingEntry,
+    WeightedRecipient, WithdrawalRecord,
+};
+
+// Sei's unbonding period
+const DEFAULT_UNBONDING_PERIOD: u64 = 21 * 24 * 60 * 60;
 
 pub const DENOM: Item<String> = Item::new("denom");
-pub const VESTING_TIMESTAMPS: Item<Vec<Timestamp>> = Item::new("ts");
-pub const VESTING_AMOUNTS: Item<Vec<u128>> = Item::new("amounts");
-pub const TOTAL_AMOUNT: Item<u128> = Item::new("ttam");
-pub const UNLOCK_DISTRIBUTION_ADDRESS: Item<Addr> = Item::new("uda");
 pub const STAKING_REWARD_ADDRESS: Item<Addr> = Item::new("sra");
 pub const WITHDRAWN_STAKING_REWARDS: Item<u128> = Item::new("wsr");
-pub const WITHDRAWN_UNLOCKED: Item<u128> = Item::new("wu");
-pub const WITHDRAWN_LOCKED: Item<u128> = Item::new("wl");
+// whether anyone may release vested tokens to the distribution addresses. Off unless set.
+pub const PERMISSIONLESS_RELEASE: Item<bool> = Item::new("permissionless_release");
+// whether withdrawals of vested tokens that exceed the liquid balance undelegate the shortfall and
+// wait for it to unbond instead of failing. Off unless set.
+pub const UNDELEGATE_FOR_WITHDRAWALS: Item<bool> = Item::new("undelegate_for_withdrawals");
+// validators ops may delegate or redelegate to. An empty allowlist places no restriction.
+pub const ALLOWED_VALIDATORS: Map<&str, EmptyStruct> = Map::new("allowed_validators");
+// applies to every validator. No cap unless set.
+pub const DELEGATION_CAP: Item<DelegationCap> = Item::new("delegation_cap");
+// `RewardPolicy::Send` unless set
+pub const REWARD_POLICY: Item<RewardPolicy> = Item::new("reward_policy");
+// staking rewards that were delegated instead of paid out and have not been undelegated since. They
+// are part of the staked balance but not principal.
+pub const COMPOUNDED_REWARDS: Item<u128> = Item::new("compounded_rewards");
+// overrides `STAKING_REWARD_ADDRESS` while set
+pub const STAKING_REWARD_SPLIT: Item<Vec<WeightedRecipient>> = Item::new("staking_reward_split");
+pub const WITHDRAWN_REWARDS_BY_RECIPIENT: Map<&Addr, u128> =
+    Map::new("withdrawn_rewards_by_recipient");
+// absent for contracts instantiated before validation policies, which use the default policy
+pub const VALIDATION_POLICY: Item<ValidationPolicy> = Item::new("validation_policy");
+
+// TRANCHE STATES, keyed by tranche ID
+// the tranche specified at instantiation. Messages that don't specify a tranche act on it.
+pub const DEFAULT_TRANCHE_ID: &str = "default";
+// keyed by (tranche ID, schedule index). Entries are removed once fully withdrawn or revoked.
+pub const VESTING_SCHEDULE: Map<(&str, u64), VestingEntry> = Map::new("tranche_schedule");
+pub const SCHEDULE_CURSOR: Map<&str, ScheduleCursor> = Map::new("tranche_cursor");
+pub const TOTAL_AMOUNT: Map<&str, u128> = Map::new("tranche_ttam");
+pub const UNLOCK_DISTRIBUTION_ADDRESS: Map<&str, Addr> = Map::new("tranche_uda");
+// if present, unlocked tokens are split among these recipients instead of going to
+// `UNLOCK_DISTRIBUTION_ADDRESS`
+pub const UNLOCK_DISTRIBUTION_SPLIT: Map<&str, Vec<WeightedRecipient>> = Map::new("tranche_uds");
+pub const WITHDRAWN_UNLOCKED: Map<&str, u128> = Map::new("tranche_wu");
+pub const WITHDRAWN_LOCKED: Map<&str, u128> = Map::new("tranche_wl");
+// only present for tranches that vest linearly
+pub const LINEAR_VESTING: Map<&str, LinearVestingState> = Map::new("tranche_lv");
+// only present for tranches that vest a CW20 token instead of `DENOM`
+pub const CW20_TOKEN: Map<&str, Addr> = Map::new("tranche_cw20");
+// only present for tranches whose schedule is not in `ScheduleUnit::Time`
+pub const SCHEDULE_UNIT: Map<&str, ScheduleUnit> = Map::new("tranche_unit");
+// keyed by (tranche ID, milestone ID). Removed once fully withdrawn or revoked.
+pub const MILESTONES: Map<(&str, &str), MilestoneState> = Map::new("tranche_milestones");
+
+// LEGACY STATES from before multi-tranche support. Only read by migrations.
+pub const LEGACY_VESTING_TIMESTAMPS: Item<Vec<Timestamp>> = Item::new("ts");
+pub const LEGACY_VESTING_AMOUNTS: Item<Vec<u128>> = Item::new("amounts");
+pub const LEGACY_TOTAL_AMOUNT: Item<u128> = Item::new("ttam");
+pub const LEGACY_UNLOCK_DISTRIBUTION_ADDRESS: Item<Addr> = Item::new("uda");
+pub const LEGACY_WITHDRAWN_UNLOCKED: Item<u128> = Item::new("wu");
+pub const LEGACY_WITHDRAWN_LOCKED: Item<u128> = Item::new("wl");
+// per-tranche schedules from before indexed schedule storage. Only read by migrations.
+pub const LEGACY_TRANCHE_VESTING_TIMESTAMPS: Map<&str, Vec<Timestamp>> = Map::new("tranche_ts");
+pub const LEGACY_TRANCHE_VESTING_AMOUNTS: Map<&str, Vec<u128>> = Map::new("tranche_amounts");
+
+// returns the remaining vesting schedule of a tranche, with the partially withdrawn amount already
+// deducted from the first entry
+pub fn load_schedule(
+    store: &dyn Storage,
+    tranche_id: &str,
+) -> StdResult<(Vec<Timestamp>, Vec<u128>)> {
+    let cursor = SCHEDULE_CURSOR
+        .may_load(store, tranche_id)?
+        .unwrap_or_default();
+    let mut vesting_ts = vec![];
+    let mut vesting_amounts = vec![];
+    for item in VESTING_SCHEDULE
+        .prefix(tranche_id)
+        .range(store, None, None, Order::Ascending)
+    {
+        let (_, entry) = item?;
+        vesting_ts.push(entry.point.position());
+        vesting_amounts.push(entry.amount);
+    }
+    if let Some(first) = vesting_amounts.first_mut() {
+        *first -= cursor.withdrawn_from_first;
+    }
+    Ok((vesting_ts, vesting_amounts))
+}
+
+// replaces the remaining vesting schedule of a tranche. Rewrites every remaining entry, so the
+// withdrawal path advances the cursor instead.
+pub fn save_schedule(
+    store: &mut dyn Storage,
+    tranche_id: &str,
+    vesting_ts: &[Timestamp],
+    vesting_amounts: &[u128],
+) -> StdResult<()> {
+    let cursor = SCHEDULE_CURSOR
+        .may_load(store, tranche_id)?
+        .unwrap_or_default();
+    let unit = SCHEDULE_UNIT
+        .may_load(store, tranche_id)?
+        .unwrap_or_default();
+    for idx in cursor.first..cursor.end {
+        VESTING_SCHEDULE.remove(store, (tranche_id, idx));
+    }
+    let mut cumulative = 0u128;
+    for (idx, (timestamp, amount)) in (cursor.first..).zip(vesting_ts.iter().zip(vesting_amounts)) {
+        cumulative += amount;
+        VESTING_SCHEDULE.save(
+            store,
+            (tranche_id, idx),
+            &VestingEntry {
+                point: unit.point(*timestamp),
+                amount: *amount,
+                cumulative,
+            },
+        )?;
+    }
+    SCHEDULE_CURSOR.save(
+        store,
+        tranche_id,
+        &ScheduleCursor {
+            first: cursor.first,
+            end: cursor.first + vesting_ts.len() as u64,
+            withdrawn_from_first: 0,
+        },
+    )
+}
+
+pub fn validation_policy(store: &dyn Storage) -> StdResult<ValidationPolicy> {
+    Ok(VALIDATION_POLICY.may_load(store)?.unwrap_or_default())
+}
+
+pub fn get_tranche_ids(store: &dyn Storage) -> StdResult<Vec<String>> {
+    TOTAL_AMOUNT
+        .keys(store, None, None, Order::Ascending)
+        .collect()
+}
+
+// sums a per-tranche amount (e.g. `TOTAL_AMOUNT`) over all tranches that vest `DENOM`
+pub fn sum_over_native_tranches(store: &dyn Storage, amounts: &Map<&str, u128>) -> StdResult<u128> {
+    let mut total = 0u128;
+    for item in amounts.range(store, None, None, Order::Ascending) {
+        let (tranche_id, amount) = item?;
+        if !CW20_TOKEN.has(store, &tranche_id) {
+            total = total
+                .checked_add(amount)
+                .ok_or_else(|| StdError::generic_err("tranche amounts overflow"))?;
+        }
+    }
+    Ok(total)
+}
 
 pub const ADMINS: Map<&Addr, EmptyStruct> = Map::new("admins");
 pub const OPS: Map<&Addr, EmptyStruct> = Map::new("ops");
@@ -39,6 +183,168 @@ pub fn get_number_of_ops(store: &dyn Storage) -> usize {
     .count()
 }
 
+// WITHDRAWAL LEDGER, keyed by sequence number
+pub const WITHDRAWAL_COUNT: Item<u64> = Item::new("withdrawal_count");
+pub const WITHDRAWALS: Map<u64, WithdrawalRecord> = Map::new("withdrawals");
+// keyed by (withdrawal kind, sequence number), so the ledger can be listed by kind without scanning it
+pub const WITHDRAWALS_BY_KIND: Map<(&str, u64), EmptyStruct> = Map::new("withdrawals_by_kind");
+
+pub fn record_withdrawal(store: &mut dyn Storage, withdrawal: &WithdrawalRecord) -> StdResult<u64> {
+    let id: u64 = WITHDRAWAL_COUNT.may_load(store)?.unwrap_or_default() + 1;
+    WITHDRAWAL_COUNT.save(store, &id)?;
+    WITHDRAWALS.save(store, id, withdrawal)?;
+    WITHDRAWALS_BY_KIND.save(store, (withdrawal.kind.key(), id), &EmptyStruct {})?;
+    Ok(id)
+}
+
+// PRINCIPAL LEDGER. Tracks where the principal is without asking the chain, so that rewards can be
+// told apart from principal. Stake beyond the delegated principal, e.g. compounded rewards, is not
+// principal.
+pub const DELEGATED_PRINCIPAL: Map<&str, u128> = Map::new("delegated_principal");
+pub const UNBONDING_PRINCIPAL_COUNT: Item<u64> = Item::new("unbonding_principal_count");
+pub const UNBONDING_PRINCIPAL: Map<u64, UnbondingPrincipal> = Map::new("unbonding_principal");
+// in seconds. Must not be shorter than the chain's, or unbonded principal is mistaken for rewards.
+pub const UNBONDING_PERIOD: Item<u64> = Item::new("unbonding_period");
+
+pub fn unbonding_period(store: &dyn Storage) -> StdResult<u64> {
+    Ok(UNBONDING_PERIOD
+        .may_load(store)?
+        .unwrap_or(DEFAULT_UNBONDING_PERIOD))
+}
+
+pub fn record_delegation(store: &mut dyn Storage, validator: &str, amount: u128) -> StdResult<()> {
+    DELEGATED_PRINCIPAL.update(store, validator, |old| -> StdResult<u128> {
+        Ok(old.unwrap_or_default() + amount)
+    })?;
+    Ok(())
+}
+
+// takes up to `amount` of the principal delegated to `validator` off the ledger. Returns how much
+// of it was principal.
+fn take_delegated_principal(
+    store: &mut dyn Storage,
+    validator: &str,
+    amount: u128,
+) -> StdResult<u128> {
+    let delegated = DELEGATED_PRINCIPAL
+        .may_load(store, validator)?
+        .unwrap_or_default();
+    let principal = std::cmp::min(amount, delegated);
+    if principal == delegated {
+        DELEGATED_PRINCIPAL.remove(store, validator);
+    } else {
+        DELEGATED_PRINCIPAL.save(store, validator, &(delegated - principal))?;
+    }
+    Ok(principal)
+}
+
+// undelegations count as principal up to the principal delegated to the validator, the rest is
+// rewards. Entries of completed unbondings are pruned here.
+pub fn record_undelegation(
+    store: &mut dyn Storage,
+    validator: &str,
+    amount: u128,
+    now: Timestamp,
+) -> StdResult<()> {
+    let completed = UNBONDING_PRINCIPAL
+        .range(store, None, None, Order::Ascending)
+        .filter_map(|item| match item {
+            Ok((id, entry)) if entry.completion_time <= now => Some(Ok(id)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<StdResult<Vec<u64>>>()?;
+    for id in completed {
+        UNBONDING_PRINCIPAL.remove(store, id);
+    }
+    let principal = take_delegated_principal(store, validator, amount)?;
+    // the rest was staked beyond principal, i.e. compounded rewards
+    if principal < amount {
+        if let Some(compounded) = COMPOUNDED_REWARDS.may_load(store)? {
+            COMPOUNDED_REWARDS.save(store, &compounded.saturating_sub(amount - principal))?;
+        }
+    }
+    if principal == 0 {
+        return Ok(());
+    }
+    let completion_time = now.plus_seconds(unbonding_period(store)?);
+    record_unbonding(store, principal, completion_time)
+}
+
+pub fn record_unbonding(
+    store: &mut dyn Storage,
+    amount: u128,
+    completion_time: Timestamp,
+) -> StdResult<()> {
+    let id = UNBONDING_PRINCIPAL_COUNT
+        .may_load(store)?
+        .unwrap_or_default()
+        + 1;
+    UNBONDING_PRINCIPAL_COUNT.save(store, &id)?;
+    UNBONDING_PRINCIPAL.save(
+        store,
+        id,
+        &UnbondingPrincipal {
+            amount: amount.into(),
+            completion_time,
+        },
+    )
+}
+
+pub fn record_redelegation(
+    store: &mut dyn Storage,
+    src_validator: &str,
+    dst_validator: &str,
+    amount: u128,
+) -> StdResult<()> {
+    let principal = take_delegated_principal(store, src_validator, amount)?;
+    if principal > 0 {
+        record_delegation(store, dst_validator, principal)?;
+    }
+    Ok(())
+}
+
+pub fn total_delegated_principal(store: &dyn Storage) -> StdResult<u128> {
+    DELEGATED_PRINCIPAL
+        .range(store, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, amount)| amount))
+        .sum()
+}
+
+// principal whose unbonding has not completed by `now`
+pub fn total_unbonding_principal(store: &dyn Storage, now: Timestamp) -> StdResult<u128> {
+    UNBONDING_PRINCIPAL
+        .range(store, None, None, Order::Ascending)
+        .filter(|item| !matches!(item, Ok((_, entry)) if entry.completion_time <= now))
+        .map(|item| item.map(|(_, entry)| entry.amount.u128()))
+        .sum()
+}
+
+// PENDING WITHDRAWALS, keyed by sequence number. Paid out in order.
+pub const PENDING_WITHDRAWAL_COUNT: Item<u64> = Item::new("pending_withdrawal_count");
+pub const PENDING_WITHDRAWALS: Map<u64, PendingWithdrawal> = Map::new("pending_withdrawals");
+
+pub fn queue_pending_withdrawal(
+    store: &mut dyn Storage,
+    withdrawal: &PendingWithdrawal,
+) -> StdResult<u64> {
+    let id: u64 = PENDING_WITHDRAWAL_COUNT
+        .may_load(store)?
+        .unwrap_or_default()
+        + 1;
+    PENDING_WITHDRAWAL_COUNT.save(store, &id)?;
+    PENDING_WITHDRAWALS.save(store, id, withdrawal)?;
+    Ok(id)
+}
+
+// the part of the contract's `DENOM` balance that is reserved for pending withdrawals
+pub fn total_pending_withdrawals(store: &dyn Storage) -> StdResult<u128> {
+    PENDING_WITHDRAWALS
+        .range(store, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, withdrawal)| withdrawal.amount.u128()))
+        .sum()
+}
+
 // ADMIN STATES
 pub const MAX_VOTING_PERIOD: Item<Duration> = Item::new("max_voting_period");
 pub const ADMIN_VOTING_THRESHOLD: Item<Threshold> = Item::new("threshold");
@@ -55,11 +361,16 @@ pub fn next_proposal_id(store: &mut dyn Storage) -> StdResult<u64> {
 
 #[cfg(test)]
 mod tests {
-    use cosmwasm_std::{testing::mock_dependencies, Addr};
+    use cosmwasm_std::{testing::mock_dependencies, Addr, Timestamp, Uint128};
 
     use crate::{
-        data_structure::EmptyStruct,
-        state::{get_number_of_admins, get_number_of_ops, ADMINS, OPS},
+        data_structure::{EmptyStruct, UnbondingPrincipal},
+        state::{
+            get_number_of_admins, get_number_of_ops, record_delegation, record_redelegation,
+            record_undelegation, take_delegated_principal, total_delegated_principal,
+            total_unbonding_principal, unbonding_period, ADMINS, COMPOUNDED_REWARDS,
+            DELEGATED_PRINCIPAL, OPS, UNBONDING_PRINCIPAL,
+        },
     };
 
     #[test]
@@ -105,4 +416,124 @@ mod tests {
         .unwrap();
         assert_eq!(2, get_number_of_ops(deps.as_ref().storage));
     }
+
+    #[test]
+    fn test_record_delegation() {
+        let mut deps = mock_dependencies();
+        record_delegation(deps.as_mut().storage, "val1", 100).unwrap();
+        record_delegation(deps.as_mut().storage, "val1", 50).unwrap();
+        record_delegation(deps.as_mut().storage, "val2", 30).unwrap();
+        assert_eq!(
+            150,
+            DELEGATED_PRINCIPAL
+                .load(deps.as_ref().storage, "val1")
+                .unwrap()
+        );
+        assert_eq!(
+            180,
+            total_delegated_principal(deps.as_ref().storage).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_take_delegated_principal() {
+        let mut deps = mock_dependencies();
+        record_delegation(deps.as_mut().storage, "val1", 100).unwrap();
+        assert_eq!(
+            40,
+            take_delegated_principal(deps.as_mut().storage, "val1", 40).unwrap()
+        );
+        assert_eq!(
+            60,
+            DELEGATED_PRINCIPAL
+                .load(deps.as_ref().storage, "val1")
+                .unwrap()
+        );
+        // anything beyond the principal is not principal
+        assert_eq!(
+            60,
+            take_delegated_principal(deps.as_mut().storage, "val1", 70).unwrap()
+        );
+        assert!(!DELEGATED_PRINCIPAL.has(deps.as_ref().storage, "val1"));
+        assert_eq!(
+            0,
+            take_delegated_principal(deps.as_mut().storage, "val2", 10).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_record_undelegation() {
+        let mut deps = mock_dependencies();
+        let now = Timestamp::from_seconds(1000);
+        let period = unbonding_period(deps.as_ref().storage).unwrap();
+        record_delegation(deps.as_mut().storage, "val1", 100).unwrap();
+        record_undelegation(deps.as_mut().storage, "val1", 30, now).unwrap();
+        assert_eq!(
+            70,
+            total_delegated_principal(deps.as_ref().storage).unwrap()
+        );
+        assert_eq!(
+            UnbondingPrincipal {
+                amount: Uint128::new(30),
+                completion_time: now.plus_seconds(period),
+            },
+            UNBONDING_PRINCIPAL.load(deps.as_ref().storage, 1).unwrap()
+        );
+        // undelegated rewards are not unbonding principal, and are no longer compounded
+        COMPOUNDED_REWARDS.save(deps.as_mut().storage, &50).unwrap();
+        record_undelegation(deps.as_mut().storage, "val1", 80, now.plus_seconds(10)).unwrap();
+        assert_eq!(0, total_delegated_principal(deps.as_ref().storage).unwrap());
+        assert_eq!(40, COMPOUNDED_REWARDS.load(deps.as_ref().storage).unwrap());
+        assert_eq!(
+            100,
+            total_unbonding_principal(deps.as_ref().storage, now).unwrap()
+        );
+        assert_eq!(
+            70,
+            total_unbonding_principal(deps.as_ref().storage, now.plus_seconds(period)).unwrap()
+        );
+
+        // completed entries are pruned on the next undelegation
+        record_delegation(deps.as_mut().storage, "val1", 10).unwrap();
+        record_undelegation(deps.as_mut().storage, "val1", 10, now.plus_seconds(period)).unwrap();
+        assert!(!UNBONDING_PRINCIPAL.has(deps.as_ref().storage, 1));
+        assert!(UNBONDING_PRINCIPAL.has(deps.as_ref().storage, 2));
+        assert!(UNBONDING_PRINCIPAL.has(deps.as_ref().storage, 3));
+        assert_eq!(
+            80,
+            total_unbonding_principal(deps.as_ref().storage, now.plus_seconds(period)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_record_redelegation() {
+        let mut deps = mock_dependencies();
+        record_delegation(deps.as_mut().storage, "val1", 100).unwrap();
+        record_redelegation(deps.as_mut().storage, "val1", "val2", 40).unwrap();
+        assert_eq!(
+            60,
+            DELEGATED_PRINCIPAL
+                .load(deps.as_ref().storage, "val1")
+                .unwrap()
+        );
+        assert_eq!(
+            40,
+            DELEGATED_PRINCIPAL
+                .load(deps.as_ref().storage, "val2")
+                .unwrap()
+        );
+        // only the principal moves along
+        record_redelegation(deps.as_mut().storage, "val1", "val2", 90).unwrap();
+        assert!(!DELEGATED_PRINCIPAL.has(deps.as_ref().storage, "val1"));
+        assert_eq!(
+            100,
+            DELEGATED_PRINCIPAL
+                .load(deps.as_ref().storage, "val2")
+                .unwrap()
+        );
+        assert_eq!(
+            100,
+            total_delegated_principal(deps.as_ref().storage).unwrap()
+        );
+    }
 }
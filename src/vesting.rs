@@ -1,108 +1,696 @@
-use cosmwasm_std::{coins, BankMsg, Response, StdResult, Storage, Timestamp};
+use cosmwasm_std::{
+    coins, to_json_binary, Addr, BankMsg, CosmosMsg, Env, Order, Response, StdResult, Storage,
+    Timestamp, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
 
 use crate::{
-    state::{DENOM, UNLOCK_DISTRIBUTION_ADDRESS, VESTING_AMOUNTS, VESTING_TIMESTAMPS},
+    data_structure::{
+        raw_amounts, LinearVestingState, MilestoneState, ScheduleAmendment, ScheduleUnit,
+        WeightedRecipient,
+    },
+    state::{
+        load_schedule, save_schedule, validation_policy, CW20_TOKEN, DENOM, LINEAR_VESTING,
+        MILESTONES, SCHEDULE_CURSOR, SCHEDULE_UNIT, UNLOCK_DISTRIBUTION_ADDRESS,
+        UNLOCK_DISTRIBUTION_SPLIT, VESTING_SCHEDULE,
+    },
     ContractError,
 };
 
+// reached milestones are drawn on before the schedule
 pub fn collect_vested(
     storage: &mut dyn Storage,
+    tranche_id: &str,
+    now: Timestamp,
+    requested_amount: u128,
+) -> Result<u128, ContractError> {
+    let from_milestones = collect_reached_milestones(storage, tranche_id, requested_amount)?;
+    let from_schedule =
+        collect_scheduled(storage, tranche_id, now, requested_amount - from_milestones)?;
+    Ok(from_milestones + from_schedule)
+}
+
+// withdraws from the front of the schedule. Only the consumed entries and the one that is partially
+// withdrawn from are touched.
+fn collect_scheduled(
+    storage: &mut dyn Storage,
+    tranche_id: &str,
     now: Timestamp,
     requested_amount: u128,
 ) -> Result<u128, ContractError> {
-    let vesting_ts = VESTING_TIMESTAMPS.load(storage)?;
-    let vesting_amounts = VESTING_AMOUNTS.load(storage)?;
+    let mut cursor = SCHEDULE_CURSOR
+        .may_load(storage, tranche_id)?
+        .unwrap_or_default();
+    let linear_vesting = LINEAR_VESTING.may_load(storage, tranche_id)?;
     let mut vested_amount = 0u128;
-    let mut amount_to_subtract = 0u128;
-    let mut remaining_first_idx: usize = 0;
-    for (i, (ts, amount)) in vesting_ts.iter().zip(vesting_amounts.iter()).enumerate() {
-        if *ts > now {
+    let mut consumed = 0u64;
+    let mut partially_collected = 0u128;
+    let mut prev_ts: Option<Timestamp> = None;
+    while cursor.first + consumed < cursor.end && vested_amount < requested_amount {
+        let entry = VESTING_SCHEDULE.load(storage, (tranche_id, cursor.first + consumed))?;
+        let remaining = if consumed == 0 {
+            entry.amount - cursor.withdrawn_from_first
+        } else {
+            entry.amount
+        };
+        let available = available_amount(
+            remaining,
+            entry.point.position(),
+            prev_ts,
+            linear_vesting.as_ref(),
+            now,
+        );
+        if available == 0 {
             break;
         }
-        vested_amount += *amount;
-        if vested_amount >= requested_amount {
-            amount_to_subtract = *amount + requested_amount - vested_amount;
-            if vested_amount == requested_amount {
-                remaining_first_idx += 1;
-                amount_to_subtract = 0;
-            }
-            vested_amount = requested_amount;
+        let to_collect = std::cmp::min(available, requested_amount - vested_amount);
+        vested_amount += to_collect;
+        if to_collect < remaining {
+            partially_collected = to_collect;
             break;
         }
-        remaining_first_idx = i + 1;
+        consumed += 1;
+        prev_ts = Some(entry.point.position());
     }
     if vested_amount < requested_amount {
         return Err(ContractError::NoSufficientUnlockedTokens {});
     }
-    if remaining_first_idx >= vesting_amounts.len() {
-        VESTING_AMOUNTS.save(storage, &vec![])?;
-        VESTING_TIMESTAMPS.save(storage, &vec![])?;
-    } else {
-        let mut remaining_amounts = vesting_amounts[remaining_first_idx..].to_vec();
-        if amount_to_subtract > 0 {
-            remaining_amounts[0] -= amount_to_subtract;
-        }
-        VESTING_AMOUNTS.save(storage, &remaining_amounts)?;
-        let remaining_ts = vesting_ts[remaining_first_idx..].to_vec();
-        VESTING_TIMESTAMPS.save(storage, &remaining_ts)?;
+    for idx in cursor.first..cursor.first + consumed {
+        VESTING_SCHEDULE.remove(storage, (tranche_id, idx));
+    }
+    if consumed > 0 {
+        cursor.first += consumed;
+        cursor.withdrawn_from_first = 0;
     }
+    cursor.withdrawn_from_first += partially_collected;
+    if let (Some(mut linear), Some(last_consumed_ts)) = (linear_vesting, prev_ts) {
+        let segment_amount = match VESTING_SCHEDULE.may_load(storage, (tranche_id, cursor.first))? {
+            Some(entry) => Uint128::new(entry.amount),
+            None => Uint128::zero(),
+        };
+        linear.restart_segment(last_consumed_ts, segment_amount);
+        LINEAR_VESTING.save(storage, tranche_id, &linear)?;
+    }
+    SCHEDULE_CURSOR.save(storage, tranche_id, &cursor)?;
 
     Ok(vested_amount)
 }
 
-pub fn total_vested_amount(storage: &dyn Storage, now: Timestamp) -> StdResult<u128> {
-    let vesting_timestamps = VESTING_TIMESTAMPS.load(storage)?;
-    let vesting_amounts = VESTING_AMOUNTS.load(storage)?;
-    let mut total_vested_amount = 0u128;
-    for i in 0..vesting_timestamps.len() {
-        if vesting_timestamps[i] <= now {
-            total_vested_amount += vesting_amounts[i];
+pub fn total_vested_amount(
+    storage: &dyn Storage,
+    tranche_id: &str,
+    now: Timestamp,
+) -> StdResult<u128> {
+    let cursor = SCHEDULE_CURSOR
+        .may_load(storage, tranche_id)?
+        .unwrap_or_default();
+    let linear_vesting = LINEAR_VESTING.may_load(storage, tranche_id)?;
+    let mut scheduled = 0u128;
+    let mut prev_ts: Option<Timestamp> = None;
+    // nothing after the first entry that is not available yet can be available either
+    for item in VESTING_SCHEDULE
+        .prefix(tranche_id)
+        .range(storage, None, None, Order::Ascending)
+    {
+        let (idx, entry) = item?;
+        let remaining = if idx == cursor.first {
+            entry.amount - cursor.withdrawn_from_first
         } else {
+            entry.amount
+        };
+        let available = available_amount(
+            remaining,
+            entry.point.position(),
+            prev_ts,
+            linear_vesting.as_ref(),
+            now,
+        );
+        if available == 0 {
+            break;
+        }
+        scheduled += available;
+        prev_ts = Some(entry.point.position());
+    }
+    let from_milestones: u128 = reached_milestones(storage, tranche_id)?
+        .iter()
+        .map(|(_, milestone)| milestone.amount.u128())
+        .sum();
+    Ok(scheduled + from_milestones)
+}
+
+fn milestones(storage: &dyn Storage, tranche_id: &str) -> StdResult<Vec<(String, MilestoneState)>> {
+    MILESTONES
+        .prefix(tranche_id)
+        .range(storage, None, None, Order::Ascending)
+        .collect()
+}
+
+fn reached_milestones(
+    storage: &dyn Storage,
+    tranche_id: &str,
+) -> StdResult<Vec<(String, MilestoneState)>> {
+    Ok(milestones(storage, tranche_id)?
+        .into_iter()
+        .filter(|(_, milestone)| milestone.reached)
+        .collect())
+}
+
+// draws up to `requested_amount` from reached milestones. Returns the drawn amount.
+fn collect_reached_milestones(
+    storage: &mut dyn Storage,
+    tranche_id: &str,
+    requested_amount: u128,
+) -> StdResult<u128> {
+    let mut collected = 0u128;
+    for (milestone_id, mut milestone) in reached_milestones(storage, tranche_id)? {
+        if collected == requested_amount {
             break;
         }
+        let to_collect = std::cmp::min(milestone.amount.u128(), requested_amount - collected);
+        collected += to_collect;
+        milestone.amount -= Uint128::new(to_collect);
+        if milestone.amount.is_zero() {
+            MILESTONES.remove(storage, (tranche_id, &milestone_id));
+        } else {
+            MILESTONES.save(storage, (tranche_id, &milestone_id), &milestone)?;
+        }
+    }
+    Ok(collected)
+}
+
+// everything the tranche has yet to pay out, i.e. the remaining schedule and all milestones
+pub fn remaining_amount(storage: &dyn Storage, tranche_id: &str) -> StdResult<u128> {
+    let scheduled: u128 = load_schedule(storage, tranche_id)?.1.iter().sum();
+    let from_milestones: u128 = milestones(storage, tranche_id)?
+        .iter()
+        .map(|(_, milestone)| milestone.amount.u128())
+        .sum();
+    Ok(scheduled + from_milestones)
+}
+
+// revokes every milestone that lapsed before `now`. Returns the revoked amount.
+pub fn clawback_lapsed_milestones(
+    storage: &mut dyn Storage,
+    tranche_id: &str,
+    now: Timestamp,
+) -> StdResult<u128> {
+    let mut revoked = 0u128;
+    for (milestone_id, milestone) in milestones(storage, tranche_id)? {
+        if milestone.is_lapsed(now) {
+            revoked += milestone.amount.u128();
+            MILESTONES.remove(storage, (tranche_id, &milestone_id));
+        }
+    }
+    Ok(revoked)
+}
+
+// revokes all milestones, reached or not. Returns the revoked amount.
+pub fn remove_milestones(storage: &mut dyn Storage, tranche_id: &str) -> StdResult<u128> {
+    let mut revoked = 0u128;
+    for (milestone_id, milestone) in milestones(storage, tranche_id)? {
+        revoked += milestone.amount.u128();
+        MILESTONES.remove(storage, (tranche_id, &milestone_id));
+    }
+    Ok(revoked)
+}
+
+// returns the first vesting timestamp after `now`, and how much becomes withdrawable between now and
+// then
+pub fn next_unlock(
+    storage: &dyn Storage,
+    tranche_id: &str,
+    now: Timestamp,
+) -> StdResult<Option<(Timestamp, u128)>> {
+    let (vesting_ts, vesting_amounts) = load_schedule(storage, tranche_id)?;
+    let linear_vesting = LINEAR_VESTING.may_load(storage, tranche_id)?;
+    let idx = match vesting_ts.iter().position(|ts| *ts > now) {
+        Some(idx) => idx,
+        None => return Ok(None),
+    };
+    let mut available_now =
+        available_amounts(&vesting_ts, &vesting_amounts, linear_vesting.as_ref(), now);
+    let mut available_then = available_amounts(
+        &vesting_ts,
+        &vesting_amounts,
+        linear_vesting.as_ref(),
+        vesting_ts[idx],
+    );
+    available_now.truncate(idx + 1);
+    available_then.truncate(idx + 1);
+    Ok(Some((
+        vesting_ts[idx],
+        available_then.iter().sum::<u128>() - available_now.iter().sum::<u128>(),
+    )))
+}
+
+// revokes everything that would vest after `cutoff`, which must not be in the past. Tokens that
+// have vested (or will have vested by the cutoff) stay withdrawable. Returns the revoked amount.
+pub fn clawback_after(
+    storage: &mut dyn Storage,
+    tranche_id: &str,
+    now: Timestamp,
+    cutoff: Timestamp,
+) -> Result<u128, ContractError> {
+    if cutoff < now {
+        return Err(ContractError::InvalidClawback(
+            "cutoff cannot be in the past".to_string(),
+        ));
+    }
+    let (mut vesting_ts, mut vesting_amounts) = load_schedule(storage, tranche_id)?;
+    let mut linear_vesting = LINEAR_VESTING.may_load(storage, tranche_id)?;
+    let revoked = truncate_after(
+        &mut vesting_ts,
+        &mut vesting_amounts,
+        linear_vesting.as_mut(),
+        cutoff,
+    );
+    if let Some(linear) = linear_vesting {
+        LINEAR_VESTING.save(storage, tranche_id, &linear)?;
+    }
+    save_schedule(storage, tranche_id, &vesting_ts, &vesting_amounts)?;
+
+    Ok(revoked)
+}
+
+// replaces everything that would vest after the amendment's cutoff with the amended entries.
+// Tokens that have vested (or will have vested by the cutoff) are left untouched. For linearly
+// vesting tranches the first amended entry streams from the cutoff. Returns the replaced amount.
+pub fn amend_schedule(
+    storage: &mut dyn Storage,
+    tranche_id: &str,
+    now: Timestamp,
+    amendment: &ScheduleAmendment,
+) -> Result<u128, ContractError> {
+    if amendment.cutoff < now {
+        return Err(ContractError::InvalidAmendment(
+            "cutoff cannot be in the past".to_string(),
+        ));
+    }
+    let (mut vesting_ts, mut vesting_amounts) = load_schedule(storage, tranche_id)?;
+    let mut linear_vesting = LINEAR_VESTING.may_load(storage, tranche_id)?;
+    let replaced = truncate_after(
+        &mut vesting_ts,
+        &mut vesting_amounts,
+        linear_vesting.as_mut(),
+        amendment.cutoff,
+    );
+    let amended_amounts = raw_amounts(&amendment.vesting_amounts);
+    let amended_total: u128 = amended_amounts.iter().sum();
+    if amended_total + amendment.clawed_back_amount.u128()
+        != replaced + amendment.added_amount.u128()
+    {
+        return Err(ContractError::InvalidAmendment(format!(
+            "amended schedule must vest the {} replaced tokens adjusted by the added and clawed back amounts, got {}",
+            replaced, amended_total
+        )));
+    }
+    if let Some(linear) = linear_vesting.as_mut() {
+        match vesting_ts.last() {
+            None => linear.restart_segment(amendment.cutoff, amendment.vesting_amounts[0]),
+            Some(last) if *last != amendment.cutoff => {
+                return Err(ContractError::InvalidAmendment(
+                    "cutoff must fall within the linear vesting schedule".to_string(),
+                ));
+            }
+            _ => {}
+        }
+    }
+    vesting_ts.extend_from_slice(&amendment.vesting_timestamps);
+    vesting_amounts.extend_from_slice(&amended_amounts);
+    validate_resulting_layout(storage, tranche_id, &vesting_ts)?;
+    if let Some(linear) = linear_vesting {
+        LINEAR_VESTING.save(storage, tranche_id, &linear)?;
+    }
+    save_schedule(storage, tranche_id, &vesting_ts, &vesting_amounts)?;
+
+    Ok(replaced)
+}
+
+// merges additional vesting entries into the remaining schedule in timestamp order. Entries that
+// share a timestamp with an existing entry are added to it. A linearly vesting tranche can only be
+// extended after its last timestamp, so that the existing entries keep streaming as before.
+pub fn merge_schedule(
+    storage: &mut dyn Storage,
+    tranche_id: &str,
+    now: Timestamp,
+    new_ts: &[Timestamp],
+    new_amounts: &[u128],
+) -> Result<(), ContractError> {
+    let (mut vesting_ts, mut vesting_amounts) = load_schedule(storage, tranche_id)?;
+    if let Some(mut linear) = LINEAR_VESTING.may_load(storage, tranche_id)? {
+        match vesting_ts.last_mut() {
+            // the previous schedule has been fully withdrawn, so start a new one
+            None => linear.restart_segment(now, Uint128::new(new_amounts[0])),
+            Some(last) if *last >= new_ts[0] => {
+                return Err(ContractError::InvalidTranche(
+                    "linearly vesting tranches can only be extended after their last vesting timestamp"
+                        .to_string(),
+                ));
+            }
+            // everything has fully vested already and stays so, but the new entries must
+            // not stream from a point in the past
+            Some(last) if *last < now => *last = now,
+            _ => {}
+        }
+        vesting_ts.extend_from_slice(new_ts);
+        vesting_amounts.extend_from_slice(new_amounts);
+        validate_resulting_layout(storage, tranche_id, &vesting_ts)?;
+        LINEAR_VESTING.save(storage, tranche_id, &linear)?;
+        save_schedule(storage, tranche_id, &vesting_ts, &vesting_amounts)?;
+        return Ok(());
+    }
+
+    let mut merged_ts = Vec::with_capacity(vesting_ts.len() + new_ts.len());
+    let mut merged_amounts = Vec::with_capacity(vesting_amounts.len() + new_amounts.len());
+    let (mut i, mut j) = (0, 0);
+    while i < vesting_ts.len() || j < new_ts.len() {
+        if j == new_ts.len() || (i < vesting_ts.len() && vesting_ts[i] < new_ts[j]) {
+            merged_ts.push(vesting_ts[i]);
+            merged_amounts.push(vesting_amounts[i]);
+            i += 1;
+        } else if i == vesting_ts.len() || new_ts[j] < vesting_ts[i] {
+            merged_ts.push(new_ts[j]);
+            merged_amounts.push(new_amounts[j]);
+            j += 1;
+        } else {
+            merged_ts.push(vesting_ts[i]);
+            merged_amounts.push(vesting_amounts[i] + new_amounts[j]);
+            i += 1;
+            j += 1;
+        }
+    }
+    validate_resulting_layout(storage, tranche_id, &merged_ts)?;
+    save_schedule(storage, tranche_id, &merged_ts, &merged_amounts)?;
+
+    Ok(())
+}
+
+// applies the validation policy to the schedule that a change would leave behind
+fn validate_resulting_layout(
+    storage: &dyn Storage,
+    tranche_id: &str,
+    vesting_ts: &[Timestamp],
+) -> Result<(), ContractError> {
+    validation_policy(storage)?.validate_layout(vesting_ts, &schedule_unit(storage, tranche_id)?)
+}
+
+// drops everything that would vest after `cutoff` from the schedule. A linearly vesting entry that
+// is streaming at the cutoff is cut short to what it streams until then. Returns the dropped amount.
+fn truncate_after(
+    vesting_ts: &mut Vec<Timestamp>,
+    vesting_amounts: &mut Vec<u128>,
+    linear_vesting: Option<&mut LinearVestingState>,
+    cutoff: Timestamp,
+) -> u128 {
+    let remaining_total: u128 = vesting_amounts.iter().sum();
+    let mut keep = vesting_ts.iter().take_while(|ts| **ts <= cutoff).count();
+    if let Some(linear) = linear_vesting {
+        if matches!(linear.cliff_position(), Some(cliff) if cutoff < cliff) {
+            // nothing vests before the cliff
+            keep = 0;
+        } else if keep < vesting_ts.len() {
+            // the entry at `keep` is streaming at the cutoff. Keep whatever streams until then.
+            let (start, original_amount) = if keep == 0 {
+                (
+                    linear.segment_start_position(),
+                    linear.segment_amount.u128(),
+                )
+            } else {
+                (vesting_ts[keep - 1], vesting_amounts[keep])
+            };
+            let withdrawn = original_amount - vesting_amounts[keep];
+            let streamed = streamed_amount(original_amount, start, vesting_ts[keep], cutoff);
+            if cutoff > start && streamed > withdrawn {
+                vesting_ts[keep] = cutoff;
+                vesting_amounts[keep] = streamed - withdrawn;
+                if keep == 0 {
+                    linear.segment_amount = Uint128::new(streamed);
+                }
+                keep += 1;
+            }
+        }
+    }
+    vesting_ts.truncate(keep);
+    vesting_amounts.truncate(keep);
+
+    remaining_total - vesting_amounts.iter().sum::<u128>()
+}
+
+// revokes `amount` of unvested tokens, starting from the end of the schedule. A linearly vesting
+// entry that is only partially revoked keeps its rate and stops streaming earlier instead.
+pub fn clawback_amount(
+    storage: &mut dyn Storage,
+    tranche_id: &str,
+    now: Timestamp,
+    amount: u128,
+) -> Result<u128, ContractError> {
+    let (mut vesting_ts, mut vesting_amounts) = load_schedule(storage, tranche_id)?;
+    let mut linear_vesting = LINEAR_VESTING.may_load(storage, tranche_id)?;
+    let available_amounts =
+        available_amounts(&vesting_ts, &vesting_amounts, linear_vesting.as_ref(), now);
+    let unvested: u128 =
+        vesting_amounts.iter().sum::<u128>() - available_amounts.iter().sum::<u128>();
+    if amount > unvested {
+        return Err(ContractError::InvalidClawback(
+            "clawback amount exceeds unvested amount".to_string(),
+        ));
+    }
+
+    let mut to_revoke = amount;
+    while to_revoke > 0 {
+        let last = vesting_amounts.len() - 1;
+        if to_revoke >= vesting_amounts[last] && available_amounts[last] == 0 {
+            to_revoke -= vesting_amounts[last];
+            vesting_amounts.pop();
+            vesting_ts.pop();
+            continue;
+        }
+        vesting_amounts[last] -= to_revoke;
+        if let Some(linear) = linear_vesting.as_mut() {
+            let (start, original_amount) = if last == 0 {
+                (
+                    linear.segment_start_position(),
+                    linear.segment_amount.u128(),
+                )
+            } else {
+                (vesting_ts[last - 1], vesting_amounts[last] + to_revoke)
+            };
+            let new_original_amount = original_amount - to_revoke;
+            let duration = vesting_ts[last].nanos() - start.nanos();
+            vesting_ts[last] = start.plus_nanos(
+                Uint128::from(duration)
+                    .multiply_ratio(new_original_amount, original_amount)
+                    .u128() as u64,
+            );
+            if last == 0 {
+                linear.segment_amount = Uint128::new(new_original_amount);
+            }
+        }
+        to_revoke = 0;
+    }
+    if let Some(linear) = linear_vesting {
+        LINEAR_VESTING.save(storage, tranche_id, &linear)?;
+    }
+    save_schedule(storage, tranche_id, &vesting_ts, &vesting_amounts)?;
+
+    Ok(amount)
+}
+
+// returns, for each remaining vesting entry, how much of it can be withdrawn at `now`
+fn available_amounts(
+    vesting_ts: &[Timestamp],
+    vesting_amounts: &[u128],
+    linear_vesting: Option<&LinearVestingState>,
+    now: Timestamp,
+) -> Vec<u128> {
+    vesting_ts
+        .iter()
+        .zip(vesting_amounts.iter())
+        .enumerate()
+        .map(|(i, (ts, amount))| {
+            let prev_ts = if i == 0 {
+                None
+            } else {
+                Some(vesting_ts[i - 1])
+            };
+            available_amount(*amount, *ts, prev_ts, linear_vesting, now)
+        })
+        .collect()
+}
+
+// returns how much of a remaining vesting entry can be withdrawn at `now`. `prev_ts` is the
+// timestamp of the entry before it, or `None` for the first remaining entry.
+fn available_amount(
+    remaining: u128,
+    ts: Timestamp,
+    prev_ts: Option<Timestamp>,
+    linear_vesting: Option<&LinearVestingState>,
+    now: Timestamp,
+) -> u128 {
+    let linear = match linear_vesting {
+        Some(linear) => linear,
+        None => return if ts <= now { remaining } else { 0 },
+    };
+    if matches!(linear.cliff_position(), Some(cliff) if now < cliff) {
+        return 0;
+    }
+    match prev_ts {
+        Some(prev_ts) => streamed_amount(remaining, prev_ts, ts, now),
+        None => {
+            // part of the first remaining amount may have been withdrawn already
+            let segment_amount = linear.segment_amount.u128();
+            let streamed =
+                streamed_amount(segment_amount, linear.segment_start_position(), ts, now);
+            let withdrawn = segment_amount.saturating_sub(remaining);
+            streamed.saturating_sub(withdrawn)
+        }
+    }
+}
+
+fn streamed_amount(amount: u128, start: Timestamp, end: Timestamp, now: Timestamp) -> u128 {
+    if now >= end {
+        return amount;
+    }
+    if now <= start {
+        return 0;
     }
-    Ok(total_vested_amount)
+    Uint128::from(amount)
+        .multiply_ratio(now.nanos() - start.nanos(), end.nanos() - start.nanos())
+        .u128()
 }
 
 pub fn distribute_vested(
     storage: &dyn Storage,
+    tranche_id: &str,
     amount: u128,
     response: Response,
 ) -> Result<Response, ContractError> {
+    let mut response = response;
+    for (addr, share) in unlocked_payouts(storage, tranche_id, amount)? {
+        response = response.add_message(transfer_msg(storage, tranche_id, &addr, share)?);
+    }
+    Ok(response)
+}
+
+// who receives how much when `amount` unlocked tokens of a tranche are distributed. Recipients
+// whose share rounds down to zero are left out.
+pub fn unlocked_payouts(
+    storage: &dyn Storage,
+    tranche_id: &str,
+    amount: u128,
+) -> StdResult<Vec<(Addr, u128)>> {
     if amount == 0 {
-        return Ok(response);
+        return Ok(vec![]);
+    }
+    match UNLOCK_DISTRIBUTION_SPLIT.may_load(storage, tranche_id)? {
+        Some(recipients) => Ok(split_by_weight(amount, &recipients)
+            .into_iter()
+            .filter(|(_, share)| *share > 0)
+            .collect()),
+        None => Ok(vec![(
+            UNLOCK_DISTRIBUTION_ADDRESS.load(storage, tranche_id)?,
+            amount,
+        )]),
+    }
+}
+
+// the native denom, or the token's contract address for CW20 tranches
+pub fn tranche_denom(storage: &dyn Storage, tranche_id: &str) -> StdResult<String> {
+    match CW20_TOKEN.may_load(storage, tranche_id)? {
+        Some(token) => Ok(token.to_string()),
+        None => DENOM.load(storage),
+    }
+}
+
+pub fn schedule_unit(storage: &dyn Storage, tranche_id: &str) -> StdResult<ScheduleUnit> {
+    Ok(SCHEDULE_UNIT
+        .may_load(storage, tranche_id)?
+        .unwrap_or_default())
+}
+
+// the point of the tranche's schedule that the current block has reached, i.e. the `now` to pass
+// to the functions above
+pub fn schedule_now(storage: &dyn Storage, tranche_id: &str, env: &Env) -> StdResult<Timestamp> {
+    Ok(schedule_unit(storage, tranche_id)?.now(env))
+}
+
+// splits `amount` pro-rata by weight, rounding down. The rounding remainder goes to the first
+// recipient so that the shares always add up to `amount`.
+pub fn split_by_weight(amount: u128, recipients: &[WeightedRecipient]) -> Vec<(Addr, u128)> {
+    let total_weight: u128 = recipients.iter().map(|r| r.weight as u128).sum();
+    let mut shares: Vec<(Addr, u128)> = recipients
+        .iter()
+        .map(|r| {
+            let share = Uint128::from(amount)
+                .multiply_ratio(r.weight as u128, total_weight)
+                .u128();
+            (r.address.clone(), share)
+        })
+        .collect();
+    let distributed: u128 = shares.iter().map(|(_, share)| share).sum();
+    if let Some((_, first)) = shares.first_mut() {
+        *first += amount - distributed;
+    }
+    shares
+}
+
+// pays out `amount` of the token a tranche vests, which is either `DENOM` or a CW20 token
+pub fn transfer_msg(
+    storage: &dyn Storage,
+    tranche_id: &str,
+    recipient: &Addr,
+    amount: u128,
+) -> Result<CosmosMsg, ContractError> {
+    match CW20_TOKEN.may_load(storage, tranche_id)? {
+        Some(token) => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount: Uint128::from(amount),
+            })?,
+            funds: vec![],
+        })),
+        None => Ok(CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins(amount, DENOM.load(storage)?),
+        })),
     }
-    let addr = UNLOCK_DISTRIBUTION_ADDRESS.load(storage)?;
-    let denom = DENOM.load(storage)?;
-    let msg = BankMsg::Send {
-        to_address: addr.to_string(),
-        amount: coins(amount, denom),
-    };
-    Ok(response.add_message(msg))
 }
 
 #[cfg(test)]
 mod tests {
     use cosmwasm_std::testing::{mock_dependencies, mock_env};
-    use cosmwasm_std::{Addr, Response};
+    use cosmwasm_std::{Addr, Response, Uint128};
 
-    use crate::state::{DENOM, UNLOCK_DISTRIBUTION_ADDRESS, VESTING_AMOUNTS, VESTING_TIMESTAMPS};
+    use crate::data_structure::{
+        LinearVestingState, ScheduleAmendment, ScheduleCursor, SchedulePoint, WeightedRecipient,
+    };
+    use crate::state::{
+        load_schedule, save_schedule, DEFAULT_TRANCHE_ID, DENOM, LINEAR_VESTING, SCHEDULE_CURSOR,
+        UNLOCK_DISTRIBUTION_ADDRESS, VESTING_SCHEDULE,
+    };
     use crate::ContractError;
 
-    use super::{collect_vested, distribute_vested};
+    use super::{
+        amend_schedule, clawback_after, clawback_amount, collect_vested, distribute_vested,
+        merge_schedule, next_unlock, split_by_weight, total_vested_amount,
+    };
 
     #[test]
     fn test_nothing_to_vest() {
         let mut deps = mock_dependencies();
         let deps_mut = deps.as_mut();
         let now = mock_env().block.time;
-        VESTING_TIMESTAMPS.save(deps_mut.storage, &vec![]).unwrap();
-        VESTING_AMOUNTS.save(deps_mut.storage, &vec![]).unwrap();
+        save_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID, &[], &[]).unwrap();
 
-        assert_eq!(0, collect_vested(deps_mut.storage, now, 0).unwrap());
+        assert_eq!(
+            0,
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, now, 0).unwrap()
+        );
         assert_eq!(
             ContractError::NoSufficientUnlockedTokens {},
-            collect_vested(deps_mut.storage, now, 10).expect_err("should error")
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, now, 10)
+                .expect_err("should error")
         );
     }
 
@@ -111,14 +699,24 @@ mod tests {
         let mut deps = mock_dependencies();
         let deps_mut = deps.as_mut();
         let now = mock_env().block.time;
-        VESTING_TIMESTAMPS
-            .save(deps_mut.storage, &vec![now])
-            .unwrap();
-        VESTING_AMOUNTS.save(deps_mut.storage, &vec![10]).unwrap();
+        save_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID, &[now], &[10]).unwrap();
 
-        assert_eq!(10, collect_vested(deps_mut.storage, now, 10).unwrap());
-        assert_eq!(VESTING_TIMESTAMPS.load(deps_mut.storage).unwrap(), vec![]);
-        assert_eq!(VESTING_AMOUNTS.load(deps_mut.storage).unwrap(), vec![]);
+        assert_eq!(
+            10,
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, now, 10).unwrap()
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
+            vec![]
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![]
+        );
     }
 
     #[test]
@@ -126,20 +724,25 @@ mod tests {
         let mut deps = mock_dependencies();
         let deps_mut = deps.as_mut();
         let now = mock_env().block.time;
-        VESTING_TIMESTAMPS
-            .save(deps_mut.storage, &vec![now])
-            .unwrap();
-        VESTING_AMOUNTS.save(deps_mut.storage, &vec![10]).unwrap();
+        save_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID, &[now], &[10]).unwrap();
 
         assert_eq!(
             ContractError::NoSufficientUnlockedTokens {},
-            collect_vested(deps_mut.storage, now, 15).expect_err("should error")
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, now, 15)
+                .expect_err("should error")
         );
         assert_eq!(
-            VESTING_TIMESTAMPS.load(deps_mut.storage).unwrap(),
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
             vec![now]
         );
-        assert_eq!(VESTING_AMOUNTS.load(deps_mut.storage).unwrap(), vec![10]);
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![10]
+        );
     }
 
     #[test]
@@ -147,17 +750,82 @@ mod tests {
         let mut deps = mock_dependencies();
         let deps_mut = deps.as_mut();
         let now = mock_env().block.time;
-        VESTING_TIMESTAMPS
-            .save(deps_mut.storage, &vec![now])
-            .unwrap();
-        VESTING_AMOUNTS.save(deps_mut.storage, &vec![10]).unwrap();
+        save_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID, &[now], &[10]).unwrap();
 
-        assert_eq!(5, collect_vested(deps_mut.storage, now, 5).unwrap());
         assert_eq!(
-            VESTING_TIMESTAMPS.load(deps_mut.storage).unwrap(),
+            5,
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, now, 5).unwrap()
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
             vec![now]
         );
-        assert_eq!(VESTING_AMOUNTS.load(deps_mut.storage).unwrap(), vec![5]);
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![5]
+        );
+    }
+
+    #[test]
+    fn test_vest_advances_cursor() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now, now, now.plus_seconds(1)],
+            &[10, 20, 30],
+        )
+        .unwrap();
+
+        assert_eq!(
+            15,
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, now, 15).unwrap()
+        );
+        // only the consumed entry is removed, the next one is marked as partially withdrawn
+        assert!(!VESTING_SCHEDULE.has(deps_mut.storage, (DEFAULT_TRANCHE_ID, 0)));
+        assert_eq!(
+            20,
+            VESTING_SCHEDULE
+                .load(deps_mut.storage, (DEFAULT_TRANCHE_ID, 1))
+                .unwrap()
+                .amount
+        );
+        assert_eq!(
+            ScheduleCursor {
+                first: 1,
+                end: 3,
+                withdrawn_from_first: 5,
+            },
+            SCHEDULE_CURSOR
+                .load(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
+        assert_eq!(
+            vec![15, 30],
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1
+        );
+
+        // rewriting the schedule keeps the indices moving forward
+        save_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID, &[now], &[15]).unwrap();
+        assert_eq!(
+            ScheduleCursor {
+                first: 1,
+                end: 2,
+                withdrawn_from_first: 0,
+            },
+            SCHEDULE_CURSOR
+                .load(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+        );
+        assert!(!VESTING_SCHEDULE.has(deps_mut.storage, (DEFAULT_TRANCHE_ID, 2)));
     }
 
     #[test]
@@ -165,20 +833,31 @@ mod tests {
         let mut deps = mock_dependencies();
         let deps_mut = deps.as_mut();
         let now = mock_env().block.time;
-        VESTING_TIMESTAMPS
-            .save(deps_mut.storage, &vec![now.plus_seconds(1)])
-            .unwrap();
-        VESTING_AMOUNTS.save(deps_mut.storage, &vec![10]).unwrap();
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now.plus_seconds(1)],
+            &[10],
+        )
+        .unwrap();
 
         assert_eq!(
             ContractError::NoSufficientUnlockedTokens {},
-            collect_vested(deps_mut.storage, now, 10).expect_err("should error")
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, now, 10)
+                .expect_err("should error")
         );
         assert_eq!(
-            VESTING_TIMESTAMPS.load(deps_mut.storage).unwrap(),
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
             vec![now.plus_seconds(1)]
         );
-        assert_eq!(VESTING_AMOUNTS.load(deps_mut.storage).unwrap(), vec![10]);
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![10]
+        );
     }
 
     #[test]
@@ -186,36 +865,45 @@ mod tests {
         let mut deps = mock_dependencies();
         let deps_mut = deps.as_mut();
         let now = mock_env().block.time;
-        VESTING_TIMESTAMPS
-            .save(
-                deps_mut.storage,
-                &vec![now.minus_seconds(1), now, now.plus_seconds(1)],
-            )
-            .unwrap();
-        VESTING_AMOUNTS
-            .save(deps_mut.storage, &vec![10, 9, 11])
-            .unwrap();
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now.minus_seconds(1), now, now.plus_seconds(1)],
+            &[10, 9, 11],
+        )
+        .unwrap();
 
-        assert_eq!(18, collect_vested(deps_mut.storage, now, 18).unwrap());
         assert_eq!(
-            VESTING_TIMESTAMPS.load(deps_mut.storage).unwrap(),
+            18,
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, now, 18).unwrap()
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
             vec![now, now.plus_seconds(1)]
         );
         assert_eq!(
-            VESTING_AMOUNTS.load(deps_mut.storage).unwrap(),
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
             vec![1u128, 11u128]
         );
 
         assert_eq!(
             2,
-            collect_vested(deps_mut.storage, now.plus_seconds(1), 2).unwrap()
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, now.plus_seconds(1), 2).unwrap()
         );
         assert_eq!(
-            VESTING_TIMESTAMPS.load(deps_mut.storage).unwrap(),
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
             vec![now.plus_seconds(1)]
         );
         assert_eq!(
-            VESTING_AMOUNTS.load(deps_mut.storage).unwrap(),
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
             vec![10u128]
         );
     }
@@ -225,55 +913,711 @@ mod tests {
         let mut deps = mock_dependencies();
         let deps_mut = deps.as_mut();
         let now = mock_env().block.time;
-        VESTING_TIMESTAMPS
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now.minus_seconds(2), now.minus_seconds(1), now],
+            &[10, 9, 11],
+        )
+        .unwrap();
+
+        assert_eq!(
+            30,
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, now, 30).unwrap()
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
+            vec![]
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_vest_multiple_none() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[
+                now.plus_seconds(1),
+                now.plus_seconds(2),
+                now.plus_seconds(3),
+            ],
+            &[10, 9, 11],
+        )
+        .unwrap();
+
+        assert_eq!(
+            ContractError::NoSufficientUnlockedTokens {},
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, now, 30)
+                .expect_err("should error")
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
+            vec![
+                now.plus_seconds(1),
+                now.plus_seconds(2),
+                now.plus_seconds(3)
+            ]
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![10, 9, 11]
+        );
+    }
+
+    #[test]
+    fn test_linear_vest_partial_segment() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now.plus_seconds(10), now.plus_seconds(20)],
+            &[100, 50],
+        )
+        .unwrap();
+        LINEAR_VESTING
             .save(
                 deps_mut.storage,
-                &vec![now.minus_seconds(2), now.minus_seconds(1), now],
+                DEFAULT_TRANCHE_ID,
+                &LinearVestingState {
+                    cliff: None,
+                    segment_start: SchedulePoint::Time(now),
+                    segment_amount: Uint128::new(100),
+                },
             )
             .unwrap();
-        VESTING_AMOUNTS
-            .save(deps_mut.storage, &vec![10, 9, 11])
-            .unwrap();
 
-        assert_eq!(30, collect_vested(deps_mut.storage, now, 30).unwrap());
-        assert_eq!(VESTING_TIMESTAMPS.load(deps_mut.storage).unwrap(), vec![]);
-        assert_eq!(VESTING_AMOUNTS.load(deps_mut.storage).unwrap(), vec![]);
+        let later = now.plus_seconds(4);
+        assert_eq!(
+            40,
+            total_vested_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, later).unwrap()
+        );
+        assert_eq!(
+            ContractError::NoSufficientUnlockedTokens {},
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, later, 41)
+                .expect_err("should error")
+        );
+        assert_eq!(
+            30,
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, later, 30).unwrap()
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![70, 50]
+        );
+        assert_eq!(
+            10,
+            total_vested_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, later).unwrap()
+        );
+        assert_eq!(
+            30,
+            total_vested_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, now.plus_seconds(6)).unwrap()
+        );
     }
 
     #[test]
-    fn test_vest_multiple_none() {
+    fn test_linear_vest_across_segments() {
         let mut deps = mock_dependencies();
         let deps_mut = deps.as_mut();
         let now = mock_env().block.time;
-        VESTING_TIMESTAMPS
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now.plus_seconds(10), now.plus_seconds(20)],
+            &[100, 50],
+        )
+        .unwrap();
+        LINEAR_VESTING
             .save(
                 deps_mut.storage,
-                &vec![
-                    now.plus_seconds(1),
-                    now.plus_seconds(2),
-                    now.plus_seconds(3),
-                ],
+                DEFAULT_TRANCHE_ID,
+                &LinearVestingState {
+                    cliff: None,
+                    segment_start: SchedulePoint::Time(now),
+                    segment_amount: Uint128::new(100),
+                },
             )
             .unwrap();
-        VESTING_AMOUNTS
-            .save(deps_mut.storage, &vec![10, 9, 11])
+
+        let later = now.plus_seconds(14);
+        assert_eq!(
+            120,
+            total_vested_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, later).unwrap()
+        );
+        assert_eq!(
+            110,
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, later, 110).unwrap()
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
+            vec![now.plus_seconds(20)]
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![40]
+        );
+        assert_eq!(
+            LINEAR_VESTING
+                .load(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap(),
+            LinearVestingState {
+                cliff: None,
+                segment_start: SchedulePoint::Time(now.plus_seconds(10)),
+                segment_amount: Uint128::new(50),
+            }
+        );
+        assert_eq!(
+            10,
+            total_vested_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, later).unwrap()
+        );
+        assert_eq!(
+            40,
+            collect_vested(
+                deps_mut.storage,
+                DEFAULT_TRANCHE_ID,
+                now.plus_seconds(20),
+                40
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_linear_vest_before_cliff() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now.plus_seconds(10)],
+            &[100],
+        )
+        .unwrap();
+        LINEAR_VESTING
+            .save(
+                deps_mut.storage,
+                DEFAULT_TRANCHE_ID,
+                &LinearVestingState {
+                    cliff: Some(SchedulePoint::Time(now.plus_seconds(5))),
+                    segment_start: SchedulePoint::Time(now),
+                    segment_amount: Uint128::new(100),
+                },
+            )
             .unwrap();
 
+        let before_cliff = now.plus_seconds(4);
+        assert_eq!(
+            0,
+            total_vested_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, before_cliff).unwrap()
+        );
         assert_eq!(
             ContractError::NoSufficientUnlockedTokens {},
-            collect_vested(deps_mut.storage, now, 30).expect_err("should error")
+            collect_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, before_cliff, 1)
+                .expect_err("should error")
+        );
+        assert_eq!(
+            50,
+            total_vested_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, now.plus_seconds(5)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_clawback_after() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now, now.plus_seconds(10), now.plus_seconds(20)],
+            &[10, 9, 11],
+        )
+        .unwrap();
+
+        assert_eq!(
+            ContractError::InvalidClawback("cutoff cannot be in the past".to_string()),
+            clawback_after(
+                deps_mut.storage,
+                DEFAULT_TRANCHE_ID,
+                now,
+                now.minus_seconds(1)
+            )
+            .expect_err("should error")
+        );
+        assert_eq!(
+            11,
+            clawback_after(
+                deps_mut.storage,
+                DEFAULT_TRANCHE_ID,
+                now,
+                now.plus_seconds(10)
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
+            vec![now, now.plus_seconds(10)]
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![10, 9]
+        );
+    }
+
+    #[test]
+    fn test_clawback_after_linear() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now.plus_seconds(10), now.plus_seconds(20)],
+            &[70, 50],
+        )
+        .unwrap();
+        LINEAR_VESTING
+            .save(
+                deps_mut.storage,
+                DEFAULT_TRANCHE_ID,
+                &LinearVestingState {
+                    cliff: None,
+                    segment_start: SchedulePoint::Time(now),
+                    segment_amount: Uint128::new(100),
+                },
+            )
+            .unwrap();
+
+        // 30 of the first segment has been withdrawn already. Another 30 streams until the cutoff.
+        let cutoff = now.plus_seconds(6);
+        assert_eq!(
+            90,
+            clawback_after(deps_mut.storage, DEFAULT_TRANCHE_ID, now, cutoff).unwrap()
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
+            vec![cutoff]
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![30]
+        );
+        assert_eq!(
+            10,
+            total_vested_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, now.plus_seconds(4)).unwrap()
+        );
+        assert_eq!(
+            30,
+            total_vested_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, cutoff).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_clawback_after_linear_before_cliff() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now.plus_seconds(10)],
+            &[100],
+        )
+        .unwrap();
+        LINEAR_VESTING
+            .save(
+                deps_mut.storage,
+                DEFAULT_TRANCHE_ID,
+                &LinearVestingState {
+                    cliff: Some(SchedulePoint::Time(now.plus_seconds(5))),
+                    segment_start: SchedulePoint::Time(now),
+                    segment_amount: Uint128::new(100),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            100,
+            clawback_after(
+                deps_mut.storage,
+                DEFAULT_TRANCHE_ID,
+                now,
+                now.plus_seconds(4)
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_clawback_amount() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now, now.plus_seconds(10), now.plus_seconds(20)],
+            &[10, 9, 11],
+        )
+        .unwrap();
+
+        assert_eq!(
+            ContractError::InvalidClawback("clawback amount exceeds unvested amount".to_string()),
+            clawback_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, now, 21)
+                .expect_err("should error")
+        );
+        assert_eq!(
+            15,
+            clawback_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, now, 15).unwrap()
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
+            vec![now, now.plus_seconds(10)]
         );
         assert_eq!(
-            VESTING_TIMESTAMPS.load(deps_mut.storage).unwrap(),
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![10, 5]
+        );
+    }
+
+    #[test]
+    fn test_clawback_amount_linear() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now.plus_seconds(10)],
+            &[100],
+        )
+        .unwrap();
+        LINEAR_VESTING
+            .save(
+                deps_mut.storage,
+                DEFAULT_TRANCHE_ID,
+                &LinearVestingState {
+                    cliff: None,
+                    segment_start: SchedulePoint::Time(now),
+                    segment_amount: Uint128::new(100),
+                },
+            )
+            .unwrap();
+
+        let later = now.plus_seconds(4);
+        assert_eq!(
+            ContractError::InvalidClawback("clawback amount exceeds unvested amount".to_string()),
+            clawback_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, later, 61)
+                .expect_err("should error")
+        );
+        assert_eq!(
+            50,
+            clawback_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, later, 50).unwrap()
+        );
+        // the remaining 50 keep streaming at the same rate and finish earlier
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
+            vec![now.plus_seconds(5)]
+        );
+        assert_eq!(
+            40,
+            total_vested_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, later).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_amend_schedule() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now, now.plus_seconds(10), now.plus_seconds(20)],
+            &[10, 9, 11],
+        )
+        .unwrap();
+
+        let mut amendment = ScheduleAmendment {
+            cutoff: now.plus_seconds(5),
+            vesting_timestamps: vec![now.plus_seconds(30), now.plus_seconds(40)],
+            vesting_amounts: vec![Uint128::new(10), Uint128::new(5)],
+            added_amount: Uint128::new(0),
+            clawed_back_amount: Uint128::new(0),
+            clawback_dst: None,
+        };
+        assert_eq!(
+            ContractError::InvalidAmendment(
+                "amended schedule must vest the 20 replaced tokens adjusted by the added and clawed back amounts, got 15"
+                    .to_string()
+            ),
+            amend_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID, now, &amendment)
+                .expect_err("should error")
+        );
+        amendment.clawed_back_amount = Uint128::new(5);
+        assert_eq!(
+            20,
+            amend_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID, now, &amendment).unwrap()
+        );
+        // the entry that had vested by the cutoff is left untouched
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
+            vec![now, now.plus_seconds(30), now.plus_seconds(40)]
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![10, 10, 5]
+        );
+    }
+
+    #[test]
+    fn test_amend_schedule_linear() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now.plus_seconds(10), now.plus_seconds(20)],
+            &[70, 50],
+        )
+        .unwrap();
+        LINEAR_VESTING
+            .save(
+                deps_mut.storage,
+                DEFAULT_TRANCHE_ID,
+                &LinearVestingState {
+                    cliff: None,
+                    segment_start: SchedulePoint::Time(now),
+                    segment_amount: Uint128::new(100),
+                },
+            )
+            .unwrap();
+
+        // 50 of the first entry streams by the cutoff, 30 of which has been withdrawn
+        let amendment = ScheduleAmendment {
+            cutoff: now.plus_seconds(5),
+            vesting_timestamps: vec![now.plus_seconds(15)],
+            vesting_amounts: vec![Uint128::new(100)],
+            added_amount: Uint128::new(0),
+            clawed_back_amount: Uint128::new(0),
+            clawback_dst: None,
+        };
+        assert_eq!(
+            100,
+            amend_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID, now, &amendment).unwrap()
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
+            vec![now.plus_seconds(5), now.plus_seconds(15)]
+        );
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![20, 100]
+        );
+        // the amended entry streams from the cutoff
+        assert_eq!(
+            70,
+            total_vested_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, now.plus_seconds(10))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_merge_schedule() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now.plus_seconds(10), now.plus_seconds(20)],
+            &[10, 20],
+        )
+        .unwrap();
+
+        merge_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            now,
+            &[
+                now.plus_seconds(5),
+                now.plus_seconds(20),
+                now.plus_seconds(30),
+            ],
+            &[1, 2, 3],
+        )
+        .unwrap();
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
             vec![
-                now.plus_seconds(1),
-                now.plus_seconds(2),
-                now.plus_seconds(3)
+                now.plus_seconds(5),
+                now.plus_seconds(10),
+                now.plus_seconds(20),
+                now.plus_seconds(30)
             ]
         );
         assert_eq!(
-            VESTING_AMOUNTS.load(deps_mut.storage).unwrap(),
-            vec![10, 9, 11]
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .1,
+            vec![1, 10, 22, 3]
+        );
+    }
+
+    #[test]
+    fn test_merge_schedule_linear() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now.plus_seconds(10)],
+            &[100],
+        )
+        .unwrap();
+        LINEAR_VESTING
+            .save(
+                deps_mut.storage,
+                DEFAULT_TRANCHE_ID,
+                &LinearVestingState {
+                    cliff: None,
+                    segment_start: SchedulePoint::Time(now),
+                    segment_amount: Uint128::new(100),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            ContractError::InvalidTranche(
+                "linearly vesting tranches can only be extended after their last vesting timestamp"
+                    .to_string()
+            ),
+            merge_schedule(
+                deps_mut.storage,
+                DEFAULT_TRANCHE_ID,
+                now,
+                &[now.plus_seconds(5)],
+                &[10]
+            )
+            .expect_err("should error")
+        );
+
+        // the existing entry has fully vested, so the new one streams from now on
+        let later = now.plus_seconds(20);
+        merge_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            later,
+            &[later.plus_seconds(10)],
+            &[10],
+        )
+        .unwrap();
+        assert_eq!(
+            load_schedule(deps_mut.storage, DEFAULT_TRANCHE_ID)
+                .unwrap()
+                .0,
+            vec![later, later.plus_seconds(10)]
+        );
+        assert_eq!(
+            105,
+            total_vested_amount(deps_mut.storage, DEFAULT_TRANCHE_ID, later.plus_seconds(5))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_next_unlock_linear() {
+        let mut deps = mock_dependencies();
+        let deps_mut = deps.as_mut();
+        let now = mock_env().block.time;
+        save_schedule(
+            deps_mut.storage,
+            DEFAULT_TRANCHE_ID,
+            &[now.plus_seconds(10), now.plus_seconds(20)],
+            &[100, 50],
+        )
+        .unwrap();
+        LINEAR_VESTING
+            .save(
+                deps_mut.storage,
+                DEFAULT_TRANCHE_ID,
+                &LinearVestingState {
+                    cliff: None,
+                    segment_start: SchedulePoint::Time(now),
+                    segment_amount: Uint128::new(100),
+                },
+            )
+            .unwrap();
+
+        // 40 of the first entry has streamed already
+        assert_eq!(
+            Some((now.plus_seconds(10), 60)),
+            next_unlock(deps_mut.storage, DEFAULT_TRANCHE_ID, now.plus_seconds(4)).unwrap()
+        );
+        assert_eq!(
+            None,
+            next_unlock(deps_mut.storage, DEFAULT_TRANCHE_ID, now.plus_seconds(20)).unwrap()
         );
     }
 
@@ -282,7 +1626,7 @@ mod tests {
         let deps = mock_dependencies();
         let deps_ref = deps.as_ref();
         let mut response = Response::new();
-        response = distribute_vested(deps_ref.storage, 0, response).unwrap();
+        response = distribute_vested(deps_ref.storage, DEFAULT_TRANCHE_ID, 0, response).unwrap();
         assert_eq!(response.messages.len(), 0);
     }
 
@@ -293,9 +1637,47 @@ mod tests {
         let mut response = Response::new();
         DENOM.save(deps_mut.storage, &"usei".to_string()).unwrap();
         UNLOCK_DISTRIBUTION_ADDRESS
-            .save(deps_mut.storage, &Addr::unchecked("unlock_address"))
+            .save(
+                deps_mut.storage,
+                DEFAULT_TRANCHE_ID,
+                &Addr::unchecked("unlock_address"),
+            )
             .unwrap();
-        response = distribute_vested(deps_mut.storage, 20, response).unwrap();
+        response = distribute_vested(deps_mut.storage, DEFAULT_TRANCHE_ID, 20, response).unwrap();
         assert_eq!(response.messages.len(), 1);
     }
+
+    #[test]
+    fn test_split_by_weight() {
+        let recipients = vec![
+            WeightedRecipient {
+                address: Addr::unchecked("a"),
+                weight: 1,
+            },
+            WeightedRecipient {
+                address: Addr::unchecked("b"),
+                weight: 1,
+            },
+            WeightedRecipient {
+                address: Addr::unchecked("c"),
+                weight: 1,
+            },
+        ];
+        assert_eq!(
+            vec![
+                (Addr::unchecked("a"), 4),
+                (Addr::unchecked("b"), 3),
+                (Addr::unchecked("c"), 3)
+            ],
+            split_by_weight(10, &recipients)
+        );
+        assert_eq!(
+            vec![
+                (Addr::unchecked("a"), 1),
+                (Addr::unchecked("b"), 0),
+                (Addr::unchecked("c"), 0)
+            ],
+            split_by_weight(1, &recipients)
+        );
+    }
 }